@@ -0,0 +1,77 @@
+use crate::{
+    scalar::Scalar,
+    types::{Quaternion, Vec3},
+};
+use serde::Deserialize;
+
+/// Error vector the Controller extracts from the error quaternion for the control law.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum ErrorRepresentation {
+    /// Vector part of q_error as computed (may unwind for errors over 180 deg)
+    VectorPart,
+    /// Vector part of the shortest rotation (q_error sign-corrected)
+    ShortestVectorPart,
+    /// Rotation axis times rotation angle [rad] of the shortest rotation
+    AxisAngle,
+    /// Modified Rodrigues parameters of the shortest rotation
+    Mrp,
+}
+
+/// Turns the raw error quaternion into the error the control law acts on.
+///
+/// q and -q describe the same attitude, so the sign of the scalar part selects which way round
+/// the satellite rotates. The sign is kept in memory and only switched once the scalar part
+/// crosses -hysteresis (Mayhew et al.), so that measurement noise around the 180 deg
+/// boundary does not make the command chatter between both directions.
+/// A hysteresis of 0 always picks the shortest rotation.
+pub struct AttitudeError<T: Scalar = f64> {
+    representation: ErrorRepresentation,
+    hysteresis: T,
+    // Sign applied to q_error (+1 or -1)
+    sign: T,
+}
+
+impl<T: Scalar> AttitudeError<T> {
+    pub fn new(representation: ErrorRepresentation, hysteresis: T) -> Self {
+        Self {
+            representation,
+            hysteresis,
+            sign: T::one(),
+        }
+    }
+
+    /// Returns the error quaternion (sign-corrected unless `VectorPart`) and the error vector.
+    pub fn compute(&mut self, q_error: Quaternion<T>) -> (Quaternion<T>, Vec3<T>) {
+        if let ErrorRepresentation::VectorPart = self.representation {
+            return (q_error, Vec3(q_error.v()));
+        }
+
+        let w = q_error.w();
+        if self.sign * w < -self.hysteresis {
+            self.sign = if w < T::zero() { -T::one() } else { T::one() };
+        }
+        let q = q_error.scale(self.sign);
+        let two = T::one() + T::one();
+
+        let error = match self.representation {
+            ErrorRepresentation::AxisAngle => {
+                let q = q.normalize();
+                let v = q.v();
+                let sin_half = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+                if sin_half > T::epsilon() {
+                    // With the memory sign the scalar part may be slightly negative (angle > pi)
+                    v * (two * sin_half.atan2(q.w()) / sin_half)
+                } else {
+                    // Small-angle limit: angle * axis = 2 * v
+                    v * two
+                }
+            }
+            ErrorRepresentation::Mrp => {
+                let q = q.normalize();
+                q.v() / (T::one() + q.w())
+            }
+            ErrorRepresentation::VectorPart | ErrorRepresentation::ShortestVectorPart => q.v(),
+        };
+        (q, Vec3(error))
+    }
+}
@@ -0,0 +1,23 @@
+use crate::{scalar::Scalar, types::Vec3};
+
+mod pd;
+
+pub use pd::PD;
+
+/// Data handed to a control law every time the `Controller` computes a new command.
+pub struct ControlInput<T: Scalar = f64> {
+    // Attitude error vector in the Controller's `ErrorRepresentation`
+    pub error: Vec3<T>,
+    // Measured angular velocity of the satellite [rad/s]
+    pub w: Vec3<T>,
+}
+
+/// A control law hosted by the `Controller` atomic.
+///
+/// The `Controller` owns the ports, computes the attitude error and saturates the
+/// returned torque to `max_torque_rw`, so a law only maps the error and rates to a torque.
+/// Laws compute in the scalar type `T` of the Controller.
+pub trait ControlLaw<T: Scalar = f64> {
+    /// Returns the (unsaturated) torque command [Nm].
+    fn compute(&mut self, input: &ControlInput<T>) -> Vec3<T>;
+}
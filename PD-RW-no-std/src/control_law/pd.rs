@@ -0,0 +1,23 @@
+use crate::{
+    control_law::{ControlInput, ControlLaw},
+    scalar::Scalar,
+    types::Vec3,
+};
+
+/// Proportional-derivative law: torque = -kp * error - kd * w
+pub struct PD<T: Scalar = f64> {
+    kp: T,
+    kd: T,
+}
+
+impl<T: Scalar> PD<T> {
+    pub fn new(kp: T, kd: T) -> Self {
+        Self { kp, kd }
+    }
+}
+
+impl<T: Scalar> ControlLaw<T> for PD<T> {
+    fn compute(&mut self, input: &ControlInput<T>) -> Vec3<T> {
+        Vec3(-(input.error.0 * self.kp) - input.w.0 * self.kd)
+    }
+}
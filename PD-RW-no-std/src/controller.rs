@@ -1,4 +1,9 @@
-use crate::types::{Quaternion, Vec3};
+use crate::{
+    attitude_error::AttitudeError,
+    control_law::{ControlInput, ControlLaw},
+    scalar::Scalar,
+    types::{Quaternion, Vec3},
+};
 use xdevs::*;
 
 pub struct ControllerState{
@@ -8,18 +13,20 @@ pub struct ControllerState{
     q_error: Option<Quaternion>,
     sigma: f64,
     time: f64,
-    q_target: Quaternion,
-    kp: f64,
-    kd: f64,
-    max_torque_rw: f64,
+    // Error quaternion, attitude error and control law, in the scalar type of the flight computer
+    computation: Box<dyn Computation>,
 }
 
 impl ControllerState{
-    pub fn new(
+    /// The Controller computes in the scalar type `T` of the law and attitude error, e.g. `f32`
+    /// or `Q16_16` on an MCU without double precision FPU. The measurements are rounded to `T`
+    /// when they arrive and the command is converted back for the simulated wheels, as the
+    /// drivers would do.
+    pub fn new<T: Scalar>(
         time: f64,
         q_target: Quaternion,
-        kp: f64,
-        kd: f64,
+        control_law: Box<dyn ControlLaw<T>>,
+        attitude_error: AttitudeError<T>,
         max_torque_rw: f64,
     ) -> Self {
         Self {
@@ -31,16 +38,52 @@ impl ControllerState{
             // Transition to Waiting state
             sigma: f64::INFINITY,
             time: time,
-            // # q_target is the desired attitude in quaternion form
-            q_target: q_target,
-            kp,
-            kd,
-            max_torque_rw: max_torque_rw,
+            computation: Box::new(ScalarComputation {
+                // # q_target is the desired attitude in quaternion form
+                q_target: q_target.cast(),
+                control_law,
+                attitude_error,
+                max_torque_rw: T::from_f64(max_torque_rw),
+            }),
         }
     }
-    // Calculates the error quaternion
-    fn quaternion_error(q_current: Quaternion, q_target: Quaternion) -> Quaternion {
-        Quaternion(q_current.0 * q_target.0.conjugate())
+}
+
+// Maps a measurement to the error quaternion and the saturated torque command
+trait Computation {
+    fn compute(&mut self, q: Quaternion, w: Vec3) -> (Quaternion, Vec3);
+}
+
+struct ScalarComputation<T: Scalar> {
+    q_target: Quaternion<T>,
+    control_law: Box<dyn ControlLaw<T>>,
+    attitude_error: AttitudeError<T>,
+    max_torque_rw: T,
+}
+
+impl<T: Scalar> ScalarComputation<T> {
+    // Calculates the error quaternion, i.e. the body attitude relative to the target frame
+    fn quaternion_error(q_current: Quaternion<T>, q_target: Quaternion<T>) -> Quaternion<T> {
+        q_target.conjugate().mul(&q_current)
+    }
+}
+
+impl<T: Scalar> Computation for ScalarComputation<T> {
+    fn compute(&mut self, q: Quaternion, w: Vec3) -> (Quaternion, Vec3) {
+        /*
+        1. Calculate attitude error quaternion (q_error = conjugate(q_target) * q_current)
+        2. Extract error vector in the configured representation (shortest rotation)
+         */
+        let q_error = Self::quaternion_error(q.cast(), self.q_target);
+        let (q_error, error) = self.attitude_error.compute(q_error);
+
+        // 3. Apply the control law:
+        let input = ControlInput { error, w: w.cast() };
+        let torque = self.control_law.compute(&input);
+
+        // Saturate the control torque
+        let torque = torque.clamp(-self.max_torque_rw, self.max_torque_rw);
+        (q_error.cast(), torque.cast())
     }
 }
 
@@ -75,26 +118,10 @@ impl Atomic for Controller{
             state.q = x.i_q.get_values().first().copied();
         }
 
-        if !state.w.is_none() && !state.q.is_none() {
-            /*
-            1. Calculate attitude error quaternion (q_error = q_current * conjugate(q_target))
-            2. Extract error vector (e.g., from the vector part of q_error)
-             */
-
-            if let Some(q) = state.q {
-                state.q_error = Some(ControllerState::quaternion_error(q, state.q_target));
-            }
-
-            // 3. Apply PD control law:
-            if let (Some(q_error), Some(w)) = (state.q_error.as_ref(), state.w.as_ref()) {
-                // imag() get the vector (x,y,z) (imaginary) part
-                state.torque = Some(Vec3(-state.kp * q_error.0.imag() - state.kd * w.0));
-            }
-
-            // Saturate the control torque
-            if let Some(torque) = &state.torque {
-                state.torque = Some(torque.clamp(-state.max_torque_rw, state.max_torque_rw));
-            }
+        if let (Some(q), Some(w)) = (state.q, state.w) {
+            let (q_error, torque) = state.computation.compute(q, w);
+            state.q_error = Some(q_error);
+            state.torque = Some(torque);
 
             // Schedule an immediate output
             state.sigma = state.time;
@@ -1,20 +1,27 @@
+mod attitude_error;
+mod control_law;
 mod controller;
 mod plotters;
+mod precision;
 mod rw;
 mod satellite_dynamics;
+mod scalar;
+mod scenario;
 mod transducer;
 mod types;
 
 use crate::{
+    attitude_error::AttitudeError,
+    control_law::PD,
     controller::{Controller, ControllerState},
+    precision::Run,
     rw::{RW, RWState},
     satellite_dynamics::{SatelliteDynamics, SatelliteDynamicsState},
+    scalar::{Q8_24, Q16_16, Scalar},
+    scenario::{LawConfig, Scenario},
     transducer::{SharedTransducerState, Transducer, TransducerState},
-    types::{Quaternion, Vec3},
 };
-use libm::{cos, sin};
-use nalgebra::{Matrix3, Vector3};
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fs, path::Path, process, rc::Rc};
 use xdevs::{
     component,
     simulator::{Config, Simulator},
@@ -43,52 +50,99 @@ component! {
 }
 
 fn main() {
-    let total_time = 100.0;
-    let h = 0.01;
-    let time = 0.;
-    let margin_ratio = 0.1;
-    // Target quaternion (identity orientation)
-    let q_target = Quaternion::default();
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "scenarios/default.toml".to_string());
+    let scenario = Scenario::load(Path::new(&path)).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    });
+    let total_time = scenario.simulation.total_time;
+    let directory = Path::new(&scenario.output.directory);
+    if scenario.output.plot {
+        fs::create_dir_all(directory).expect("Unable to create the output directory");
+    }
 
-    // Proportional gain
-    let kp = 0.01;
-    // Derivative gain
-    let kd = 0.1;
-    // Maximum torque of each reaction wheel [Nm]
-    let max_torque_rw = 0.001;
+    let reference = simulate(&scenario, flight_controller::<f64>(&scenario));
+    if scenario.output.plot {
+        plotters::draw(reference.clone(), total_time, directory);
+    }
 
-    // Initial conditions for the reaction wheels and satellite
-    let rw_speeds_initial = Vec3(Vector3::new(0.0, 0.0, 0.0));
-    // Inertia of each reaction wheel
-    let i_rw = Matrix3::from_diagonal(&Vector3::new(5.0e-5, 5.0e-5, 5.0e-5));
-    // Maximum angular speed of the reaction wheels [rad/s]
-    let max_speed_rw = 20.0;
-    let w0 = Vec3(Vector3::new(0.1, -0.1, 0.2));
-    let angle_initial = core::f64::consts::FRAC_PI_4;
-    let axis_initial = Vector3::new(1.0, 1.0, 1.0).normalize();
-    let w = cos(angle_initial / 2.0);
-    let v = axis_initial * sin(angle_initial / 2.0);
-    let q0 = Quaternion(nalgebra::Quaternion::new(w, v.x, v.y, v.z));
+    // Closed-loop trajectories with the arithmetic available on the flight MCU
+    let runs = [
+        Run::new("f64", &reference),
+        Run::new(
+            "f32",
+            &simulate(&scenario, flight_controller::<f32>(&scenario)),
+        ),
+        Run::new(
+            "Q8.24",
+            &simulate(&scenario, flight_controller::<Q8_24>(&scenario)),
+        ),
+        Run::new(
+            "Q16.16",
+            &simulate(&scenario, flight_controller::<Q16_16>(&scenario)),
+        ),
+    ];
+    for run in &runs {
+        println!(
+            "{}: final error {:.6} rad, max deviation from f64 {:.2e} rad",
+            run.label,
+            run.final_error(),
+            run.max_deviation(&runs[0])
+        );
+    }
+    if scenario.output.plot {
+        plotters::draw_precision(&runs, total_time, directory);
+    }
+}
 
-    /*
-    Nanosatellite Parameters (1U CubeSat)
-    Assuming a uniform mass distribution for a 1U CubeSat of 1.33 kg and 10cm side length
-    I = M * d^2 / 6
-    */
-    let i_sat = Matrix3::from_diagonal_element(1.33 * 0.1 * 0.1 / 6.0);
+// Controller of `scenario` computing in the scalar type `T` of the flight computer
+fn flight_controller<T: Scalar>(scenario: &Scenario) -> Controller {
+    let time = 0.;
+    let controller = &scenario.controller;
+    let attitude_error = AttitudeError::new(controller.error, T::from_f64(controller.hysteresis));
+    let LawConfig::PD { kp, kd } = controller.law;
+
+    Controller::new(ControllerState::new(
+        time,
+        scenario.guidance.q_target(),
+        Box::new(PD::new(T::from_f64(kp), T::from_f64(kd))),
+        attitude_error,
+        scenario.wheels.max_torque,
+    ))
+}
+
+// Simulates the satellite and reaction wheels of `scenario` in f64 under `controller`
+fn simulate(scenario: &Scenario, controller: Controller) -> SharedTransducerState {
+    let h = scenario.simulation.h;
+    let time = 0.;
 
-    let controller = Controller::new(ControllerState::new(time, q_target, kp, kd, max_torque_rw));
-    let rw = RW::new(RWState::new(time, rw_speeds_initial, i_rw, max_speed_rw, h));
-    let sd = SatelliteDynamics::new(SatelliteDynamicsState::new(time, w0, q0, h, i_sat));
-    let shared_state: SharedTransducerState =
-        Rc::new(RefCell::new(TransducerState::new(margin_ratio)));
+    let rw = RW::new(RWState::new(
+        time,
+        scenario.rw_speeds_initial(),
+        scenario.i_rw(),
+        scenario.wheels.max_speed,
+        h,
+    ));
+    let sd = SatelliteDynamics::new(SatelliteDynamicsState::new(
+        time,
+        scenario.w0(),
+        scenario.q0(),
+        h,
+        scenario.i_sat(),
+    ));
+    let shared_state: SharedTransducerState = Rc::new(RefCell::new(TransducerState::new(
+        scenario.output.margin_ratio,
+    )));
     let transducer = Transducer::new(shared_state.clone());
     let discrete_time_model = DiscreteTimeModel::new(controller, rw, transducer, sd);
 
     let mut simulator = Simulator::new(discrete_time_model);
 
-    let config = Config::new(0.0, total_time, h, None);
-    simulator.simulate_rt(&config, xdevs::simulator::std::sleep(&config), |_| {});
+    // Virtual time: the comparison runs every precision back to back
+    let config = Config::new(0.0, scenario.simulation.total_time, h, None);
+    simulator.simulate_vt(&config);
 
-    plotters::draw(shared_state, total_time);
+    shared_state
 }
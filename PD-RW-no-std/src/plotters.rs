@@ -4,21 +4,25 @@ use plotters::{
 };
 
 use crate::{
+    precision::Run,
     transducer::SharedTransducerState,
     types::{Quaternion, Vec3},
 };
+use std::path::Path;
 
-const OUT_FILE_NAME: &str = "images/Simulation Result.png";
+const OUT_FILE_NAME: &str = "Simulation Result.png";
+const PRECISION_FILE_NAME: &str = "Precision Comparison.png";
 
-pub fn draw(shared_transducer: SharedTransducerState, total_time: f64) {
+/// Plots the recorded histories into `OUT_FILE_NAME` inside the output `directory`
+pub fn draw(shared_transducer: SharedTransducerState, total_time: f64, directory: &Path) {
     let transducer = shared_transducer.borrow();
-    let root = BitMapBackend::new(OUT_FILE_NAME, (1600, 1200)).into_drawing_area();
+    let out_file = directory.join(OUT_FILE_NAME);
+    let root = BitMapBackend::new(&out_file, (1600, 1200)).into_drawing_area();
     root.fill(&WHITE).unwrap();
 
     let areas = root.split_evenly((3, 1));
     let ten_percent = total_time * 0.1;
     let max_x = total_time + ten_percent;
-    let dt = total_time / transducer.get_q_error_history().len() as f64;
     let q_error_range = transducer.get_q_error_range_with_margin();
     let w_history_range = transducer.get_w_history_range_with_margin();
     let rw_speeds_range = transducer.get_rw_speeds_range_with_margin();
@@ -31,7 +35,6 @@ pub fn draw(shared_transducer: SharedTransducerState, total_time: f64) {
         max_x,
         q_error_range.0,
         q_error_range.1,
-        dt,
     );
     draw_w_history(
         &areas[1],
@@ -40,7 +43,6 @@ pub fn draw(shared_transducer: SharedTransducerState, total_time: f64) {
         max_x,
         w_history_range.0,
         w_history_range.1,
-        dt,
     );
     draw_rw_speeds_history(
         &areas[2],
@@ -49,26 +51,94 @@ pub fn draw(shared_transducer: SharedTransducerState, total_time: f64) {
         max_x,
         rw_speeds_range.0,
         rw_speeds_range.1,
-        dt,
     );
 
     root.present().expect(
-        "Unable to write result to file, please make sure 'images' dir exists under current dir",
+        "Unable to write result to file, please make sure the output directory exists",
     );
     println!(
         "Nanosatellite Attitude Control Simulation has been saved to {}",
-        OUT_FILE_NAME
+        out_file.display()
     );
 }
 
+/// Plots the attitude error of each run and its deviation from the first one, the reference
+pub fn draw_precision(runs: &[Run], total_time: f64, directory: &Path) {
+    let colors = [BLACK, BLUE, RED, GREEN, MAGENTA, CYAN];
+    let out_file = directory.join(PRECISION_FILE_NAME);
+    let root = BitMapBackend::new(&out_file, (1600, 1200)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+
+    let areas = root.split_evenly((2, 1));
+    let ten_percent = total_time * 0.1;
+    let max_x = total_time + ten_percent;
+    let reference = &runs[0];
+    let deviations: Vec<Vec<(f64, f64)>> =
+        runs.iter().map(|run| run.deviation(reference)).collect();
+    let max_angle = runs
+        .iter()
+        .flat_map(|run| run.error_angle.iter().map(|(_, angle)| *angle))
+        .fold(0.0, f64::max);
+    let max_deviation = deviations
+        .iter()
+        .flatten()
+        .fold(f64::MIN_POSITIVE, |max, (_, d)| max.max(d.abs()));
+
+    let panels = [
+        (
+            "Attitude Error [rad]",
+            runs.iter().map(|run| run.error_angle.as_slice()).collect::<Vec<_>>(),
+            0.0,
+            max_angle * 1.1,
+        ),
+        (
+            "Deviation from f64 [rad]",
+            deviations.iter().map(|d| d.as_slice()).collect::<Vec<_>>(),
+            -max_deviation * 1.1,
+            max_deviation * 1.1,
+        ),
+    ];
+    for (i, (y_label, data, min_y, max_y)) in panels.into_iter().enumerate() {
+        let mut builder = ChartBuilder::on(&areas[i]);
+        builder
+            .set_label_area_size(LabelAreaPosition::Left, 80)
+            .set_label_area_size(LabelAreaPosition::Bottom, 40)
+            .margin(30);
+        if i == 0 {
+            builder.caption("Controller Precision Comparison", ("sans-serif", 40));
+        }
+        let mut ctx = builder
+            .build_cartesian_2d(-ten_percent..max_x, min_y..max_y)
+            .unwrap();
+
+        configure_mesh(&mut ctx, "Time [s]", y_label);
+
+        for ((run, values), color) in runs.iter().zip(data).zip(colors.iter().cycle()) {
+            ctx.draw_series(LineSeries::new(
+                values.iter().copied(),
+                ShapeStyle::from(color).stroke_width(2),
+            ))
+            .unwrap()
+            .label(run.label)
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+        }
+
+        draw_series_labels(&mut ctx);
+    }
+
+    root.present().expect(
+        "Unable to write result to file, please make sure the output directory exists",
+    );
+    println!("Precision comparison has been saved to {}", out_file.display());
+}
+
 fn draw_q_error(
     area: &DrawingArea<BitMapBackend, Shift>,
-    data: &[Quaternion],
+    data: &[(f64, Quaternion)],
     min_x: f64,
     max_x: f64,
     min_y: f64,
     max_y: f64,
-    dt: f64,
 ) {
     let mut ctx = ChartBuilder::on(area)
         .set_label_area_size(LabelAreaPosition::Left, 60)
@@ -91,9 +161,7 @@ fn draw_q_error(
 
     for (color, label, extractor) in series_data {
         ctx.draw_series(LineSeries::new(
-            data.iter()
-                .enumerate()
-                .map(|(i, q)| (i as f64 * dt, extractor(q))),
+            data.iter().map(|(t, q)| (*t, extractor(q))),
             ShapeStyle::from(color).stroke_width(2),
         ))
         .unwrap()
@@ -106,12 +174,11 @@ fn draw_q_error(
 
 fn draw_w_history(
     area: &DrawingArea<BitMapBackend, Shift>,
-    data: &[Vec3],
+    data: &[(f64, Vec3)],
     min_x: f64,
     max_x: f64,
     min_y: f64,
     max_y: f64,
-    dt: f64,
 ) {
     let mut ctx = ChartBuilder::on(area)
         .margin(30)
@@ -130,9 +197,7 @@ fn draw_w_history(
 
     for (color, label, extractor) in series_data {
         ctx.draw_series(LineSeries::new(
-            data.iter()
-                .enumerate()
-                .map(|(i, v)| (i as f64 * dt, extractor(v))),
+            data.iter().map(|(t, v)| (*t, extractor(v))),
             ShapeStyle::from(color).stroke_width(2),
         ))
         .unwrap()
@@ -145,12 +210,11 @@ fn draw_w_history(
 
 fn draw_rw_speeds_history(
     area: &DrawingArea<BitMapBackend, Shift>,
-    data: &[Vec3],
+    data: &[(f64, Vec3)],
     min_x: f64,
     max_x: f64,
     min_y: f64,
     max_y: f64,
-    dt: f64,
 ) {
     let mut ctx = ChartBuilder::on(area)
         .margin(30)
@@ -169,9 +233,7 @@ fn draw_rw_speeds_history(
 
     for (color, label, extractor) in series_data {
         ctx.draw_series(LineSeries::new(
-            data.iter()
-                .enumerate()
-                .map(|(i, v)| (i as f64 * dt, extractor(v))),
+            data.iter().map(|(t, v)| (*t, extractor(v))),
             ShapeStyle::from(color).stroke_width(2),
         ))
         .unwrap()
@@ -0,0 +1,48 @@
+use crate::transducer::SharedTransducerState;
+use libm::asin;
+
+/// Closed-loop response of the Controller computing in one scalar type.
+pub struct Run {
+    pub label: &'static str,
+    // Rotation angle of the error quaternion [rad] with the time of each sample [s]
+    pub error_angle: Vec<(f64, f64)>,
+}
+
+impl Run {
+    pub fn new(label: &'static str, transducer: &SharedTransducerState) -> Self {
+        let error_angle = transducer
+            .borrow()
+            .get_q_error_history()
+            .iter()
+            .map(|(t, q)| (*t, 2.0 * asin(q.0.imag().norm().min(1.0))))
+            .collect();
+        Self { label, error_angle }
+    }
+
+    pub fn final_error(&self) -> f64 {
+        self.error_angle.last().map_or(f64::NAN, |(_, angle)| *angle)
+    }
+
+    /// Largest difference with the attitude error angle of `reference` [rad]
+    pub fn max_deviation(&self, reference: &Run) -> f64 {
+        self.deviation(reference)
+            .iter()
+            .map(|(_, d)| d.abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// Difference with the attitude error angle of `reference` at each sample, against the last
+    /// sample of `reference` at or before it [rad]
+    pub fn deviation(&self, reference: &Run) -> Vec<(f64, f64)> {
+        let history = &reference.error_angle;
+        self.error_angle
+            .iter()
+            .filter_map(|(t, angle)| {
+                let i = history.partition_point(|(time, _)| time <= t);
+                history
+                    .get(i.checked_sub(1)?)
+                    .map(|(_, reference)| (*t, angle - reference))
+            })
+            .collect()
+    }
+}
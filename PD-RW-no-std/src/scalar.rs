@@ -0,0 +1,320 @@
+use core::{
+    fmt,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+use nalgebra::{ClosedAddAssign, ClosedDivAssign, ClosedMulAssign, ClosedSubAssign};
+
+/// Number type the Controller computes with, so the flight software can be simulated with the
+/// arithmetic of the target MCU (f64, f32 or fixed point).
+pub trait Scalar:
+    nalgebra::Scalar
+    + Copy
+    + PartialOrd
+    + ClosedAddAssign
+    + ClosedSubAssign
+    + ClosedMulAssign
+    + ClosedDivAssign
+    + Neg<Output = Self>
+{
+    /// Nearest representable value
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    /// Smallest x such that 1 + x != 1
+    fn epsilon() -> Self;
+    fn sqrt(self) -> Self;
+    /// Four-quadrant arctangent of self / x [rad]
+    fn atan2(self, x: Self) -> Self;
+
+    fn zero() -> Self {
+        Self::from_f64(0.0)
+    }
+
+    fn one() -> Self {
+        Self::from_f64(1.0)
+    }
+}
+
+impl Scalar for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn epsilon() -> Self {
+        f64::EPSILON
+    }
+
+    fn sqrt(self) -> Self {
+        libm::sqrt(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        libm::atan2(self, x)
+    }
+}
+
+impl Scalar for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn epsilon() -> Self {
+        f32::EPSILON
+    }
+
+    fn sqrt(self) -> Self {
+        libm::sqrtf(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        libm::atan2f(self, x)
+    }
+}
+
+/// Signed fixed-point number with `FRAC` fractional bits (1 to 30) in an `i32`, Q(31 - FRAC).FRAC.
+///
+/// Only integer instructions are used, as on an MCU without FPU. Products are rounded to
+/// nearest and every operation saturates instead of wrapping around. The square root is an
+/// integer Newton iteration and the arctangent a CORDIC rotation.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Default)]
+pub struct Fixed<const FRAC: u32>(pub i32);
+
+/// 16 integer and 16 fractional bits, resolution 1.5e-5
+#[allow(non_camel_case_types)]
+pub type Q16_16 = Fixed<16>;
+/// 8 integer and 24 fractional bits, resolution 6e-8, range +-128
+#[allow(non_camel_case_types)]
+pub type Q8_24 = Fixed<24>;
+
+// atan(2^-i) in Q1.30 for the CORDIC iterations
+const CORDIC_ANGLES: [i64; 31] = [
+    843314857, 497837829, 263043837, 133525159, 67021687, 33543516, 16775851, 8388437, 4194283,
+    2097149, 1048576, 524288, 262144, 131072, 65536, 32768, 16384, 8192, 4096, 2048, 1024, 512,
+    256, 128, 64, 32, 16, 8, 4, 2, 1,
+];
+// pi in Q2.30
+const PI_Q30: i64 = 3373259426;
+
+impl<const FRAC: u32> Fixed<FRAC> {
+    fn saturate(raw: i64) -> Self {
+        Fixed(raw.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+
+    // Converts a Q1.30 constant to FRAC fractional bits
+    fn from_q30(raw: i64) -> i64 {
+        raw >> (30 - FRAC)
+    }
+}
+
+impl<const FRAC: u32> Add for Fixed<FRAC> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Fixed(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl<const FRAC: u32> Sub for Fixed<FRAC> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Fixed(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl<const FRAC: u32> Mul for Fixed<FRAC> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let product = self.0 as i64 * rhs.0 as i64;
+        Self::saturate((product + (1 << (FRAC - 1))) >> FRAC)
+    }
+}
+
+impl<const FRAC: u32> Div for Fixed<FRAC> {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            return Self::saturate(if self.0 < 0 { i64::MIN } else { i64::MAX });
+        }
+        Self::saturate(((self.0 as i64) << FRAC) / rhs.0 as i64)
+    }
+}
+
+impl<const FRAC: u32> Neg for Fixed<FRAC> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Fixed(self.0.saturating_neg())
+    }
+}
+
+impl<const FRAC: u32> AddAssign for Fixed<FRAC> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const FRAC: u32> SubAssign for Fixed<FRAC> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const FRAC: u32> MulAssign for Fixed<FRAC> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const FRAC: u32> DivAssign for Fixed<FRAC> {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl<const FRAC: u32> fmt::Debug for Fixed<FRAC> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_f64())
+    }
+}
+
+impl<const FRAC: u32> Scalar for Fixed<FRAC> {
+    fn from_f64(value: f64) -> Self {
+        Self::saturate(libm::round(value * (1u64 << FRAC) as f64) as i64)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / (1u64 << FRAC) as f64
+    }
+
+    fn epsilon() -> Self {
+        Fixed(1)
+    }
+
+    fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Fixed(0);
+        }
+        // sqrt(raw / 2^FRAC) * 2^FRAC = sqrt(raw * 2^FRAC)
+        let n = (self.0 as u64) << FRAC;
+        let mut x = n;
+        let mut y = x.div_ceil(2);
+        while y < x {
+            x = y;
+            y = (x + n / x) / 2;
+        }
+        Self::saturate(x as i64)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        if self.0 == 0 && x.0 == 0 {
+            return Fixed(0);
+        }
+        // Vectoring mode: rotate (x, y) onto the positive x axis, accumulating the angle. The
+        // inputs are scaled up so that small vectors keep enough bits through the shifts.
+        let (mut x, mut y) = ((x.0 as i64) << 16, (self.0 as i64) << 16);
+        let mut angle = 0;
+        if x < 0 {
+            angle = if y < 0 { -PI_Q30 } else { PI_Q30 };
+            (x, y) = (-x, -y);
+        }
+        for (i, step) in CORDIC_ANGLES.iter().enumerate() {
+            let (dx, dy) = (y >> i, x >> i);
+            if y > 0 {
+                (x, y) = (x + dx, y - dy);
+                angle += step;
+            } else {
+                (x, y) = (x - dx, y + dy);
+                angle -= step;
+            }
+        }
+        Self::saturate(Self::from_q30(angle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: [f64; 7] = [-3.7, -1.25, -0.5, 0.3, 0.75, 2.6, 5.0];
+
+    // `actual` is within `lsb` units of the last place of `T`, at the scale of the operands, of
+    // the f64 result
+    fn assert_close<T: Scalar>(op: &str, actual: T, expected: f64, scale: f64, lsb: f64) {
+        let tolerance = lsb * T::epsilon().to_f64() * scale.max(1.0);
+        let error = (actual.to_f64() - expected).abs();
+        assert!(
+            error <= tolerance,
+            "{}: {:?} != {} (error {:e}, tolerance {:e})",
+            op,
+            actual,
+            expected,
+            error,
+            tolerance
+        );
+    }
+
+    // Checks every operation of `T` against f64, within `lsb` units of the last place. Divisors
+    // and atan2 arguments stay away from zero, where rounding the operands is amplified.
+    fn check_against_f64<T: Scalar>(lsb: f64) {
+        let t = T::from_f64;
+        for a in VALUES {
+            let scale = a.abs();
+            assert_close("from_f64", t(a), a, scale, lsb);
+            assert_close("neg", -t(a), -a, scale, lsb);
+            for b in VALUES {
+                let scale = a.abs().max(b.abs());
+                assert_close("add", t(a) + t(b), a + b, scale, lsb);
+                assert_close("sub", t(a) - t(b), a - b, scale, lsb);
+                assert_close("mul", t(a) * t(b), a * b, scale, lsb);
+                if b.abs() >= 0.5 {
+                    assert_close("div", t(a) / t(b), a / b, scale, lsb);
+                }
+                if a.hypot(b) >= 0.5 {
+                    assert_close("atan2", t(a).atan2(t(b)), a.atan2(b), 1.0, lsb);
+                }
+            }
+        }
+        for x in [0.25, 0.5, 1.0, 2.0, 10.0, 100.0] {
+            assert_close("sqrt", t(x).sqrt(), x.sqrt(), x.sqrt(), lsb);
+        }
+        for (y, x) in [(0.0, 1.0), (1.0, 0.0), (0.0, -1.0), (-1.0, 0.0)] {
+            assert_close("atan2", t(y).atan2(t(x)), f64::atan2(y, x), 1.0, lsb);
+        }
+    }
+
+    #[test]
+    fn f32_matches_f64() {
+        check_against_f64::<f32>(4.0);
+    }
+
+    #[test]
+    fn q8_24_matches_f64() {
+        check_against_f64::<Q8_24>(8.0);
+    }
+
+    #[test]
+    fn q16_16_matches_f64() {
+        check_against_f64::<Q16_16>(8.0);
+    }
+
+    #[test]
+    fn fixed_point_saturates() {
+        let (max, min) = (Q8_24::from_f64(128.0), Q8_24::from_f64(-129.0));
+        assert_eq!((max.0, min.0), (i32::MAX, i32::MIN));
+        let (big, two) = (Q8_24::from_f64(100.0), Q8_24::from_f64(2.0));
+        assert_eq!(big + big, max);
+        assert_eq!(-big - big, min);
+        assert_eq!(big * two, max);
+        assert_eq!(-big * two, min);
+        assert_eq!(two / Q8_24::zero(), max);
+        assert_eq!(-two / Q8_24::zero(), min);
+        assert_eq!(-min, max);
+        assert_eq!(Q8_24::from_f64(-4.0).sqrt(), Q8_24::zero());
+        assert_eq!(Q8_24::zero().atan2(Q8_24::zero()), Q8_24::zero());
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    attitude_error::ErrorRepresentation,
+    types::{Quaternion, Vec3},
+};
+use libm::{cos, sin};
+use nalgebra::{Matrix3, Vector3};
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+/// Simulation scenario: the spacecraft, its wheels, the flight controller, the initial state,
+/// the duration and the outputs of a run. Read from a TOML or JSON file, see
+/// `scenarios/default.toml` for the reference 1U CubeSat scenario.
+///
+/// The format is the one of the PD-RW simulator restricted to what this model implements: a PD
+/// law holding an inertial attitude, with every component running at the plant step.
+/// All quantities are in SI units and angles in radians. Vectors are `[x, y, z]`, matrices are
+/// written row by row and attitudes are either `{ axis = [x, y, z], angle = a }` or a quaternion
+/// `[w, x, y, z]` (body frame to inertial frame).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub simulation: Simulation,
+    pub spacecraft: Spacecraft,
+    pub wheels: Wheels,
+    pub controller: ControllerConfig,
+    pub guidance: GuidanceConfig,
+    pub initial_state: InitialState,
+    #[serde(default)]
+    pub output: Output,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Simulation {
+    // Plant integration step [s]
+    pub h: f64,
+    // Simulated time [s]
+    pub total_time: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spacecraft {
+    // Inertia tensor of the satellite in the body frame [kg m^2]
+    pub inertia: [[f64; 3]; 3],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wheels {
+    // Inertia of the x, y and z wheels about their spin axes [kg m^2]
+    pub inertia: [f64; 3],
+    // Maximum torque of each wheel [Nm]
+    pub max_torque: f64,
+    // Maximum angular speed of each wheel [rad/s]
+    pub max_speed: f64,
+    #[serde(default)]
+    pub initial_speeds: [f64; 3],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerConfig {
+    pub law: LawConfig,
+    #[serde(default = "default_error")]
+    pub error: ErrorRepresentation,
+    // Hysteresis on the sign of the error quaternion (0 always takes the shortest rotation)
+    #[serde(default)]
+    pub hysteresis: f64,
+}
+
+fn default_error() -> ErrorRepresentation {
+    ErrorRepresentation::ShortestVectorPart
+}
+
+/// Control law hosted by the Controller, selected by `type`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum LawConfig {
+    PD { kp: f64, kd: f64 },
+}
+
+/// Guidance law providing the target attitude, selected by `type`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum GuidanceConfig {
+    InertialHold {
+        #[serde(default)]
+        attitude: Attitude,
+    },
+}
+
+impl GuidanceConfig {
+    pub fn q_target(&self) -> Quaternion {
+        match self {
+            GuidanceConfig::InertialHold { attitude } => attitude.quaternion(),
+        }
+    }
+}
+
+/// Attitude given as a rotation or as the quaternion coefficients [w, x, y, z].
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(
+    untagged,
+    deny_unknown_fields,
+    expecting = "an attitude { axis = [x, y, z], angle = a } or a quaternion [w, x, y, z]"
+)]
+pub enum Attitude {
+    AxisAngle { axis: [f64; 3], angle: f64 },
+    Quaternion([f64; 4]),
+}
+
+impl Default for Attitude {
+    fn default() -> Self {
+        Attitude::Quaternion([1.0, 0.0, 0.0, 0.0])
+    }
+}
+
+impl Attitude {
+    pub fn quaternion(&self) -> Quaternion {
+        match *self {
+            Attitude::AxisAngle { axis, angle } => {
+                let axis = Vector3::from(axis).normalize();
+                let w = cos(angle / 2.0);
+                let v = axis * sin(angle / 2.0);
+                Quaternion(nalgebra::Quaternion::new(w, v.x, v.y, v.z))
+            }
+            Attitude::Quaternion([w, x, y, z]) => {
+                Quaternion(nalgebra::Quaternion::new(w, x, y, z).normalize())
+            }
+        }
+    }
+
+    fn validate(&self, field: &str) -> Result<(), ScenarioError> {
+        match *self {
+            Attitude::AxisAngle { axis, angle } => {
+                ensure(
+                    axis.iter().all(|c| c.is_finite()) && axis.iter().any(|c| *c != 0.0),
+                    &format!("{}.axis", field),
+                    "must be a non-zero vector",
+                )?;
+                ensure(
+                    angle.is_finite(),
+                    &format!("{}.angle", field),
+                    "must be a finite number",
+                )
+            }
+            Attitude::Quaternion(q) => ensure(
+                q.iter().all(|c| c.is_finite()) && q.iter().any(|c| *c != 0.0),
+                field,
+                "must be a non-zero quaternion",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InitialState {
+    // Angular velocity of the satellite [rad/s]
+    pub w: [f64; 3],
+    pub attitude: Attitude,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Output {
+    // Directory the plots are written to
+    #[serde(default = "default_directory")]
+    pub directory: String,
+    // Margin around the recorded ranges in the plots
+    #[serde(default = "default_margin_ratio")]
+    pub margin_ratio: f64,
+    #[serde(default = "default_plot")]
+    pub plot: bool,
+}
+
+fn default_directory() -> String {
+    "images".to_string()
+}
+
+fn default_margin_ratio() -> f64 {
+    0.1
+}
+
+fn default_plot() -> bool {
+    true
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            directory: default_directory(),
+            margin_ratio: default_margin_ratio(),
+            plot: default_plot(),
+        }
+    }
+}
+
+/// Scenario that cannot be read, with the path of the offending field (e.g.
+/// `controller.law.kp`).
+#[derive(Debug)]
+pub struct ScenarioError {
+    pub field: String,
+    pub reason: String,
+}
+
+impl ScenarioError {
+    fn new(field: &str, reason: impl Into<String>) -> Self {
+        ScenarioError {
+            field: field.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "{}: {}", self.field, self.reason)
+        }
+    }
+}
+
+impl Scenario {
+    /// Reads and validates a scenario, as TOML or JSON depending on the file extension
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let text = fs::read_to_string(path).map_err(|e| ScenarioError::new("", e.to_string()))?;
+        let scenario: Scenario = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => {
+                let deserializer = toml::Deserializer::parse(&text)
+                    .map_err(|e| ScenarioError::new("", e.to_string()))?;
+                serde_path_to_error::deserialize(deserializer)
+                    .map_err(|e| ScenarioError::new(&e.path().to_string(), e.inner().message()))?
+            }
+            Some("json") => {
+                let mut deserializer = serde_json::Deserializer::from_str(&text);
+                serde_path_to_error::deserialize(&mut deserializer)
+                    .map_err(|e| ScenarioError::new(&e.path().to_string(), e.inner().to_string()))?
+            }
+            _ => {
+                return Err(ScenarioError::new(
+                    "",
+                    "unknown scenario format, expected a .toml or .json file",
+                ));
+            }
+        };
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Checks every value is in range
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        positive("simulation.h", self.simulation.h)?;
+        positive("simulation.total_time", self.simulation.total_time)?;
+
+        let i_sat = self.i_sat();
+        ensure(
+            i_sat.iter().all(|c| c.is_finite()),
+            "spacecraft.inertia",
+            "must contain finite numbers",
+        )?;
+        ensure(
+            (i_sat - i_sat.transpose()).amax() <= 1e-12 * i_sat.amax(),
+            "spacecraft.inertia",
+            "must be symmetric",
+        )?;
+        ensure(
+            i_sat.cholesky().is_some(),
+            "spacecraft.inertia",
+            "must be positive definite",
+        )?;
+
+        let wheels = &self.wheels;
+        for (i, inertia) in wheels.inertia.iter().enumerate() {
+            positive(&format!("wheels.inertia[{}]", i), *inertia)?;
+        }
+        positive("wheels.max_torque", wheels.max_torque)?;
+        positive("wheels.max_speed", wheels.max_speed)?;
+        for (i, speed) in wheels.initial_speeds.iter().enumerate() {
+            ensure(
+                speed.abs() <= wheels.max_speed,
+                &format!("wheels.initial_speeds[{}]", i),
+                "must not exceed wheels.max_speed",
+            )?;
+        }
+
+        let LawConfig::PD { kp, kd } = self.controller.law;
+        positive("controller.law.kp", kp)?;
+        positive("controller.law.kd", kd)?;
+        ensure(
+            (0.0..1.0).contains(&self.controller.hysteresis),
+            "controller.hysteresis",
+            "must be in [0, 1)",
+        )?;
+
+        let GuidanceConfig::InertialHold { attitude } = &self.guidance;
+        attitude.validate("guidance.attitude")?;
+
+        for (i, w) in self.initial_state.w.iter().enumerate() {
+            ensure(
+                w.is_finite(),
+                &format!("initial_state.w[{}]", i),
+                "must be a finite number",
+            )?;
+        }
+        self.initial_state
+            .attitude
+            .validate("initial_state.attitude")?;
+
+        ensure(
+            !self.output.directory.is_empty(),
+            "output.directory",
+            "must not be empty",
+        )?;
+        ensure(
+            self.output.margin_ratio.is_finite() && self.output.margin_ratio >= 0.0,
+            "output.margin_ratio",
+            "must not be negative",
+        )
+    }
+
+    pub fn i_sat(&self) -> Matrix3<f64> {
+        Matrix3::from_row_slice(self.spacecraft.inertia.as_flattened())
+    }
+
+    pub fn i_rw(&self) -> Matrix3<f64> {
+        Matrix3::from_diagonal(&Vector3::from(self.wheels.inertia))
+    }
+
+    pub fn rw_speeds_initial(&self) -> Vec3 {
+        Vec3(Vector3::from(self.wheels.initial_speeds))
+    }
+
+    pub fn w0(&self) -> Vec3 {
+        Vec3(Vector3::from(self.initial_state.w))
+    }
+
+    pub fn q0(&self) -> Quaternion {
+        self.initial_state.attitude.quaternion()
+    }
+}
+
+fn ensure(condition: bool, field: &str, reason: &str) -> Result<(), ScenarioError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(ScenarioError::new(field, reason))
+    }
+}
+
+fn positive(field: &str, value: f64) -> Result<(), ScenarioError> {
+    ensure(value.is_finite() && value > 0.0, field, "must be positive")
+}
@@ -5,9 +5,12 @@ pub type SharedTransducerState = Rc<RefCell<TransducerState>>;
 
 pub struct TransducerState{
     sigma: f64,
-    q_error_history: Vec<Quaternion>,
-    w_history: Vec<Vec3>,
-    rw_speeds_history: Vec<Vec3>,
+    // Simulation time of the last transition, the simulation starts at 0 [s]
+    t: f64,
+    // Every sample is stored with the simulation time it was received at [s]
+    q_error_history: Vec<(f64, Quaternion)>,
+    w_history: Vec<(f64, Vec3)>,
+    rw_speeds_history: Vec<(f64, Vec3)>,
     q_error_range: (f64, f64),
     w_history_range: (f64, f64),
     rw_speeds_history_range: (f64, f64),
@@ -19,6 +22,7 @@ impl TransducerState{
         Self {
             // Transition to Waiting state
             sigma: f64::INFINITY,
+            t: 0.0,
             q_error_history: Vec::new(),
             w_history: Vec::new(),
             rw_speeds_history: Vec::new(),
@@ -29,15 +33,15 @@ impl TransducerState{
         }
     }
 
-    pub fn get_q_error_history(&self) -> &[Quaternion] {
+    pub fn get_q_error_history(&self) -> &[(f64, Quaternion)] {
         self.q_error_history.as_slice()
     }
 
-    pub fn get_w_history(&self) -> &[Vec3] {
+    pub fn get_w_history(&self) -> &[(f64, Vec3)] {
         self.w_history.as_slice()
     }
 
-    pub fn get_rw_speeds_history(&self) -> &[Vec3] {
+    pub fn get_rw_speeds_history(&self) -> &[(f64, Vec3)] {
         self.rw_speeds_history.as_slice()
     }
 
@@ -78,30 +82,34 @@ component!{
 
 impl Atomic for Transducer {
     fn delta_int(state: &mut Self::State) {
-        state.borrow_mut().sigma = f64::INFINITY;
+        let mut s = state.borrow_mut();
+        s.t += s.sigma;
+        s.sigma = f64::INFINITY;
     }
 
     fn delta_ext(state: &mut Self::State, e: f64, x: &Self::Input) {
         let mut s = state.borrow_mut();
         s.sigma -= e;
+        s.t += e;
+        let t = s.t;
 
         if !x.i_q_error.is_empty() {
             if let Some(q_error) = x.i_q_error.get_values().first().copied() {
-                s.q_error_history.push(q_error);
+                s.q_error_history.push((t, q_error));
                 let values = [q_error.0.i, q_error.0.j, q_error.0.k];
                 s.q_error_range = TransducerState::update_range(s.q_error_range, &values);
             }
         }
         if !x.i_w.is_empty() {
             if let Some(w) = x.i_w.get_values().first().copied() {
-                s.w_history.push(w);
+                s.w_history.push((t, w));
                 let values = [w.0.x, w.0.y, w.0.z];
                 s.w_history_range = TransducerState::update_range(s.w_history_range, &values);
             }
         }
         if !x.i_rw_speeds.is_empty() {
             if let Some(rw_speeds) = x.i_rw_speeds.get_values().first().copied() {
-                s.rw_speeds_history.push(rw_speeds);
+                s.rw_speeds_history.push((t, rw_speeds));
                 let values = [rw_speeds.0.x, rw_speeds.0.y, rw_speeds.0.z];
                 s.rw_speeds_history_range = TransducerState::update_range(s.rw_speeds_history_range, &values);
             }
@@ -1,10 +1,13 @@
-use nalgebra::{Quaternion as nalgebraQuaternion, Vector3};
+use crate::scalar::Scalar;
+use nalgebra::{Quaternion as nalgebraQuaternion, Vector3, Vector4};
 
+/// Three-component vector, in f64 for the simulated plant and in the flight computer's scalar
+/// type inside the Controller.
 #[derive(Debug, Clone, Copy)]
-pub struct Vec3(pub Vector3<f64>);
+pub struct Vec3<T: Scalar = f64>(pub Vector3<T>);
 
-impl Vec3 {
-    pub fn clamp(&self, min: f64, max: f64) -> Self {
+impl<T: Scalar> Vec3<T> {
+    pub fn clamp(&self, min: T, max: T) -> Self {
         let v = &self.0;
         Vec3(Vector3::new(
             clamp(v.x, min, max),
@@ -14,20 +17,84 @@ impl Vec3 {
     }
 
     pub fn default() -> Self {
-        Vec3(Vector3::zeros())
+        Vec3(Vector3::from_element(T::zero()))
+    }
+
+    /// Same vector rounded to the scalar type `U`
+    pub fn cast<U: Scalar>(&self) -> Vec3<U> {
+        Vec3(self.0.map(|x| U::from_f64(x.to_f64())))
     }
 }
 
+/// Attitude quaternion. nalgebra only provides the quaternion algebra for floating point, so
+/// the generic operations the Controller needs are implemented on the coefficients.
 #[derive(Debug, Clone, Copy)]
-pub struct Quaternion(pub nalgebraQuaternion<f64>);
+pub struct Quaternion<T: Scalar = f64>(pub nalgebraQuaternion<T>);
 
-impl Quaternion {
+impl<T: Scalar> Quaternion<T> {
     pub fn default() -> Self {
-        Quaternion(nalgebra::Quaternion::identity())
+        Quaternion::from_parts(T::one(), Vector3::from_element(T::zero()))
+    }
+
+    pub fn from_parts(w: T, v: Vector3<T>) -> Self {
+        Quaternion(nalgebraQuaternion {
+            coords: Vector4::new(v.x, v.y, v.z, w),
+        })
+    }
+
+    /// Scalar part
+    pub fn w(&self) -> T {
+        self.0.coords[3]
+    }
+
+    /// Vector part
+    pub fn v(&self) -> Vector3<T> {
+        self.0.coords.xyz()
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Quaternion::from_parts(self.w(), -self.v())
+    }
+
+    /// Hamilton product self * other, in the same order of operations as nalgebra
+    pub fn mul(&self, other: &Self) -> Self {
+        let (a, b) = (&self.0.coords, &other.0.coords);
+        Quaternion(nalgebraQuaternion {
+            coords: Vector4::new(
+                a[3] * b[0] + a[0] * b[3] + a[1] * b[2] - a[2] * b[1],
+                a[3] * b[1] - a[0] * b[2] + a[1] * b[3] + a[2] * b[0],
+                a[3] * b[2] + a[0] * b[1] - a[1] * b[0] + a[2] * b[3],
+                a[3] * b[3] - a[0] * b[0] - a[1] * b[1] - a[2] * b[2],
+            ),
+        })
+    }
+
+    pub fn scale(&self, factor: T) -> Self {
+        Quaternion(nalgebraQuaternion {
+            coords: self.0.coords * factor,
+        })
+    }
+
+    pub fn norm(&self) -> T {
+        let c = &self.0.coords;
+        (c[0] * c[0] + c[1] * c[1] + c[2] * c[2] + c[3] * c[3]).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        Quaternion(nalgebraQuaternion {
+            coords: self.0.coords / self.norm(),
+        })
+    }
+
+    /// Same quaternion rounded to the scalar type `U`
+    pub fn cast<U: Scalar>(&self) -> Quaternion<U> {
+        Quaternion(nalgebraQuaternion {
+            coords: self.0.coords.map(|x| U::from_f64(x.to_f64())),
+        })
     }
 }
 
-fn clamp(value: f64, min: f64, max: f64) -> f64 {
+fn clamp<T: Scalar>(value: T, min: T, max: T) -> T {
     if value < min {
         min
     } else if value > max {
@@ -0,0 +1,159 @@
+use crate::{
+    scenario::Override,
+    sweep::{Metric, SweepParameter},
+};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Attitude control simulator of a nanosatellite with reaction wheels.
+///
+/// Scenarios are TOML or JSON files (see scenarios/default.toml). Tables and summaries go to
+/// stdout, progress and errors to stderr. The exit code is 0 on success, 1 when a scenario or
+/// results file cannot be used or a run violates the requirements of its scenario and 2 on
+/// invalid arguments.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    #[command(flatten)]
+    pub options: Options,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Simulate a scenario, save its results and plot them, and check its requirements
+    Run {
+        scenario: PathBuf,
+        /// Pace the simulation with the wall clock, at SPEED simulated seconds per second
+        #[arg(
+            long,
+            value_name = "SPEED",
+            num_args = 0..=1,
+            default_missing_value = "1",
+            value_parser = parse_speed
+        )]
+        real_time: Option<f64>,
+    },
+    /// Simulate a scenario over a grid or a Latin hypercube of parameter values
+    Sweep {
+        scenario: PathBuf,
+        /// Field path of a parameter and its values, V1,V2,... or MIN:MAX:COUNT evenly spaced.
+        /// Repeat for each parameter, e.g. --param controller.law.kp=0.005:0.02:4
+        /// --param initial_state.w[0]=0,0.1,0.2
+        #[arg(long = "param", value_name = "PATH=VALUES", required = true)]
+        parameters: Vec<SweepParameter>,
+        /// Simulate N points of a Latin hypercube over MIN:MAX ranges instead of the full grid
+        #[arg(long, value_name = "N")]
+        latin_hypercube: Option<usize>,
+        /// Seed of the Latin hypercube
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Metrics plotted as heat maps over the first two parameters
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "final-error,settling-time,peak-wheel-speed"
+        )]
+        metrics: Vec<Metric>,
+        /// Number of points simulated at once, all the cores by default
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Simulate the dispersed runs of the `[monte_carlo]` table of a scenario in parallel
+    Montecarlo {
+        scenario: PathBuf,
+        /// Number of runs (monte_carlo.runs)
+        #[arg(long)]
+        runs: Option<usize>,
+        /// Seed of the dispersions, the same seed draws the same runs (monte_carlo.seed)
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Number of runs simulated at once, all the cores by default
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Tune the `[tuning]` parameters of a scenario, simulating it for every cost evaluation
+    Tune {
+        scenario: PathBuf,
+        /// Largest number of iterations of the search (tuning.max_iterations)
+        #[arg(long)]
+        max_iterations: Option<usize>,
+        /// Number of simulations run at once, all the cores by default
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Plot the results file saved by `run`
+    Plot { results: PathBuf },
+    /// Simulate two scenarios and plot their attitude errors together
+    Compare { a: PathBuf, b: PathBuf },
+    /// Compare the continuous design of a scenario's Compensator law with its discrete
+    /// equivalents on a slower controller
+    Discretization {
+        scenario: PathBuf,
+        /// Period of the discrete controller [s]
+        #[arg(long, value_name = "SECONDS", default_value_t = 0.08)]
+        period: f64,
+    },
+    /// Check scenarios without simulating them
+    Validate {
+        #[arg(required = true)]
+        scenarios: Vec<PathBuf>,
+    },
+}
+
+/// Replacements of scenario values, for every scenario a command reads
+#[derive(Debug, Args)]
+pub struct Options {
+    /// Directory the results and plots are written to (output.directory)
+    #[arg(long, short, global = true, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+    /// Simulated time [s] (simulation.total_time)
+    #[arg(long, short, global = true, value_name = "SECONDS")]
+    pub duration: Option<f64>,
+    /// Plant integration step [s] (simulation.h)
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub step: Option<f64>,
+    /// Do not draw the plots (output.plot)
+    #[arg(long, global = true)]
+    pub no_plot: bool,
+    /// Replace any scenario value, e.g. --set controller.law.kp=0.02 or --set modes.initial=Safe
+    #[arg(long = "set", global = true, value_name = "PATH=VALUE")]
+    pub overrides: Vec<Override>,
+}
+
+impl Options {
+    /// Every replacement, the `--set` ones last so they take precedence
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = Vec::new();
+        if let Some(directory) = &self.output_dir {
+            overrides.push(Override {
+                path: "output.directory".to_string(),
+                value: directory.to_string_lossy().into(),
+            });
+        }
+        if let Some(duration) = self.duration {
+            overrides.push(Override::new(
+                "simulation.total_time",
+                &duration.to_string(),
+            ));
+        }
+        if let Some(step) = self.step {
+            overrides.push(Override::new("simulation.h", &step.to_string()));
+        }
+        if self.no_plot {
+            overrides.push(Override::new("output.plot", "false"));
+        }
+        overrides.extend(self.overrides.iter().cloned());
+        overrides
+    }
+}
+
+// Pace of --real-time, a finite number above 0
+fn parse_speed(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        Ok(_) => Err("must be a finite number above 0".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
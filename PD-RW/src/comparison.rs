@@ -0,0 +1,132 @@
+use crate::{
+    discrete_time_model::{
+        DiscreteTimeModel, Rates,
+        control_law::{
+            Compensator,
+            discretization::{Discretization, TransferFunction},
+        },
+        recorder::recordings,
+    },
+    metrics::Metrics,
+    results::Results,
+    scenario::{Scenario, ScenarioError},
+};
+use std::fmt;
+use xdevs::simulation::*;
+
+/// Closed-loop attitude error of one run, with the figures saved in its metrics file.
+pub struct Response {
+    pub label: String,
+    // Rotation angle of the error quaternion [rad] with the time of each sample [s]
+    pub error_angle: Vec<(f64, f64)>,
+    pub metrics: Metrics,
+}
+
+impl Response {
+    /// Attitude error of a run of `scenario`
+    pub fn new(label: String, results: &Results, scenario: &Scenario) -> Self {
+        Response {
+            label,
+            error_angle: results.error_angle(),
+            metrics: Metrics::new(results, scenario),
+        }
+    }
+
+    /// Attitude error at the end of the run [rad]
+    pub fn final_error(&self) -> f64 {
+        self.metrics.final_error_deg.to_radians()
+    }
+
+    /// See `Metrics::settling_time`
+    pub fn settling_time(&self) -> Option<f64> {
+        self.metrics.settling_time
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let final_error = self.final_error();
+        match self.settling_time() {
+            Some(time) => write!(
+                f,
+                "{}: final error {:.4} rad, settled after {:.1} s",
+                self.label, final_error, time
+            ),
+            None => write!(
+                f,
+                "{}: final error {:.4} rad, not settled",
+                self.label, final_error
+            ),
+        }
+    }
+}
+
+/// Runs `scenario` with the continuous design c_error(s), c_rate(s) (see `Compensator`) in
+/// place of its control law and with its discrete equivalents for a controller running every
+/// `period` seconds. The continuous reference is the Tustin equivalent at the plant step, where
+/// all methods coincide with the continuous law.
+pub fn compare_discretizations(
+    scenario: &Scenario,
+    c_error: &TransferFunction,
+    c_rate: &TransferFunction,
+    period: f64,
+) -> Result<Vec<Response>, ScenarioError> {
+    let h = scenario.simulation.h;
+    let mut responses = vec![simulate(
+        scenario,
+        "Continuous".to_string(),
+        Compensator::from_continuous(c_error, c_rate, Discretization::Tustin, h),
+        h,
+    )?];
+    for method in [
+        Discretization::ZeroOrderHold,
+        Discretization::Tustin,
+        Discretization::MatchedPoleZero,
+    ] {
+        responses.push(simulate(
+            scenario,
+            format!("{:?} at {} s", method, period),
+            Compensator::from_continuous(c_error, c_rate, method, period),
+            period,
+        )?);
+    }
+    Ok(responses)
+}
+
+fn simulate(
+    scenario: &Scenario,
+    label: String,
+    compensator: Compensator,
+    period: f64,
+) -> Result<Response, ScenarioError> {
+    // Only the control law changes between runs: no modes and no faults
+    let mut options = scenario.model_options()?;
+    options.modes = None;
+    options.faults.clear();
+    options.fdir = false;
+    // The whole on-board loop runs at the controller period
+    options.rates = Some(Rates {
+        sensor_period: period,
+        control_period: period,
+        actuator_period: period,
+        control_delay: 0.0,
+    });
+    let model = DiscreteTimeModel::new(
+        "DiscreteTimeModel",
+        scenario.simulation.h,
+        Box::new(compensator),
+        scenario.guidance_law(&scenario.guidance, "guidance")?,
+        options,
+    );
+    let mut simulator = RootCoordinator::new(model.coupled);
+    simulator.simulate(scenario.simulation.total_time);
+
+    let transducer = unsafe { &*model.transducer_ref };
+    let results = Results::new(
+        transducer,
+        recordings(&model.recorders),
+        scenario.simulation.total_time,
+        scenario.output.margin_ratio,
+    );
+    Ok(Response::new(label, &results, scenario))
+}
@@ -1,88 +1,323 @@
-use crate::discrete_time_model::types::{Quaternion, Vec3};
-use nalgebra::{Matrix3, Vector3};
-use xdevs::modeling::*;
+use crate::discrete_time_model::types::{Fault, Mat3, Mode, Quaternion, Vec3};
+use nalgebra::Matrix3;
+use xdevs::{modeling::*, simulation::Simulator};
 
+pub mod attitude_error;
+pub mod control_law;
 mod controller;
+pub mod fdir;
+pub mod guidance;
+pub mod mode_manager;
+pub mod recorder;
 mod rw;
 mod satellite_dynamics;
+mod sensor;
 pub(crate) mod transducer;
 pub mod types;
 
+use attitude_error::AttitudeError;
+use control_law::ControlLaw;
 use controller::Controller;
+use fdir::{Fdir, FdirConfig, Injection};
+use guidance::{Guidance, GuidanceLaw};
+use mode_manager::{ModeCriteria, ModeManager};
+use recorder::{
+    OutputPorts, Outputs, PortType, Probe, ProbeError, Recordable, Recorder, RecorderConfig,
+};
+pub use rw::{WheelFault, WheelFaultKind};
 use rw::RW;
 use satellite_dynamics::SatelliteDynamics;
+use sensor::Sensor;
+pub use sensor::SensorNoise;
 use transducer::Transducer;
 
+/// Operating modes of the ADCS and the laws each one activates.
+pub struct ModeSetup {
+    pub initial_mode: Mode,
+    // Transition thresholds (None uses `ModeCriteria::new` for the scenario's wheels)
+    pub criteria: Option<ModeCriteria>,
+    // Per-mode laws, modes without one use the default control and guidance laws
+    pub control_laws: Vec<(Mode, Box<dyn ControlLaw>)>,
+    pub guidance_laws: Vec<(Mode, Box<dyn GuidanceLaw>)>,
+}
+
+/// Recorder coupled to the output `port` of `component`, named `name`.
+pub struct ProbeSetup {
+    pub name: String,
+    pub component: String,
+    pub port: String,
+    pub config: RecorderConfig,
+}
+
+/// Execution rates of the on-board loop. Periods should be multiples of the plant step `h`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rates {
+    // Sampling period of the attitude and rate sensors [s]
+    pub sensor_period: f64,
+    // Period of the control law [s]
+    pub control_period: f64,
+    // Period at which the wheels latch a new torque command [s]
+    pub actuator_period: f64,
+    // Delay between a measurement and the command computed from it [s]
+    pub control_delay: f64,
+}
+
+impl Rates {
+    /// Every part of the loop runs at the plant step, with no delay
+    pub fn single(h: f64) -> Self {
+        Rates {
+            sensor_period: h,
+            control_period: h,
+            actuator_period: h,
+            control_delay: 0.0,
+        }
+    }
+}
+
+/// Physical parameters and initial state of the satellite and its reaction wheels.
+#[derive(Debug, Clone, Copy)]
+pub struct Plant {
+    // Inertia of the satellite [kg m^2]
+    pub i_sat: Matrix3<f64>,
+    // Inertia of each reaction wheel [kg m^2]
+    pub i_rw: Matrix3<f64>,
+    // Maximum torque of each reaction wheel [Nm]
+    pub max_torque_rw: f64,
+    // Maximum angular speed of the reaction wheels [rad/s]
+    pub max_speed_rw: f64,
+    pub rw_speeds_initial: Vec3,
+    // Initial angular velocity [rad/s] and attitude of the satellite
+    pub w0: Vec3,
+    pub q0: Quaternion,
+}
+
+/// Parameters of the model and optional parts on top of the basic control loop.
+pub struct ModelOptions {
+    pub plant: Plant,
+    // Error the control law acts on
+    pub attitude_error: AttitudeError,
+    // Adds the Controller feedforward (see `Controller::with_feedforward`) for the plant inertia
+    pub feedforward: bool,
+    // Loop rates (None runs everything at the plant step)
+    pub rates: Option<Rates>,
+    // Measurement noise (None measures the true state)
+    pub sensor_noise: Option<SensorNoise>,
+    pub modes: Option<ModeSetup>,
+    // Faults injected into the wheels and sensors
+    pub faults: Vec<Injection>,
+    // Adds the FDIR component, with the thresholds of `FdirConfig::new` for the scenario
+    pub fdir: bool,
+}
+
 pub struct DiscreteTimeModel {
     pub(crate) coupled: Coupled,
     pub transducer_ref: *const Transducer,
+    // Recorders added by `add_probe`, readable as long as the coupled model is alive
+    pub recorders: Vec<*const dyn Probe>,
+    // Output ports of each component, empty for the ones without outputs
+    outputs: Vec<(String, OutputPorts)>,
 }
 
 impl DiscreteTimeModel {
-    pub fn new(name: &str, h: Option<f64>) -> Self {
+    /// Builds the model for the plant of `options`, with its optional components: a `ModeManager`
+    /// switching the laws of the Controller and the Guidance (e.g. to simulate the
+    /// post-deployment sequence from detumbling onwards), injected faults and the FDIR.
+    pub fn new(
+        name: &str,
+        h: f64,
+        control_law: Box<dyn ControlLaw>,
+        guidance_law: Box<dyn GuidanceLaw>,
+        options: ModelOptions,
+    ) -> Self {
         let mut coupled = Coupled::new(name);
-        let h = h.unwrap_or(0.01);
         let time = 0.;
-        let margin_ratio = 0.1;
-        // Target quaternion (identity orientation)
-        let q_target = Quaternion::default();
-
-        // Proportional gain
-        let kp = 0.01;
-        // Derivative gain
-        let kd = 0.1;
-        // Maximum torque of each reaction wheel [Nm]
-        let max_torque_rw = 0.001;
-
-        // Initial conditions for the reaction wheels and satellite
-        let rw_speeds_initial = Vec3(Vector3::new(0.0, 0.0, 0.0));
-        // Inertia of each reaction wheel
-        let i_rw = Matrix3::from_diagonal(&Vector3::new(5.0e-5, 5.0e-5, 5.0e-5));
-        // Maximum angular speed of the reaction wheels [rad/s]
-        let max_speed_rw = 20.0;
-        let w0 = Vec3(Vector3::new(0.1, -0.1, 0.2));
-        let angle_initial = std::f64::consts::FRAC_PI_4;
-        let axis_initial = Vector3::new(1.0, 1.0, 1.0).normalize();
-        let w = (angle_initial / 2.0).cos();
-        let v = axis_initial * (angle_initial / 2.0).sin();
-        let q0 = Quaternion(nalgebra::Quaternion::new(w, v.x, v.y, v.z));
-
-        /*
-        Nanosatellite Parameters (1U CubeSat)
-        Assuming a uniform mass distribution for a 1U CubeSat of 1.33 kg and 10cm side length
-        I = M * d^2 / 6
-        */
-        let i_sat = Matrix3::from_diagonal_element(1.33 * 0.1 * 0.1 / 6.0);
+        let Plant {
+            i_sat,
+            i_rw,
+            max_torque_rw,
+            max_speed_rw,
+            rw_speeds_initial,
+            w0,
+            q0,
+        } = options.plant;
+        // Initial target quaternion, updated by the Guidance during the run
+        let q_target = guidance_law.attitude(time);
 
         // Instantiate components
-        let controller = Controller::new("Controller", time, q_target, kp, kd, max_torque_rw);
-        let rw = RW::new("ReationWheels", time, rw_speeds_initial, i_rw, max_speed_rw, h,);
+        let mut controller = Controller::new(
+            "Controller",
+            time,
+            q_target,
+            control_law,
+            options.attitude_error,
+            max_torque_rw,
+        );
+        if options.feedforward {
+            controller = controller.with_feedforward(i_sat);
+        }
+        let mut rw = RW::new("ReationWheels", time, rw_speeds_initial, i_rw, max_speed_rw, h,);
+        let mut sensor = Sensor::new("Sensor");
+        let rates = options.rates.unwrap_or(Rates::single(h));
+        controller = controller.with_timing(rates.control_period, rates.control_delay);
+        rw = rw.with_actuator_period(rates.actuator_period);
+        sensor = sensor.with_period(rates.sensor_period);
+        if let Some(noise) = options.sensor_noise {
+            sensor = sensor.with_noise(noise);
+        }
+        for fault in &options.faults {
+            match *fault {
+                Injection::Wheel(fault) => rw = rw.with_fault(fault),
+                Injection::SensorDropout { start, end } => sensor = sensor.with_dropout(start, end),
+                Injection::AttitudeGlitch(time) => sensor = sensor.with_attitude_glitch(time),
+            }
+        }
         let sd = SatelliteDynamics::new("SatelliteDynamics", time, w0, q0, h, i_sat);
-        let transducer = Box::new(Transducer::new("Transducer", margin_ratio));
+        let mut guidance = Guidance::new("Guidance", time, guidance_law, h);
+        let mut mode_manager = None;
+        if let Some(modes) = options.modes {
+            for (mode, law) in modes.control_laws {
+                controller = controller.with_mode_law(mode, law);
+            }
+            for (mode, law) in modes.guidance_laws {
+                guidance = guidance.with_mode_law(mode, law);
+            }
+            let criteria = modes.criteria.unwrap_or(ModeCriteria::new(max_speed_rw));
+            mode_manager = Some(ModeManager::new("ModeManager", modes.initial_mode, criteria));
+        }
+        let transducer = Box::new(Transducer::new("Transducer"));
         let transducer_ptr: *const Transducer = &*transducer;
 
         // Add components to model
-        coupled.add_component(Box::new(controller));
-        coupled.add_component(Box::new(rw));
-        coupled.add_component(Box::new(sd));
+        let mut outputs = Vec::new();
+        add_component(&mut coupled, &mut outputs, controller);
+        add_component(&mut coupled, &mut outputs, rw);
+        add_component(&mut coupled, &mut outputs, sd);
+        add_component(&mut coupled, &mut outputs, sensor);
+        add_component(&mut coupled, &mut outputs, guidance);
         coupled.add_component(transducer);
+        outputs.push(("Transducer".to_string(), OutputPorts::default()));
 
         // Connect components
         coupled.add_ic("Controller", "o_torque", "ReationWheels", "i_torque");
-        coupled.add_ic("Controller", "o_torque", "SatelliteDynamics", "i_torque");
         coupled.add_ic("Controller", "o_q_error", "Transducer", "i_qerror");
+        coupled.add_ic("Controller", "o_integrator", "Transducer", "i_integrator");
+        coupled.add_ic("Controller", "o_sliding", "Transducer", "i_sliding");
+        coupled.add_ic("Controller", "o_inertia", "Transducer", "i_inertia");
 
         coupled.add_ic("ReationWheels", "o_h_rw", "SatelliteDynamics", "i_h_rw");
+        coupled.add_ic("ReationWheels", "o_h_rw", "Controller", "i_h_rw");
         coupled.add_ic("ReationWheels", "o_rw_speeds", "Transducer", "i_rw_speeds");
+        // The body feels the torque the wheels deliver, not the command
+        coupled.add_ic("ReationWheels", "o_torque", "SatelliteDynamics", "i_torque");
+        coupled.add_ic("ReationWheels", "o_torque", "Transducer", "i_torque");
 
-        coupled.add_ic("SatelliteDynamics", "o_w", "Controller", "i_w");
-        coupled.add_ic("SatelliteDynamics", "o_q", "Controller", "i_q");
+        // The on-board components see the measurements, the Transducer the true state
+        coupled.add_ic("SatelliteDynamics", "o_w", "Sensor", "i_w");
+        coupled.add_ic("SatelliteDynamics", "o_q", "Sensor", "i_q");
         coupled.add_ic("SatelliteDynamics", "o_w", "Transducer", "i_w");
+        coupled.add_ic("SatelliteDynamics", "o_q", "Transducer", "i_q");
+        coupled.add_ic("Sensor", "o_w", "Controller", "i_w");
+        coupled.add_ic("Sensor", "o_q", "Controller", "i_q");
+
+        coupled.add_ic("Guidance", "o_q_target", "Controller", "i_q_target");
+        coupled.add_ic("Guidance", "o_w_ref", "Controller", "i_w_ref");
+        coupled.add_ic("Guidance", "o_w_ref_dot", "Controller", "i_w_ref_dot");
+        coupled.add_ic("Guidance", "o_q_target", "Transducer", "i_q_target");
+
+        let has_mode_manager = mode_manager.is_some();
+        if let Some(mode_manager) = mode_manager {
+            add_component(&mut coupled, &mut outputs, mode_manager);
+            coupled.add_ic("Sensor", "o_w", "ModeManager", "i_w");
+            coupled.add_ic("ReationWheels", "o_rw_speeds", "ModeManager", "i_rw_speeds");
+            coupled.add_ic("Controller", "o_q_error", "ModeManager", "i_q_error");
+            coupled.add_ic("ModeManager", "o_mode", "Controller", "i_mode");
+            coupled.add_ic("ModeManager", "o_mode", "Guidance", "i_mode");
+            coupled.add_ic("ModeManager", "o_mode", "Transducer", "i_mode");
+        }
+
+        if options.fdir {
+            let config = FdirConfig::new(i_rw.diagonal(), max_speed_rw, rates.sensor_period);
+            add_component(&mut coupled, &mut outputs, Fdir::new("FDIR", config));
+            coupled.add_ic("Controller", "o_torque", "FDIR", "i_torque");
+            coupled.add_ic("ReationWheels", "o_rw_speeds", "FDIR", "i_rw_speeds");
+            coupled.add_ic("Sensor", "o_w", "FDIR", "i_w");
+            coupled.add_ic("Sensor", "o_q", "FDIR", "i_q");
+            coupled.add_ic("FDIR", "o_fault", "Controller", "i_fault");
+            coupled.add_ic("FDIR", "o_fault", "Transducer", "i_fault");
+            if has_mode_manager {
+                coupled.add_ic("FDIR", "o_fault", "ModeManager", "i_fault");
+                coupled.add_ic("FDIR", "o_sensor_valid", "ModeManager", "i_sensor_valid");
+            }
+        }
 
         DiscreteTimeModel {
             coupled: coupled,
             transducer_ref: transducer_ptr,
+            recorders: Vec::new(),
+            outputs,
         }
     }
-    
+
+    /// Adds the recorder of `probe`, coupled to any output port declared by a component of the
+    /// model
+    pub fn add_probe(&mut self, probe: &ProbeSetup) -> Result<(), ProbeError> {
+        if self.outputs.iter().any(|(name, _)| *name == probe.name) {
+            return Err(ProbeError::NameTaken);
+        }
+        let (_, ports) = self
+            .outputs
+            .iter()
+            .find(|(name, _)| *name == probe.component)
+            .ok_or_else(|| {
+                ProbeError::UnknownComponent(
+                    self.outputs
+                        .iter()
+                        .filter(|(_, ports)| !ports.names().is_empty())
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                )
+            })?;
+        let port_type = ports
+            .port_type(&probe.port)
+            .ok_or_else(|| ProbeError::UnknownPort(ports.names()))?;
+        let (name, component, port) = (&probe.name, &probe.component, &probe.port);
+        let config = probe.config.clone();
+        match port_type {
+            PortType::Vec3 => self.add_recorder::<Vec3>(name, component, port, config),
+            PortType::Quaternion => self.add_recorder::<Quaternion>(name, component, port, config),
+            PortType::Mat3 => self.add_recorder::<Mat3>(name, component, port, config),
+            PortType::Mode => self.add_recorder::<Mode>(name, component, port, config),
+            PortType::Bool => self.add_recorder::<bool>(name, component, port, config),
+            PortType::Fault => self.add_recorder::<Fault>(name, component, port, config),
+        }
+    }
+
+    // Adds a `Recorder` named `name` and couples it to the output `port` of `component`, which
+    // `add_probe` has checked to carry values of type `T`
+    fn add_recorder<T: Recordable>(
+        &mut self,
+        name: &str,
+        component: &str,
+        port: &str,
+        config: RecorderConfig,
+    ) -> Result<(), ProbeError> {
+        let recorder = Box::new(Recorder::<T>::new(name, config)?);
+        let recorder_ptr: *const Recorder<T> = &*recorder;
+        self.coupled.add_component(recorder);
+        self.coupled.add_ic(component, port, name, "i_value");
+        self.recorders.push(recorder_ptr);
+        self.outputs.push((name.to_string(), OutputPorts::default()));
+        Ok(())
+    }
+}
+
+// Adds `component` to `coupled`, keeping the output ports it declares in `outputs`
+fn add_component<C: Simulator + Outputs + 'static>(
+    coupled: &mut Coupled,
+    outputs: &mut Vec<(String, OutputPorts)>,
+    component: C,
+) {
+    let name = component.get_component().get_name().to_string();
+    outputs.push((name, component.output_ports().clone()));
+    coupled.add_component(Box::new(component));
 }
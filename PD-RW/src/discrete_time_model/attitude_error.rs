@@ -0,0 +1,116 @@
+use crate::discrete_time_model::types::{Quaternion, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Error vector the Controller extracts from the error quaternion for the control law.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ErrorRepresentation {
+    /// Vector part of q_error as computed (may unwind for errors over 180 deg)
+    VectorPart,
+    /// Vector part of the shortest rotation (q_error sign-corrected)
+    ShortestVectorPart,
+    /// Rotation axis times rotation angle [rad] of the shortest rotation
+    AxisAngle,
+    /// Modified Rodrigues parameters of the shortest rotation
+    Mrp,
+}
+
+impl ErrorRepresentation {
+    /// Rate of change of the error vector per unit of angular velocity near the target, where the
+    /// vector part is half the rotation vector and the MRP a quarter of it.
+    pub fn small_angle_rate(&self) -> f64 {
+        match self {
+            ErrorRepresentation::VectorPart | ErrorRepresentation::ShortestVectorPart => 0.5,
+            ErrorRepresentation::AxisAngle => 1.0,
+            ErrorRepresentation::Mrp => 0.25,
+        }
+    }
+}
+
+/// Turns the raw error quaternion into the error the control law acts on.
+///
+/// q and -q describe the same attitude, so the sign of the scalar part selects which way round
+/// the satellite rotates. The sign is kept in memory and only switched once the scalar part
+/// crosses -hysteresis (Mayhew et al.), so that measurement noise around the 180 deg
+/// boundary does not make the command chatter between both directions.
+/// A hysteresis of 0 always picks the shortest rotation.
+pub struct AttitudeError {
+    representation: ErrorRepresentation,
+    hysteresis: f64,
+    // Sign applied to q_error (+1 or -1)
+    sign: f64,
+}
+
+impl AttitudeError {
+    pub fn new(representation: ErrorRepresentation, hysteresis: f64) -> Self {
+        AttitudeError {
+            representation,
+            hysteresis,
+            sign: 1.0,
+        }
+    }
+
+    /// Returns the error quaternion (sign-corrected unless `VectorPart`) and the error vector.
+    pub fn compute(&mut self, q_error: Quaternion) -> (Quaternion, Vec3) {
+        if let ErrorRepresentation::VectorPart = self.representation {
+            return (q_error, Vec3(q_error.0.imag()));
+        }
+
+        let w = q_error.0.w;
+        if self.sign * w < -self.hysteresis {
+            self.sign = if w < 0.0 { -1.0 } else { 1.0 };
+        }
+        let q = Quaternion(q_error.0 * self.sign);
+
+        let error = match self.representation {
+            ErrorRepresentation::AxisAngle => {
+                let q = q.0.normalize();
+                let v = q.imag();
+                let sin_half = v.norm();
+                if sin_half > f64::EPSILON {
+                    // With the memory sign the scalar part may be slightly negative (angle > pi)
+                    v * (2.0 * sin_half.atan2(q.w) / sin_half)
+                } else {
+                    // Small-angle limit: angle * axis = 2 * v
+                    2.0 * v
+                }
+            }
+            ErrorRepresentation::Mrp => {
+                let q = q.0.normalize();
+                q.imag() / (1.0 + q.w)
+            }
+            ErrorRepresentation::VectorPart | ErrorRepresentation::ShortestVectorPart => q.0.imag(),
+        };
+        (q, Vec3(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Unit error quaternion with scalar part `w`, rotated about x
+    fn q_error(w: f64) -> Quaternion {
+        Quaternion::from([w, (1.0 - w * w).sqrt(), 0.0, 0.0])
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_sign_inside_the_band() {
+        let mut attitude_error = AttitudeError::new(ErrorRepresentation::ShortestVectorPart, 0.1);
+        // (scalar part in, scalar part out): the sign flips only once w leaves the band of
+        // +-0.1 on the side opposite to the current sign
+        let steps = [
+            (0.5, 0.5),
+            (-0.05, -0.05),
+            (0.05, 0.05),
+            (-0.15, 0.15),
+            (0.05, -0.05),
+            (-0.05, 0.05),
+            (0.2, 0.2),
+        ];
+        for (w, expected) in steps {
+            let (q, error) = attitude_error.compute(q_error(w));
+            assert_eq!(q.0.w, expected, "w = {}", w);
+            assert_eq!(error.0, q.0.imag(), "w = {}", w);
+        }
+    }
+}
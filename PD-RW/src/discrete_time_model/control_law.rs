@@ -0,0 +1,71 @@
+use crate::discrete_time_model::types::{Mat3, Quaternion, Vec3};
+use nalgebra::Vector3;
+
+mod adaptive;
+mod compensator;
+mod detumble;
+pub mod discretization;
+pub mod lqr;
+mod mpc;
+mod pd;
+mod pid;
+mod sliding_mode;
+
+pub use adaptive::Adaptive;
+pub use compensator::Compensator;
+pub use detumble::Detumble;
+pub use mpc::{Mpc, MpcConfig};
+pub use pd::PD;
+pub use pid::{AntiWindup, Pid};
+pub use sliding_mode::{BoundaryLayer, SlidingMode};
+
+/// Data handed to a control law every time the `Controller` computes a new command.
+pub struct ControlInput {
+    // Attitude error quaternion (conjugate(q_target) * q_current), sign-corrected
+    pub q_error: Quaternion,
+    // Attitude error vector in the Controller's `ErrorRepresentation`
+    pub error: Vec3,
+    // Measured angular velocity of the satellite [rad/s]
+    pub w: Vec3,
+    // Reference angular velocity from the Guidance, expressed in the body frame [rad/s]
+    pub w_ref: Vec3,
+    // Derivative of `w_ref` as seen in the body frame [rad/s^2]
+    pub w_ref_dot: Vec3,
+    // Latest reaction wheel momentum [Nms] (None until the wheels report it)
+    pub h_rw: Option<Vec3>,
+    // Time elapsed since the previous control step [s] (0 on the first step)
+    pub dt: f64,
+    // Torque limit the Controller saturates the command to [Nm]
+    pub max_torque: f64,
+}
+
+impl ControlInput {
+    /// Rate tracking error w - w_ref [rad/s]
+    pub fn w_error(&self) -> Vector3<f64> {
+        self.w.0 - self.w_ref.0
+    }
+}
+
+/// A control law hosted by the `Controller` atomic.
+///
+/// The `Controller` owns the ports, computes the attitude error and saturates the
+/// returned torque to `max_torque_rw`, so a law only maps the error and rates to a torque.
+pub trait ControlLaw {
+    /// Returns the (unsaturated) torque command [Nm].
+    fn compute(&mut self, input: &ControlInput) -> Vec3;
+
+    /// Integrator state of the law, published on the Controller's `o_integrator` port.
+    fn integrator(&self) -> Option<Vec3> {
+        None
+    }
+
+    /// Sliding variable of the law, published on the Controller's `o_sliding` port.
+    fn sliding_variable(&self) -> Option<Vec3> {
+        None
+    }
+
+    /// Inertia estimate of the law, published on the Controller's `o_inertia` port.
+    fn inertia_estimate(&self) -> Option<Mat3> {
+        None
+    }
+}
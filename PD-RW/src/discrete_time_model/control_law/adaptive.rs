@@ -0,0 +1,79 @@
+use crate::discrete_time_model::{
+    control_law::{ControlInput, ControlLaw},
+    types::{Mat3, Vec3},
+};
+use nalgebra::{Matrix3, SMatrix, Vector3, Vector6};
+
+/// Certainty-equivalence adaptive law (Slotine & Di Benedetto) with online inertia estimation.
+///
+/// With the reference rate w_r = w_ref - lambda * q_v and s = w - w_r, the law applies
+///     torque = Y(w, w_r, dw_r/dt) * theta - kd * s
+/// where Y * theta = J * dw_r/dt - (J * w) x w_r is linear in the six inertia parameters
+/// theta = (Jxx, Jyy, Jzz, Jxy, Jxz, Jyz). The estimate follows d(theta)/dt = -gamma * Y' * s,
+/// which makes 0.5 * s'Js + 0.5 * theta_error' theta_error / gamma non-increasing.
+/// The estimate only converges to the true inertia under persistently exciting manoeuvres, and
+/// the principal moments are kept above `min_inertia` so that it stays physical.
+pub struct Adaptive {
+    lambda: f64,
+    kd: Matrix3<f64>,
+    gamma: f64,
+    min_inertia: f64,
+    theta: Vector6<f64>,
+}
+
+impl Adaptive {
+    pub fn new(lambda: f64, kd: Vector3<f64>, gamma: f64, i_sat_initial: Matrix3<f64>) -> Self {
+        let m = i_sat_initial;
+        Adaptive {
+            lambda,
+            kd: Matrix3::from_diagonal(&kd),
+            gamma,
+            min_inertia: 0.01 * m.diagonal().min(),
+            theta: Vector6::new(m[(0, 0)], m[(1, 1)], m[(2, 2)], m[(0, 1)], m[(0, 2)], m[(1, 2)]),
+        }
+    }
+
+    fn inertia(&self) -> Matrix3<f64> {
+        let t = &self.theta;
+        Matrix3::new(t[0], t[3], t[4], t[3], t[1], t[5], t[4], t[5], t[2])
+    }
+
+    /// Regressor of J * v in the inertia parameters: J * v = l(v) * theta
+    fn l(v: &Vector3<f64>) -> SMatrix<f64, 3, 6> {
+        SMatrix::<f64, 3, 6>::from_row_slice(&[
+            v.x, 0.0, 0.0, v.y, v.z, 0.0, //
+            0.0, v.y, 0.0, v.x, 0.0, v.z, //
+            0.0, 0.0, v.z, 0.0, v.x, v.y,
+        ])
+    }
+}
+
+impl ControlLaw for Adaptive {
+    fn compute(&mut self, input: &ControlInput) -> Vec3 {
+        let w = input.w.0;
+        let w_error = input.w_error();
+        let q = input.q_error.0;
+        let q_v = q.imag();
+
+        // Reference rate and its derivative, d(q_v)/dt = 0.5 * (q_w * w_error + q_v x w_error)
+        let w_r = input.w_ref.0 - self.lambda * q_v;
+        let w_r_dot = input.w_ref_dot.0 - self.lambda * 0.5 * (q.w * w_error + q_v.cross(&w_error));
+        let s = w - w_r;
+
+        let y = Adaptive::l(&w_r_dot) + w_r.cross_matrix() * Adaptive::l(&w);
+        // The wheel momentum coupling w x h_rw does not depend on the inertia
+        let h_rw = input.h_rw.unwrap_or(Vec3::default()).0;
+        let torque = y * self.theta + w.cross(&h_rw) - self.kd * s;
+
+        // Adaptation over the last controller period
+        self.theta -= self.gamma * input.dt * y.transpose() * s;
+        for i in 0..3 {
+            self.theta[i] = self.theta[i].max(self.min_inertia);
+        }
+        Vec3(torque)
+    }
+
+    fn inertia_estimate(&self) -> Option<Mat3> {
+        Some(Mat3(self.inertia()))
+    }
+}
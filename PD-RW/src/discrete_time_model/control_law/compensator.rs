@@ -0,0 +1,56 @@
+use crate::discrete_time_model::{
+    control_law::{
+        ControlInput, ControlLaw,
+        discretization::{DiscreteTransferFunction, Discretization, Filter, TransferFunction},
+    },
+    types::Vec3,
+};
+use nalgebra::Vector3;
+
+/// Linear compensator running at a fixed controller period, the same on every axis:
+/// torque = -c_error(z) * error - c_rate(z) * (w - w_ref)
+///
+/// With static gains it reduces to the PD law. Designs made in continuous time (a filtered
+/// derivative, an integral term, a lead network) are turned into difference equations by
+/// `from_continuous`. The filters assume one call per period, so the Controller must run with
+/// `Rates::control_period` equal to the period they were discretized for.
+pub struct Compensator {
+    error_filters: [Filter; 3],
+    rate_filters: [Filter; 3],
+}
+
+impl Compensator {
+    pub fn new(c_error: &DiscreteTransferFunction, c_rate: &DiscreteTransferFunction) -> Self {
+        assert!(
+            (c_error.period - c_rate.period).abs() <= f64::EPSILON * c_error.period.abs().max(1.0),
+            "both transfer functions must share the sample period"
+        );
+        Compensator {
+            error_filters: std::array::from_fn(|_| c_error.filter()),
+            rate_filters: std::array::from_fn(|_| c_rate.filter()),
+        }
+    }
+
+    /// Discretizes the continuous design `c_error(s)`, `c_rate(s)` with `method` for a
+    /// controller running every `period` seconds.
+    pub fn from_continuous(
+        c_error: &TransferFunction,
+        c_rate: &TransferFunction,
+        method: Discretization,
+        period: f64,
+    ) -> Self {
+        Compensator::new(
+            &c_error.discretize(method, period),
+            &c_rate.discretize(method, period),
+        )
+    }
+}
+
+impl ControlLaw for Compensator {
+    fn compute(&mut self, input: &ControlInput) -> Vec3 {
+        let w_error = input.w_error();
+        Vec3(Vector3::from_fn(|i, _| {
+            -self.error_filters[i].step(input.error.0[i]) - self.rate_filters[i].step(w_error[i])
+        }))
+    }
+}
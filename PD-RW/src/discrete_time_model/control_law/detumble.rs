@@ -0,0 +1,22 @@
+use crate::discrete_time_model::{
+    control_law::{ControlInput, ControlLaw},
+    types::Vec3,
+};
+
+/// Rate damping law for safe mode: torque = -kd * w. It ignores the attitude error, so it only
+/// needs the gyros.
+pub struct Detumble {
+    kd: f64,
+}
+
+impl Detumble {
+    pub fn new(kd: f64) -> Self {
+        Detumble { kd }
+    }
+}
+
+impl ControlLaw for Detumble {
+    fn compute(&mut self, input: &ControlInput) -> Vec3 {
+        Vec3(-self.kd * input.w.0)
+    }
+}
@@ -0,0 +1,367 @@
+use crate::discrete_time_model::control_law::lqr::zoh;
+use nalgebra::{Complex, DMatrix};
+use serde::{Deserialize, Serialize};
+
+// Roots closer than this to s = 0 are treated as integrators or differentiators
+const ORIGIN_TOLERANCE: f64 = 1e-12;
+
+/// Method used to turn a continuous transfer function into a discrete one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Discretization {
+    /// Step invariant: exact when the input is held between samples
+    ZeroOrderHold,
+    /// Bilinear transform s = 2/T (z - 1)/(z + 1), maps the stable half-plane onto the unit disc
+    Tustin,
+    /// Poles and zeros mapped by z = exp(sT), zeros at infinity moved to z = -1 and the gain
+    /// matched at low frequency
+    MatchedPoleZero,
+}
+
+/// Continuous SISO transfer function num(s)/den(s), coefficients in descending powers of s.
+#[derive(Debug, Clone)]
+pub struct TransferFunction {
+    pub num: Vec<f64>,
+    pub den: Vec<f64>,
+}
+
+impl TransferFunction {
+    pub fn new(num: Vec<f64>, den: Vec<f64>) -> Self {
+        let num = trim(num);
+        let den = trim(den);
+        assert!(
+            !den.is_empty(),
+            "the denominator of a transfer function cannot be zero"
+        );
+        assert!(
+            num.len() <= den.len(),
+            "the transfer function must be proper"
+        );
+        TransferFunction { num, den }
+    }
+
+    /// Discrete equivalent for a controller running every `period` seconds.
+    pub fn discretize(&self, method: Discretization, period: f64) -> DiscreteTransferFunction {
+        let (num, den) = match method {
+            Discretization::ZeroOrderHold => self.zero_order_hold(period),
+            Discretization::Tustin => self.tustin(period),
+            Discretization::MatchedPoleZero => self.matched_pole_zero(period),
+        };
+        DiscreteTransferFunction::new(num, den, period)
+    }
+
+    fn order(&self) -> usize {
+        self.den.len() - 1
+    }
+
+    fn zero_order_hold(&self, period: f64) -> (Vec<f64>, Vec<f64>) {
+        let n = self.order();
+        let den = self.den.iter().map(|c| c / self.den[0]).collect::<Vec<_>>();
+        let num = pad(&self.num, n + 1)
+            .iter()
+            .map(|c| c / self.den[0])
+            .collect::<Vec<_>>();
+        if n == 0 {
+            return (num, den);
+        }
+        // Controllable canonical realization of num/den = d + c (sI - a)^-1 b
+        let d = num[0];
+        let mut a = DMatrix::zeros(n, n);
+        for j in 0..n {
+            a[(0, j)] = -den[j + 1];
+        }
+        for i in 1..n {
+            a[(i, i - 1)] = 1.0;
+        }
+        let mut b = DMatrix::zeros(n, 1);
+        b[(0, 0)] = 1.0;
+        let c = DMatrix::from_fn(1, n, |_, j| num[j + 1] - d * den[j + 1]);
+
+        let (ad, bd) = zoh(&a, &b, period);
+        // c adj(zI - ad) bd = det(zI - ad + bd c) - det(zI - ad) (matrix determinant lemma)
+        let den_z = characteristic_polynomial(&ad);
+        let coupled = characteristic_polynomial(&(&ad - &bd * &c));
+        let num_z = den_z
+            .iter()
+            .zip(&coupled)
+            .map(|(p, q)| q - p + d * p)
+            .collect();
+        (num_z, den_z)
+    }
+
+    fn tustin(&self, period: f64) -> (Vec<f64>, Vec<f64>) {
+        // Multiplying num and den by (z + 1)^n, each s^k becomes (2/T)^k (z - 1)^k (z + 1)^(n - k)
+        let n = self.order();
+        let substitute = |coefficients: &[f64]| {
+            let coefficients = pad(coefficients, n + 1);
+            let mut result = vec![0.0; n + 1];
+            for (i, coefficient) in coefficients.iter().enumerate() {
+                let k = n - i;
+                let term = poly_mul(&poly_pow(&[1.0, -1.0], k), &poly_pow(&[1.0, 1.0], n - k));
+                let scale = coefficient * (2.0 / period).powi(k as i32);
+                for (r, t) in result.iter_mut().zip(term) {
+                    *r += scale * t;
+                }
+            }
+            result
+        };
+        (substitute(&self.num), substitute(&self.den))
+    }
+
+    fn matched_pole_zero(&self, period: f64) -> (Vec<f64>, Vec<f64>) {
+        let one = Complex::new(1.0, 0.0);
+        let at_origin = |r: &Complex<f64>| r.norm() < ORIGIN_TOLERANCE;
+        // Each root s_i maps to exp(s_i T), zeros at infinity go to z = -1
+        let map = |roots: &[Complex<f64>], len: usize| {
+            let mut mapped = roots
+                .iter()
+                .map(|r| ((r * period).exp(), at_origin(r)))
+                .collect::<Vec<_>>();
+            mapped.resize(len, (-one, false));
+            mapped
+        };
+        let zeros = roots(&self.num);
+        let poles = roots(&self.den);
+        let zeros_z = map(&zeros, poles.len());
+        let poles_z = map(&poles, poles.len());
+
+        // Near s = 0 (z = 1) a root at the origin behaves as s ~ (z - 1)/T, match the gain of the
+        // remaining factors there
+        let continuous = self.num[0] / self.den[0]
+            * zeros
+                .iter()
+                .filter(|r| !at_origin(r))
+                .map(|r| -r)
+                .product::<Complex<f64>>()
+            / poles
+                .iter()
+                .filter(|r| !at_origin(r))
+                .map(|r| -r)
+                .product::<Complex<f64>>();
+        let reduced = |mapped: &[(Complex<f64>, bool)]| {
+            mapped
+                .iter()
+                .filter(|(_, origin)| !origin)
+                .map(|(r, _)| one - r)
+                .product::<Complex<f64>>()
+        };
+        let origin_order = zeros_z.iter().filter(|(_, origin)| *origin).count() as i32
+            - poles_z.iter().filter(|(_, origin)| *origin).count() as i32;
+        let discrete = reduced(&zeros_z) / reduced(&poles_z) * period.powi(origin_order);
+        let gain = (continuous / discrete).re;
+
+        let roots_of =
+            |mapped: &[(Complex<f64>, bool)]| mapped.iter().map(|(r, _)| *r).collect::<Vec<_>>();
+        let num = poly_from_roots(&roots_of(&zeros_z))
+            .iter()
+            .map(|c| gain * c)
+            .collect();
+        (num, poly_from_roots(&roots_of(&poles_z)))
+    }
+}
+
+/// Discrete SISO transfer function num(z)/den(z) for a fixed sample period, coefficients in
+/// descending powers of z, with den[0] = 1 and num padded to the length of den.
+#[derive(Debug, Clone)]
+pub struct DiscreteTransferFunction {
+    pub num: Vec<f64>,
+    pub den: Vec<f64>,
+    // Sample period [s]
+    pub period: f64,
+}
+
+impl DiscreteTransferFunction {
+    pub fn new(num: Vec<f64>, den: Vec<f64>, period: f64) -> Self {
+        let den = trim(den);
+        assert!(
+            !den.is_empty(),
+            "the denominator of a transfer function cannot be zero"
+        );
+        let num = trim(num);
+        assert!(
+            num.len() <= den.len(),
+            "the transfer function must be causal"
+        );
+        let leading = den[0];
+        DiscreteTransferFunction {
+            num: pad(&num, den.len()).iter().map(|c| c / leading).collect(),
+            den: den.iter().map(|c| c / leading).collect(),
+            period,
+        }
+    }
+
+    /// Difference equation implementing the transfer function, starting at rest
+    pub fn filter(&self) -> Filter {
+        Filter {
+            b: self.num.clone(),
+            a: self.den.clone(),
+            state: vec![0.0; self.den.len() - 1],
+        }
+    }
+}
+
+/// Difference equation sum(a_i y[k-i]) = sum(b_i u[k-i]) in transposed direct form II.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    b: Vec<f64>,
+    a: Vec<f64>,
+    state: Vec<f64>,
+}
+
+impl Filter {
+    /// Feeds the next input sample and returns the output at the same instant
+    pub fn step(&mut self, u: f64) -> f64 {
+        let y = self.b[0] * u + self.state.first().copied().unwrap_or(0.0);
+        let n = self.state.len();
+        for i in 0..n {
+            let next = if i + 1 < n { self.state[i + 1] } else { 0.0 };
+            self.state[i] = next + self.b[i + 1] * u - self.a[i + 1] * y;
+        }
+        y
+    }
+}
+
+// Drops leading zero coefficients
+fn trim(coefficients: Vec<f64>) -> Vec<f64> {
+    let first = coefficients
+        .iter()
+        .position(|c| *c != 0.0)
+        .unwrap_or(coefficients.len());
+    coefficients[first..].to_vec()
+}
+
+// Prepends zeros up to `len` coefficients
+fn pad(coefficients: &[f64], len: usize) -> Vec<f64> {
+    let mut padded = vec![0.0; len - coefficients.len()];
+    padded.extend_from_slice(coefficients);
+    padded
+}
+
+fn poly_mul(p: &[f64], q: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; p.len() + q.len() - 1];
+    for (i, a) in p.iter().enumerate() {
+        for (j, b) in q.iter().enumerate() {
+            result[i + j] += a * b;
+        }
+    }
+    result
+}
+
+fn poly_pow(p: &[f64], k: usize) -> Vec<f64> {
+    (0..k).fold(vec![1.0], |result, _| poly_mul(&result, p))
+}
+
+// Monic polynomial with the given roots, complex ones in conjugate pairs
+fn poly_from_roots(roots: &[Complex<f64>]) -> Vec<f64> {
+    let mut result = vec![Complex::new(1.0, 0.0)];
+    for root in roots {
+        let mut next = result.clone();
+        next.push(Complex::new(0.0, 0.0));
+        for (i, c) in result.iter().enumerate() {
+            next[i + 1] -= root * c;
+        }
+        result = next;
+    }
+    result.iter().map(|c| c.re).collect()
+}
+
+// Roots as the eigenvalues of the companion matrix
+fn roots(coefficients: &[f64]) -> Vec<Complex<f64>> {
+    let n = coefficients.len().saturating_sub(1);
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut companion = DMatrix::zeros(n, n);
+    for j in 0..n {
+        companion[(0, j)] = -coefficients[j + 1] / coefficients[0];
+    }
+    for i in 1..n {
+        companion[(i, i - 1)] = 1.0;
+    }
+    companion.complex_eigenvalues().iter().copied().collect()
+}
+
+// det(zI - a) in descending powers of z (Faddeev-LeVerrier)
+fn characteristic_polynomial(a: &DMatrix<f64>) -> Vec<f64> {
+    let n = a.nrows();
+    let identity = DMatrix::<f64>::identity(n, n);
+    let mut coefficients = vec![1.0];
+    let mut m = DMatrix::<f64>::zeros(n, n);
+    for k in 1..=n {
+        m = a * &m + &identity * coefficients[k - 1];
+        coefficients.push(-(a * &m).trace() / k as f64);
+    }
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: f64 = 0.1;
+
+    // First-order lag 2/(s + 2)
+    fn lag() -> TransferFunction {
+        TransferFunction::new(vec![2.0], vec![1.0, 2.0])
+    }
+
+    fn assert_coefficients(actual: &[f64], expected: &[f64]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn zero_order_hold_of_a_lag() {
+        // (1 - p)/(z - p) with p = exp(-2T)
+        let p = (-2.0 * PERIOD).exp();
+        let discrete = lag().discretize(Discretization::ZeroOrderHold, PERIOD);
+        assert_coefficients(&discrete.num, &[0.0, 1.0 - p]);
+        assert_coefficients(&discrete.den, &[1.0, -p]);
+    }
+
+    #[test]
+    fn zero_order_hold_of_a_lead() {
+        // (s + 1)/(s + 10) = 1 - 9/(s + 10), so 1 - 0.9 (1 - p)/(z - p) with p = exp(-10T)
+        let p = (-10.0 * PERIOD).exp();
+        let lead = TransferFunction::new(vec![1.0, 1.0], vec![1.0, 10.0]);
+        let discrete = lead.discretize(Discretization::ZeroOrderHold, PERIOD);
+        assert_coefficients(&discrete.num, &[1.0, -p - 0.9 * (1.0 - p)]);
+        assert_coefficients(&discrete.den, &[1.0, -p]);
+    }
+
+    #[test]
+    fn zero_order_hold_keeps_the_step_response() {
+        let mut filter = lag()
+            .discretize(Discretization::ZeroOrderHold, PERIOD)
+            .filter();
+        for k in 0..50 {
+            let expected = 1.0 - (-2.0 * PERIOD * k as f64).exp();
+            assert!((filter.step(1.0) - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn tustin_of_a_lag() {
+        // s = 20 (z - 1)/(z + 1): 2 (z + 1)/(22 z - 18)
+        let discrete = lag().discretize(Discretization::Tustin, PERIOD);
+        assert_coefficients(&discrete.num, &[2.0 / 22.0, 2.0 / 22.0]);
+        assert_coefficients(&discrete.den, &[1.0, -18.0 / 22.0]);
+    }
+
+    #[test]
+    fn matched_pole_zero_of_a_lag() {
+        // The zero at infinity goes to z = -1: k (z + 1)/(z - p), unit gain at z = 1
+        let p = (-2.0 * PERIOD).exp();
+        let k = (1.0 - p) / 2.0;
+        let discrete = lag().discretize(Discretization::MatchedPoleZero, PERIOD);
+        assert_coefficients(&discrete.num, &[k, k]);
+        assert_coefficients(&discrete.den, &[1.0, -p]);
+    }
+}
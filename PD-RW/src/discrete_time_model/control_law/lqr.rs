@@ -0,0 +1,278 @@
+use crate::discrete_time_model::{
+    attitude_error::ErrorRepresentation, control_law::PD, types::Vec3,
+};
+use nalgebra::{DMatrix, Matrix3, Vector3};
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+
+/// Attitude and wheel model linearized about the target attitude, x = [error, w] and u = torque.
+///
+/// With the wheels spinning at `rw_speeds`, `SatelliteDynamics` and `RW` reduce near the target to
+///     d(error)/dt = c * w
+///     i_sat * dw/dt = torque + h_rw x w,    h_rw = i_rw * rw_speeds
+/// where c depends on the error representation the Controller hands to the law, 0.5 for the
+/// vector part of the error quaternion (see `ErrorRepresentation::small_angle_rate`).
+/// The wheel momentum is not kept as a state: it only follows the commanded torque and the total
+/// momentum is conserved, so it would be an uncontrollable mode for the Riccati solvers.
+pub struct LinearizedModel {
+    pub a: DMatrix<f64>,
+    pub b: DMatrix<f64>,
+}
+
+impl LinearizedModel {
+    pub fn new(
+        i_sat: Matrix3<f64>,
+        i_rw: Matrix3<f64>,
+        rw_speeds: Vec3,
+        error: ErrorRepresentation,
+    ) -> Option<Self> {
+        let i_inv = i_sat.try_inverse()?;
+        let h_rw = i_rw * rw_speeds.0;
+
+        let mut a = DMatrix::zeros(6, 6);
+        a.view_mut((0, 3), (3, 3))
+            .copy_from(&Matrix3::from_diagonal_element(error.small_angle_rate()));
+        a.view_mut((3, 3), (3, 3))
+            .copy_from(&(i_inv * h_rw.cross_matrix()));
+        let mut b = DMatrix::zeros(6, 3);
+        b.view_mut((3, 0), (3, 3)).copy_from(&i_inv);
+        Some(LinearizedModel { a, b })
+    }
+
+    /// Zero-order-hold equivalent of the model for a controller running every `dt` seconds.
+    pub fn discretize(&self, dt: f64) -> LinearizedModel {
+        let (a, b) = zoh(&self.a, &self.b, dt);
+        LinearizedModel { a, b }
+    }
+}
+
+/// Diagonal LQR weights, one entry per body axis.
+pub struct LqrWeights {
+    // Weight on the attitude error, in the representation of the model
+    pub attitude: Vector3<f64>,
+    // Weight on the angular velocity
+    pub rate: Vector3<f64>,
+    // Weight on the control torque
+    pub torque: Vector3<f64>,
+}
+
+impl LqrWeights {
+    fn q(&self) -> DMatrix<f64> {
+        let diagonal = [self.attitude.as_slice(), self.rate.as_slice()].concat();
+        DMatrix::from_diagonal(&nalgebra::DVector::from_vec(diagonal))
+    }
+
+    fn r(&self) -> DMatrix<f64> {
+        DMatrix::from_diagonal(&nalgebra::DVector::from_column_slice(
+            self.torque.as_slice(),
+        ))
+    }
+}
+
+/// State-feedback gains torque = -kp * error - kd * w.
+pub struct LqrGains {
+    pub kp: Matrix3<f64>,
+    pub kd: Matrix3<f64>,
+}
+
+impl LqrGains {
+    /// Gains minimizing the continuous-time cost integral(x'Qx + u'Ru) dt (CARE).
+    pub fn continuous(model: &LinearizedModel, weights: &LqrWeights) -> Option<Self> {
+        let r = weights.r();
+        let p = solve_care(&model.a, &model.b, &weights.q(), &r)?;
+        let k = r.try_inverse()? * model.b.transpose() * &p;
+        Some(LqrGains::from_k(&k))
+    }
+
+    /// Gains minimizing sum(x'Qx + u'Ru) for a controller running every `dt` seconds (DARE).
+    pub fn discrete(model: &LinearizedModel, weights: &LqrWeights, dt: f64) -> Option<Self> {
+        let discrete = model.discretize(dt);
+        let (a, b) = (&discrete.a, &discrete.b);
+        let r = weights.r();
+        let p = solve_dare(a, b, &weights.q(), &r)?;
+        let bt_p = b.transpose() * &p;
+        let k = (r + &bt_p * b).try_inverse()? * bt_p * a;
+        Some(LqrGains::from_k(&k))
+    }
+
+    /// PD law applying these gains in the Controller.
+    pub fn to_pd(&self) -> PD {
+        PD::with_gains(self.kp, self.kd)
+    }
+
+    fn from_k(k: &DMatrix<f64>) -> Self {
+        LqrGains {
+            kp: Matrix3::from_fn(|i, j| k[(i, j)]),
+            kd: Matrix3::from_fn(|i, j| k[(i, j + 3)]),
+        }
+    }
+}
+
+/// Zero-order-hold discretization of dx/dt = a x + b u with sample period dt.
+pub fn zoh(a: &DMatrix<f64>, b: &DMatrix<f64>, dt: f64) -> (DMatrix<f64>, DMatrix<f64>) {
+    let (n, m) = (a.nrows(), b.ncols());
+    // exp([[a, b], [0, 0]] * dt) = [[ad, bd], [0, I]]
+    let mut augmented = DMatrix::zeros(n + m, n + m);
+    augmented.view_mut((0, 0), (n, n)).copy_from(&(a * dt));
+    augmented.view_mut((0, n), (n, m)).copy_from(&(b * dt));
+    let phi = augmented.exp();
+    (
+        phi.view((0, 0), (n, n)).into_owned(),
+        phi.view((0, n), (n, m)).into_owned(),
+    )
+}
+
+/// Stabilizing solution of the continuous algebraic Riccati equation
+/// a'P + Pa - Pb r^-1 b'P + q = 0, by the matrix sign function of the Hamiltonian. None when the
+/// iteration does not converge within `MAX_ITERATIONS`.
+pub fn solve_care(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    q: &DMatrix<f64>,
+    r: &DMatrix<f64>,
+) -> Option<DMatrix<f64>> {
+    let n = a.nrows();
+    let g = b * r.clone().try_inverse()? * b.transpose();
+
+    let mut z = DMatrix::zeros(2 * n, 2 * n);
+    z.view_mut((0, 0), (n, n)).copy_from(a);
+    z.view_mut((0, n), (n, n)).copy_from(&(-g));
+    z.view_mut((n, 0), (n, n)).copy_from(&(-q));
+    z.view_mut((n, n), (n, n)).copy_from(&(-a.transpose()));
+
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        let z_inv = z.clone().try_inverse()?;
+        // Determinant scaling speeds up the Newton iteration
+        let c = z.determinant().abs().powf(1.0 / (2 * n) as f64);
+        let next = 0.5 * (&z / c + z_inv * c);
+        converged = (&next - &z).norm() <= TOLERANCE * next.norm();
+        z = next;
+        if converged {
+            break;
+        }
+    }
+    if !converged {
+        return None;
+    }
+
+    // The stable invariant subspace [I; P] is the null space of sign(H) + I
+    let identity = DMatrix::<f64>::identity(n, n);
+    let mut lhs = DMatrix::zeros(2 * n, n);
+    lhs.view_mut((0, 0), (n, n))
+        .copy_from(&z.view((0, n), (n, n)));
+    lhs.view_mut((n, 0), (n, n))
+        .copy_from(&(z.view((n, n), (n, n)) + &identity));
+    let mut rhs = DMatrix::zeros(2 * n, n);
+    rhs.view_mut((0, 0), (n, n))
+        .copy_from(&(-(z.view((0, 0), (n, n)) + &identity)));
+    rhs.view_mut((n, 0), (n, n))
+        .copy_from(&(-z.view((n, 0), (n, n))));
+
+    let p = lhs.svd(true, true).solve(&rhs, TOLERANCE).ok()?;
+    Some(0.5 * (&p + p.transpose()))
+}
+
+/// Stabilizing solution of the discrete algebraic Riccati equation
+/// P = a'Pa - a'Pb (r + b'Pb)^-1 b'Pa + q, by the structure-preserving doubling algorithm. None
+/// when the iteration does not converge within `MAX_ITERATIONS`.
+pub fn solve_dare(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    q: &DMatrix<f64>,
+    r: &DMatrix<f64>,
+) -> Option<DMatrix<f64>> {
+    let n = a.nrows();
+    let identity = DMatrix::<f64>::identity(n, n);
+    let mut a_k = a.clone();
+    let mut g_k = b * r.clone().try_inverse()? * b.transpose();
+    let mut h_k = q.clone();
+
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        let w = (&identity + &g_k * &h_k).try_inverse()?;
+        let a_next = &a_k * &w * &a_k;
+        let g_next = &g_k + &a_k * &w * &g_k * a_k.transpose();
+        let h_next = &h_k + a_k.transpose() * &h_k * &w * &a_k;
+        let size = h_next.norm();
+        // Without a stabilizing solution the iterates blow up
+        if !size.is_finite() {
+            return None;
+        }
+        converged = (&h_next - &h_k).norm() <= TOLERANCE * size;
+        a_k = a_next;
+        g_k = g_next;
+        h_k = h_next;
+        if converged {
+            break;
+        }
+    }
+    if !converged {
+        return None;
+    }
+    Some(0.5 * (&h_k + h_k.transpose()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Double integrator x'' = u
+    fn double_integrator() -> (DMatrix<f64>, DMatrix<f64>) {
+        (
+            DMatrix::from_row_slice(2, 2, &[0.0, 1.0, 0.0, 0.0]),
+            DMatrix::from_row_slice(2, 1, &[0.0, 1.0]),
+        )
+    }
+
+    #[test]
+    fn care_solves_the_double_integrator() {
+        let (a, b) = double_integrator();
+        let q = DMatrix::identity(2, 2);
+        let r = DMatrix::identity(1, 1);
+        let p = solve_care(&a, &b, &q, &r).unwrap();
+
+        let residual = a.transpose() * &p + &p * &a - &p * &b * b.transpose() * &p + &q;
+        assert!(residual.norm() < 1e-9, "residual {}", residual);
+        // Known solution [sqrt(3), 1; 1, sqrt(3)]
+        let expected = DMatrix::from_row_slice(2, 2, &[3f64.sqrt(), 1.0, 1.0, 3f64.sqrt()]);
+        assert!((p - expected).norm() < 1e-9);
+    }
+
+    #[test]
+    fn dare_solves_the_sampled_double_integrator() {
+        let (a, b) = double_integrator();
+        let (a, b) = zoh(&a, &b, 0.1);
+        let q = DMatrix::identity(2, 2);
+        let r = DMatrix::identity(1, 1);
+        let p = solve_dare(&a, &b, &q, &r).unwrap();
+
+        let gain = (&r + b.transpose() * &p * &b).try_inverse().unwrap();
+        let residual =
+            a.transpose() * &p * &a - a.transpose() * &p * &b * gain * b.transpose() * &p * &a + &q
+                - &p;
+        assert!(residual.norm() < 1e-9 * p.norm(), "residual {}", residual);
+        assert!(p.symmetric_eigenvalues().min() > 0.0);
+    }
+
+    #[test]
+    fn dare_fails_on_an_unstabilizable_system() {
+        let a = DMatrix::from_element(1, 1, 2.0);
+        let b = DMatrix::zeros(1, 1);
+        let q = DMatrix::identity(1, 1);
+        let r = DMatrix::identity(1, 1);
+        assert!(solve_dare(&a, &b, &q, &r).is_none());
+    }
+
+    #[test]
+    fn zoh_samples_the_double_integrator() {
+        let (a, b) = double_integrator();
+        let dt = 0.1;
+        let (ad, bd) = zoh(&a, &b, dt);
+        let expected_a = DMatrix::from_row_slice(2, 2, &[1.0, dt, 0.0, 1.0]);
+        let expected_b = DMatrix::from_row_slice(2, 1, &[0.5 * dt * dt, dt]);
+        assert!((ad - expected_a).norm() < 1e-12);
+        assert!((bd - expected_b).norm() < 1e-12);
+    }
+}
@@ -0,0 +1,446 @@
+use crate::discrete_time_model::{
+    attitude_error::ErrorRepresentation,
+    control_law::{
+        ControlInput, ControlLaw,
+        lqr::{LinearizedModel, LqrWeights, solve_dare, zoh},
+    },
+    types::Vec3,
+};
+use nalgebra::{DMatrix, DVector, Matrix3, Vector3};
+
+// States per prediction step: attitude error, rate tracking error and wheel speeds
+const N_X: usize = 9;
+// Inputs per prediction step: torque
+const N_U: usize = 3;
+// Violation of a limit, relative to it, up to which a QP solution is accepted
+const FEASIBILITY_TOLERANCE: f64 = 1e-6;
+
+/// Tuning and actuator limits of the MPC law.
+pub struct MpcConfig {
+    // Number of prediction steps
+    pub horizon: usize,
+    // Prediction step the model is discretized with [s] (may be longer than the controller period)
+    pub dt: f64,
+    // Stage weights; the terminal weight is the discrete LQR cost-to-go for the same weights
+    pub weights: LqrWeights,
+    // Hard limit on the torque of each wheel [Nm]
+    pub max_torque_rw: f64,
+    // Hard limit on the speed of each wheel [rad/s]
+    pub max_speed_rw: f64,
+    // Maximum number of QP iterations per controller step
+    pub max_iterations: usize,
+    // Error the Controller hands to the law, which sets the attitude part of the model
+    pub error: ErrorRepresentation,
+}
+
+/// Model predictive law over the linearized attitude and wheel dynamics.
+///
+/// Every step it minimizes sum(x'Qx + u'Ru) over the horizon subject to |torque| <= max_torque_rw
+/// and |wheel speed| <= max_speed_rw at every predicted step. The QP is condensed to the torque
+/// sequence and solved with Hildreth's dual method, which only iterates when the unconstrained
+/// optimum violates a constraint. The wheel speeds come from the measured wheel momentum; until
+/// the wheels report it they are propagated from the applied torques and the initial speeds.
+///
+/// When the wheel speed limit cannot be kept (e.g. a wheel starts above it) or the method does not
+/// reach a feasible solution within max_iterations, the law reports it on the first occurrence
+/// and falls back to the QP with the torque limit only. Should that one fail as well, it applies
+/// the next torque of the last plan, which is within the torque limit.
+pub struct Mpc {
+    config: MpcConfig,
+    i_rw_inv: Matrix3<f64>,
+    rw_speeds: Vector3<f64>,
+    torque: Vector3<f64>,
+    // Torque sequence of the last solved QP
+    plan: DVector<f64>,
+    // Steps solved with the torque limit only, and steps not solved at all
+    speed_limit_dropped: usize,
+    unsolved_steps: usize,
+    // Condensed QP: min 0.5 U'HU + x0'F'U, s.t. M U <= gamma(x0), the torque rows first
+    h_inv: DMatrix<f64>,
+    f: DMatrix<f64>,
+    m: DMatrix<f64>,
+    torque_rows: usize,
+    // Limit each row of M bounds, the scale its violation is measured against
+    limits: DVector<f64>,
+    // Largest decrease of each row of M U within the torque limit
+    reach: DVector<f64>,
+    // Wheel speed rows of the free response, to build gamma(x0)
+    phi_w: DMatrix<f64>,
+    // Dual Hessian M H^-1 M' and the multipliers of the last step, to warm start the next one
+    dual: DMatrix<f64>,
+    lambda: DVector<f64>,
+}
+
+impl Mpc {
+    pub fn new(
+        config: MpcConfig,
+        i_sat: Matrix3<f64>,
+        i_rw: Matrix3<f64>,
+        rw_speeds_initial: Vec3,
+    ) -> Option<Self> {
+        let n = config.horizon;
+        let i_rw_inv = i_rw.try_inverse()?;
+
+        // Continuous model: attitude part linearized with the wheels at rest, wheel speeds integrate -torque
+        let attitude = LinearizedModel::new(i_sat, i_rw, Vec3::default(), config.error)?;
+        let mut a = DMatrix::zeros(N_X, N_X);
+        a.view_mut((0, 0), (6, 6)).copy_from(&attitude.a);
+        let mut b = DMatrix::zeros(N_X, N_U);
+        b.view_mut((0, 0), (6, N_U)).copy_from(&attitude.b);
+        b.view_mut((6, 0), (3, N_U)).copy_from(&(-i_rw_inv));
+        let (a, b) = zoh(&a, &b, config.dt);
+
+        // Stage and terminal weights
+        let w = &config.weights;
+        let mut q = DMatrix::zeros(N_X, N_X);
+        for i in 0..3 {
+            q[(i, i)] = w.attitude[i];
+            q[(i + 3, i + 3)] = w.rate[i];
+        }
+        let r = DMatrix::from_diagonal(&DVector::from_column_slice(w.torque.as_slice()));
+        let discrete = attitude.discretize(config.dt);
+        let p_attitude = solve_dare(
+            &discrete.a,
+            &discrete.b,
+            &q.view((0, 0), (6, 6)).into_owned(),
+            &r,
+        )?;
+        let mut p = DMatrix::zeros(N_X, N_X);
+        p.view_mut((0, 0), (6, 6)).copy_from(&p_attitude);
+
+        // Prediction X = phi x0 + gamma U over steps 1..=n
+        let mut phi = DMatrix::zeros(N_X * n, N_X);
+        let mut gamma = DMatrix::zeros(N_X * n, N_U * n);
+        let mut a_k = DMatrix::<f64>::identity(N_X, N_X);
+        let mut a_k_b = Vec::with_capacity(n);
+        for k in 0..n {
+            a_k_b.push(&a_k * &b);
+            a_k = &a * a_k;
+            phi.view_mut((N_X * k, 0), (N_X, N_X)).copy_from(&a_k);
+        }
+        for k in 0..n {
+            for j in 0..=k {
+                gamma
+                    .view_mut((N_X * k, N_U * j), (N_X, N_U))
+                    .copy_from(&a_k_b[k - j]);
+            }
+        }
+
+        let mut q_bar = DMatrix::zeros(N_X * n, N_X * n);
+        let mut r_bar = DMatrix::zeros(N_U * n, N_U * n);
+        for k in 0..n {
+            let stage = if k + 1 == n { &p } else { &q };
+            q_bar
+                .view_mut((N_X * k, N_X * k), (N_X, N_X))
+                .copy_from(stage);
+            r_bar.view_mut((N_U * k, N_U * k), (N_U, N_U)).copy_from(&r);
+        }
+        let gamma_t_q = gamma.transpose() * &q_bar;
+        let h = &gamma_t_q * &gamma + r_bar;
+        let f = gamma_t_q * &phi;
+        let h_inv = h.cholesky()?.inverse();
+
+        // Constraints: +-U <= max_torque_rw, +-(wheel speeds) <= max_speed_rw
+        let mut gamma_w = DMatrix::zeros(N_U * n, N_U * n);
+        let mut phi_w = DMatrix::zeros(N_U * n, N_X);
+        for k in 0..n {
+            gamma_w
+                .view_mut((N_U * k, 0), (N_U, N_U * n))
+                .copy_from(&gamma.view((N_X * k + 6, 0), (N_U, N_U * n)));
+            phi_w
+                .view_mut((N_U * k, 0), (N_U, N_X))
+                .copy_from(&phi.view((N_X * k + 6, 0), (N_U, N_X)));
+        }
+        let identity = DMatrix::<f64>::identity(N_U * n, N_U * n);
+        let mut m = DMatrix::zeros(4 * N_U * n, N_U * n);
+        m.view_mut((0, 0), (N_U * n, N_U * n)).copy_from(&identity);
+        m.view_mut((N_U * n, 0), (N_U * n, N_U * n))
+            .copy_from(&(-identity));
+        m.view_mut((2 * N_U * n, 0), (N_U * n, N_U * n))
+            .copy_from(&gamma_w);
+        m.view_mut((3 * N_U * n, 0), (N_U * n, N_U * n))
+            .copy_from(&(-gamma_w));
+        let dual = &m * &h_inv * m.transpose();
+        let limits = DVector::from_fn(4 * N_U * n, |i, _| match i / (N_U * n) {
+            0 | 1 => config.max_torque_rw,
+            _ => config.max_speed_rw,
+        });
+        let reach = DVector::from_fn(4 * N_U * n, |i, _| {
+            config.max_torque_rw * m.row(i).abs().sum()
+        });
+
+        Some(Mpc {
+            config,
+            i_rw_inv,
+            rw_speeds: rw_speeds_initial.0,
+            torque: Vector3::zeros(),
+            plan: DVector::zeros(N_U * n),
+            speed_limit_dropped: 0,
+            unsolved_steps: 0,
+            h_inv,
+            f,
+            m,
+            torque_rows: 2 * N_U * n,
+            limits,
+            reach,
+            phi_w,
+            lambda: DVector::zeros(dual.nrows()),
+            dual,
+        })
+    }
+
+    fn gamma(&self, x0: &DVector<f64>) -> DVector<f64> {
+        let n = N_U * self.config.horizon;
+        let wheel_free = &self.phi_w * x0;
+        DVector::from_fn(4 * n, |i, _| match i / n {
+            0 | 1 => self.config.max_torque_rw,
+            2 => self.config.max_speed_rw - wheel_free[i - 2 * n],
+            _ => self.config.max_speed_rw + wheel_free[i - 3 * n],
+        })
+    }
+
+    /// Hildreth's quadratic programming: coordinate ascent on the dual multipliers, over the first
+    /// `rows` constraints. Returns None when the solution still violates one of them after
+    /// max_iterations.
+    fn solve(
+        &mut self,
+        f_x0: &DVector<f64>,
+        gamma: &DVector<f64>,
+        rows: usize,
+    ) -> Option<DVector<f64>> {
+        let unconstrained = -(&self.h_inv * f_x0);
+        if (self.m.rows(0, rows) * &unconstrained - gamma.rows(0, rows)).max() <= 0.0 {
+            self.lambda.fill(0.0);
+            return Some(unconstrained);
+        }
+
+        // The multipliers of the other rows stay at zero, so they drop out of the dual
+        self.lambda
+            .rows_mut(rows, self.lambda.len() - rows)
+            .fill(0.0);
+        let k = gamma + &self.m * &self.h_inv * f_x0;
+        for _ in 0..self.config.max_iterations {
+            let mut change: f64 = 0.0;
+            for i in 0..rows {
+                // The dual Hessian is symmetric: read its contiguous column instead of the row
+                let column = self.dual.column(i);
+                let sum = column.dot(&self.lambda) - column[i] * self.lambda[i];
+                let lambda_i = (-(k[i] + sum) / column[i]).max(0.0);
+                change = change.max((lambda_i - self.lambda[i]).abs());
+                self.lambda[i] = lambda_i;
+            }
+            if change <= 1e-10 * self.lambda.amax().max(1e-12) {
+                break;
+            }
+        }
+        let u = -(&self.h_inv * (f_x0 + self.m.transpose() * &self.lambda));
+        let violation = self.m.rows(0, rows) * &u - gamma.rows(0, rows);
+        let feasible = violation
+            .iter()
+            .zip(self.limits.iter())
+            .all(|(v, limit)| *v <= FEASIBILITY_TOLERANCE * limit);
+        feasible.then_some(u)
+    }
+}
+
+impl ControlLaw for Mpc {
+    fn compute(&mut self, input: &ControlInput) -> Vec3 {
+        // Use the measured wheel momentum when available, otherwise the wheels have been
+        // applying the previous torque since the last step
+        match input.h_rw {
+            Some(h_rw) => self.rw_speeds = self.i_rw_inv * h_rw.0,
+            None => self.rw_speeds -= input.dt * self.i_rw_inv * self.torque,
+        }
+
+        // The rate state tracks the Guidance reference
+        let w_error = input.w_error();
+        let x0 = DVector::from_iterator(
+            N_X,
+            input
+                .error
+                .0
+                .iter()
+                .chain(w_error.iter())
+                .chain(self.rw_speeds.iter())
+                .copied(),
+        );
+        let f_x0 = &self.f * &x0;
+        let gamma = self.gamma(&x0);
+
+        // A row no torque sequence within the limit can meet makes the QP infeasible
+        let reachable = (&gamma + &self.reach).min() >= 0.0;
+        let mut u = match reachable {
+            true => self.solve(&f_x0, &gamma, gamma.len()),
+            false => None,
+        };
+        if u.is_none() {
+            if self.speed_limit_dropped == 0 {
+                eprintln!(
+                    "MPC: the wheel speed limit cannot be kept, solving with the torque limit only"
+                );
+            }
+            self.speed_limit_dropped += 1;
+            u = self.solve(&f_x0, &gamma, self.torque_rows);
+        }
+        match u {
+            Some(u) => self.plan = u,
+            None => {
+                if self.unsolved_steps == 0 {
+                    eprintln!(
+                        "MPC: the QP has no solution after {} iterations, following the last plan",
+                        self.config.max_iterations
+                    );
+                }
+                self.unsolved_steps += 1;
+                // Move on to the next step of the plan, with no torque past its end
+                let n = self.plan.len();
+                let next = self.plan.rows(N_U, n - N_U).into_owned();
+                self.plan.rows_mut(0, n - N_U).copy_from(&next);
+                self.plan.rows_mut(n - N_U, N_U).fill(0.0);
+            }
+        }
+        self.torque = Vector3::new(self.plan[0], self.plan[1], self.plan[2]);
+        Vec3(self.torque)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete_time_model::types::Quaternion;
+
+    // Law holding only the QP min 0.5 U'HU + f'U s.t. M U <= gamma, for `Mpc::solve`, with the
+    // violations measured against 1
+    fn qp(h: &[f64], m: DMatrix<f64>) -> Mpc {
+        let h_inv =
+            DMatrix::from_diagonal(&DVector::from_iterator(h.len(), h.iter().map(|h| 1.0 / h)));
+        let dual = &m * &h_inv * m.transpose();
+        Mpc {
+            config: MpcConfig {
+                horizon: 1,
+                dt: 0.1,
+                weights: LqrWeights {
+                    attitude: Vector3::zeros(),
+                    rate: Vector3::zeros(),
+                    torque: Vector3::zeros(),
+                },
+                max_torque_rw: 0.0,
+                max_speed_rw: 0.0,
+                max_iterations: 1000,
+                error: ErrorRepresentation::VectorPart,
+            },
+            i_rw_inv: Matrix3::identity(),
+            rw_speeds: Vector3::zeros(),
+            torque: Vector3::zeros(),
+            plan: DVector::zeros(0),
+            speed_limit_dropped: 0,
+            unsolved_steps: 0,
+            h_inv,
+            f: DMatrix::zeros(0, 0),
+            torque_rows: m.nrows(),
+            limits: DVector::from_element(m.nrows(), 1.0),
+            reach: DVector::zeros(m.nrows()),
+            phi_w: DMatrix::zeros(0, 0),
+            lambda: DVector::zeros(m.nrows()),
+            m,
+            dual,
+        }
+    }
+
+    // Box |u_i| <= 1.5 on two variables
+    fn bounds() -> DMatrix<f64> {
+        DMatrix::from_row_slice(4, 2, &[1.0, 0.0, 0.0, 1.0, -1.0, 0.0, 0.0, -1.0])
+    }
+
+    #[test]
+    fn hildreth_returns_the_unconstrained_optimum_inside_the_bounds() {
+        let mut mpc = qp(&[1.0, 2.0], bounds());
+        let u = mpc
+            .solve(
+                &DVector::from_vec(vec![-1.0, -1.0]),
+                &DVector::from_element(4, 1.5),
+                4,
+            )
+            .unwrap();
+        assert!((u - DVector::from_vec(vec![1.0, 0.5])).norm() < 1e-12);
+        assert_eq!(mpc.lambda.amax(), 0.0);
+    }
+
+    #[test]
+    fn hildreth_stops_at_an_active_bound() {
+        // Unconstrained optimum (2, 1), the first variable is clipped to its bound
+        let mut mpc = qp(&[1.0, 2.0], bounds());
+        let u = mpc
+            .solve(
+                &DVector::from_vec(vec![-2.0, -2.0]),
+                &DVector::from_element(4, 1.5),
+                4,
+            )
+            .unwrap();
+        assert!((u - DVector::from_vec(vec![1.5, 1.0])).norm() < 1e-9);
+        // Only the upper bound of the first variable holds, with multiplier 2 - 1.5
+        assert!((&mpc.lambda - DVector::from_vec(vec![0.5, 0.0, 0.0, 0.0])).norm() < 1e-9);
+    }
+
+    #[test]
+    fn hildreth_solves_a_coupled_constraint() {
+        // min 0.5 |U|^2 - 2 (u1 + u2) s.t. u1 + u2 <= 2: the optimum is (1, 1)
+        let mut mpc = qp(&[1.0, 1.0], DMatrix::from_row_slice(1, 2, &[1.0, 1.0]));
+        let u = mpc
+            .solve(
+                &DVector::from_vec(vec![-2.0, -2.0]),
+                &DVector::from_element(1, 2.0),
+                1,
+            )
+            .unwrap();
+        assert!((u - DVector::from_vec(vec![1.0, 1.0])).norm() < 1e-9);
+    }
+
+    #[test]
+    fn hildreth_reports_an_infeasible_qp() {
+        // u <= -1 and -u <= -1 cannot both hold
+        let mut mpc = qp(&[1.0], DMatrix::from_row_slice(2, 1, &[1.0, -1.0]));
+        let u = mpc.solve(
+            &DVector::from_element(1, 0.0),
+            &DVector::from_element(2, -1.0),
+            2,
+        );
+        assert!(u.is_none());
+    }
+
+    #[test]
+    fn unreachable_speed_limit_falls_back_to_the_torque_limit() {
+        // The x wheel starts 5 rad/s above its limit and the torque limit only slows it by
+        // 2 rad/s per prediction step
+        let config = MpcConfig {
+            horizon: 10,
+            dt: 0.1,
+            weights: LqrWeights {
+                attitude: Vector3::repeat(1.0),
+                rate: Vector3::repeat(1.0),
+                torque: Vector3::repeat(1e3),
+            },
+            max_torque_rw: 1e-3,
+            max_speed_rw: 20.0,
+            max_iterations: 1000,
+            error: ErrorRepresentation::VectorPart,
+        };
+        let i_rw = Matrix3::identity() * 5e-5;
+        let speeds = Vector3::new(25.0, 0.0, 0.0);
+        let mut mpc = Mpc::new(config, Matrix3::identity() * 0.1, i_rw, Vec3(speeds)).unwrap();
+        let input = ControlInput {
+            q_error: Quaternion::default(),
+            error: Vec3(Vector3::new(0.1, 0.0, 0.0)),
+            w: Vec3::default(),
+            w_ref: Vec3::default(),
+            w_ref_dot: Vec3::default(),
+            h_rw: Some(Vec3(i_rw * speeds)),
+            dt: 0.1,
+            max_torque: 1e-3,
+        };
+        let torque = mpc.compute(&input).0;
+        assert_eq!((mpc.speed_limit_dropped, mpc.unsolved_steps), (1, 0));
+        // The attitude error is still corrected, within the torque limit
+        assert!(torque.x < 0.0 && torque.amax() <= 1e-3 * (1.0 + FEASIBILITY_TOLERANCE));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::discrete_time_model::{
+    control_law::{ControlInput, ControlLaw},
+    types::Vec3,
+};
+use nalgebra::Matrix3;
+
+/// Proportional-derivative law: torque = -kp * error - kd * (w - w_ref)
+pub struct PD {
+    kp: Matrix3<f64>,
+    kd: Matrix3<f64>,
+}
+
+impl PD {
+    pub fn new(kp: f64, kd: f64) -> Self {
+        PD::with_gains(
+            Matrix3::from_diagonal_element(kp),
+            Matrix3::from_diagonal_element(kd),
+        )
+    }
+
+    /// PD law with per-axis (or fully coupled) gain matrices, e.g. from `lqr::LqrGains`.
+    pub fn with_gains(kp: Matrix3<f64>, kd: Matrix3<f64>) -> Self {
+        PD { kp, kd }
+    }
+}
+
+impl ControlLaw for PD {
+    fn compute(&mut self, input: &ControlInput) -> Vec3 {
+        Vec3(-self.kp * input.error.0 - self.kd * input.w_error())
+    }
+}
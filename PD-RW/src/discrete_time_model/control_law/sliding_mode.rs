@@ -0,0 +1,74 @@
+use crate::discrete_time_model::{
+    control_law::{ControlInput, ControlLaw},
+    types::Vec3,
+};
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Function replacing sign(s) in the switching term, to limit chattering.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum BoundaryLayer {
+    /// Discontinuous sign(s): ideal sliding, chatters at the controller rate
+    None,
+    /// sat(s / width): linear inside a boundary layer of the given width
+    Saturation(f64),
+    /// tanh(s / width): smooth approximation of sign(s)
+    Tanh(f64),
+}
+
+impl BoundaryLayer {
+    fn apply(&self, s: f64) -> f64 {
+        match *self {
+            BoundaryLayer::None => s.signum(),
+            BoundaryLayer::Saturation(width) => (s / width).clamp(-1.0, 1.0),
+            BoundaryLayer::Tanh(width) => (s / width).tanh(),
+        }
+    }
+}
+
+/// Quaternion sliding-mode law (Crassidis & Markley).
+///
+/// Sliding surface s = (w - w_ref) + lambda * q_v, with q_v the vector part of the sign-corrected
+/// error. The equivalent control keeps the satellite on s = 0 with the nominal inertia, and the switching
+/// term -i_sat * k * sign(s) drives it onto the surface despite inertia errors bounded by k.
+pub struct SlidingMode {
+    lambda: f64,
+    k: Matrix3<f64>,
+    i_sat: Matrix3<f64>,
+    boundary_layer: BoundaryLayer,
+    s: Vector3<f64>,
+}
+
+impl SlidingMode {
+    pub fn new(lambda: f64, k: Vector3<f64>, i_sat: Matrix3<f64>, boundary_layer: BoundaryLayer) -> Self {
+        SlidingMode {
+            lambda,
+            k: Matrix3::from_diagonal(&k),
+            i_sat,
+            boundary_layer,
+            s: Vector3::zeros(),
+        }
+    }
+}
+
+impl ControlLaw for SlidingMode {
+    fn compute(&mut self, input: &ControlInput) -> Vec3 {
+        let w = input.w.0;
+        let w_error = input.w_error();
+        let q = input.q_error.0;
+        let q_v = q.imag();
+        self.s = w_error + self.lambda * q_v;
+
+        // d(q_v)/dt = 0.5 * (q_w * w_error + q_v x w_error)
+        let q_v_dot = 0.5 * (q.w * w_error + q_v.cross(&w_error));
+        let equivalent = input.w_ref_dot.0 - self.lambda * q_v_dot;
+        let switching = -self.k * self.s.map(|s| self.boundary_layer.apply(s));
+        let h_rw = input.h_rw.unwrap_or(Vec3::default()).0;
+        let gyroscopic = w.cross(&(self.i_sat * w + h_rw));
+        Vec3(self.i_sat * (equivalent + switching) + gyroscopic)
+    }
+
+    fn sliding_variable(&self) -> Option<Vec3> {
+        Some(Vec3(self.s))
+    }
+}
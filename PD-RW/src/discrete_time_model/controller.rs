@@ -1,22 +1,60 @@
-use crate::discrete_time_model::types::{Quaternion, Vec3};
+use crate::discrete_time_model::{
+    attitude_error::AttitudeError,
+    control_law::{ControlInput, ControlLaw},
+    recorder::{OutputPorts, Outputs},
+    types::{Fault, Mat3, Mode, Quaternion, Vec3},
+};
+use nalgebra::{Matrix3, UnitQuaternion};
 use xdevs::modeling::*;
 
+// Tolerance when comparing event times with control instants [s]
+const TIME_EPSILON: f64 = 1e-9;
+
 pub struct Controller {
     component: Component,
+    outputs: OutputPorts,
     i_w: InPort<Vec3>,
     i_q: InPort<Quaternion>,
+    i_h_rw: InPort<Vec3>,
+    i_q_target: InPort<Quaternion>,
+    i_w_ref: InPort<Vec3>,
+    i_w_ref_dot: InPort<Vec3>,
+    i_mode: InPort<Mode>,
+    i_fault: InPort<Fault>,
     o_torque: OutPort<Vec3>,
     o_qerror: OutPort<Quaternion>,
+    o_integrator: OutPort<Vec3>,
+    o_sliding: OutPort<Vec3>,
+    o_inertia: OutPort<Mat3>,
     w: Option<Vec3>,
     q: Option<Quaternion>,
+    h_rw: Option<Vec3>,
     torque: Option<Vec3>,
     q_error: Option<Quaternion>,
     sigma: f64,
-    time: f64,
+    // Control period, 0 computes a command on every measurement [s]
+    period: f64,
+    // Computational delay between the measurement and the command [s]
+    delay: f64,
+    next_control: f64,
+    // Current time, also valid inside confluent transitions [s]
+    clock: f64,
     q_target: Quaternion,
-    kp: f64,
-    kd: f64,
+    // Reference rate and acceleration of the target frame, expressed in the target frame
+    w_ref: Vec3,
+    w_ref_dot: Vec3,
+    control_law: Box<dyn ControlLaw>,
+    // Laws that replace `control_law` in specific ADCS modes
+    mode_laws: Vec<(Mode, Box<dyn ControlLaw>)>,
+    mode: Option<Mode>,
+    // Wheels isolated by the FDIR, which are no longer commanded
+    isolated_wheels: [bool; 3],
+    attitude_error: AttitudeError,
+    error: Option<Vec3>,
     max_torque_rw: f64,
+    last_control_time: Option<f64>,
+    // Inertia used by the feedforward (None disables it)
+    feedforward_inertia: Option<Matrix3<f64>>,
 }
 
 impl Controller {
@@ -24,40 +62,120 @@ impl Controller {
         name: &str,
         time: f64,
         q_target: Quaternion,
-        kp: f64,
-        kd: f64,
+        control_law: Box<dyn ControlLaw>,
+        attitude_error: AttitudeError,
         max_torque_rw: f64,
     ) -> Self {
         let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
         let i_w = component.add_in_port::<Vec3>("i_w");
         let i_q = component.add_in_port::<Quaternion>("i_q");
-        let o_t = component.add_out_port::<Vec3>("o_torque");
-        let o_qe = component.add_out_port::<Quaternion>("o_q_error");
+        let i_h = component.add_in_port::<Vec3>("i_h_rw");
+        let i_qt = component.add_in_port::<Quaternion>("i_q_target");
+        let i_wr = component.add_in_port::<Vec3>("i_w_ref");
+        let i_wrd = component.add_in_port::<Vec3>("i_w_ref_dot");
+        let i_m = component.add_in_port::<Mode>("i_mode");
+        let i_f = component.add_in_port::<Fault>("i_fault");
+        let o_t = outputs.add::<Vec3>(&mut component, "o_torque");
+        let o_qe = outputs.add::<Quaternion>(&mut component, "o_q_error");
+        let o_i = outputs.add::<Vec3>(&mut component, "o_integrator");
+        let o_s = outputs.add::<Vec3>(&mut component, "o_sliding");
+        let o_in = outputs.add::<Mat3>(&mut component, "o_inertia");
         Controller {
             component,
+            outputs,
             i_w: i_w,
             i_q: i_q,
+            i_h_rw: i_h,
+            i_q_target: i_qt,
+            i_w_ref: i_wr,
+            i_w_ref_dot: i_wrd,
+            i_mode: i_m,
+            i_fault: i_f,
             o_torque: o_t,
             o_qerror: o_qe,
+            o_integrator: o_i,
+            o_sliding: o_s,
+            o_inertia: o_in,
             // Initialize the torque command to zero
             w: None,
             q: None,
+            h_rw: None,
             torque: None,
             q_error: None,
             // Transition to Waiting state
             sigma: f64::INFINITY,
-            time: time,
-            // # q_target is the desired attitude in quaternion form
+            period: 0.0,
+            delay: 0.0,
+            next_control: time,
+            clock: time,
+            // # q_target is the desired attitude in quaternion form (until the Guidance updates it)
             q_target: q_target,
-            kp,
-            kd,
+            w_ref: Vec3::default(),
+            w_ref_dot: Vec3::default(),
+            control_law,
+            mode_laws: Vec::new(),
+            mode: None,
+            isolated_wheels: [false; 3],
+            attitude_error,
+            error: None,
             max_torque_rw: max_torque_rw,
+            last_control_time: None,
+            feedforward_inertia: None,
         }
     }
 
-    // Calculates the error quaternion
+    /// Adds the feedforward i_sat * dw_ref/dt + w x (i_sat * w + h_rw) to the command, supplying the
+    /// reference acceleration and cancelling the gyroscopic coupling that `SatelliteDynamics`
+    /// applies. Meant for laws that do not model them themselves (PD, PID, LQR, MPC);
+    /// `SlidingMode` and `Adaptive` already include both.
+    pub fn with_feedforward(mut self, i_sat: Matrix3<f64>) -> Self {
+        self.feedforward_inertia = Some(i_sat);
+        self
+    }
+
+    /// Computes a command every `period` seconds (a multiple of the sensor period) and outputs it
+    /// `delay` seconds after the measurement it is based on. The command is held by the wheels
+    /// until the next one (zero-order hold). The delay must be shorter than the period, which
+    /// `Scenario::validate` checks.
+    pub fn with_timing(mut self, period: f64, delay: f64) -> Self {
+        self.period = period;
+        self.delay = delay;
+        self
+    }
+
+    /// Uses `control_law` while the `ModeManager` reports `mode`. Laws keep their internal state
+    /// (integrators, estimates) while inactive.
+    pub fn with_mode_law(mut self, mode: Mode, control_law: Box<dyn ControlLaw>) -> Self {
+        self.mode_laws.push((mode, control_law));
+        self
+    }
+
+    // Law for the current mode, the default one when the mode has none
+    fn active_law(&self) -> &dyn ControlLaw {
+        match self.mode_laws.iter().find(|(m, _)| Some(*m) == self.mode) {
+            Some((_, law)) => law.as_ref(),
+            None => self.control_law.as_ref(),
+        }
+    }
+
+    fn active_law_mut(&mut self) -> &mut dyn ControlLaw {
+        let mode = self.mode;
+        match self.mode_laws.iter_mut().find(|(m, _)| Some(*m) == mode) {
+            Some((_, law)) => law.as_mut(),
+            None => self.control_law.as_mut(),
+        }
+    }
+
+    // Calculates the error quaternion, i.e. the body attitude relative to the target frame
     fn quaternion_error(q_current: Quaternion, q_target: Quaternion) -> Quaternion {
-        Quaternion(q_current.0 * q_target.0.conjugate())
+        Quaternion(q_target.0.conjugate() * q_current.0)
+    }
+}
+
+impl Outputs for Controller {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
     }
 }
 
@@ -75,6 +193,16 @@ impl Atomic for Controller {
         if let (Some(q_error), Some(torque)) = (self.q_error, self.torque) {
             unsafe { self.o_qerror.add_value(q_error) };
             unsafe { self.o_torque.add_value(torque) };
+            let control_law = self.active_law();
+            if let Some(integrator) = control_law.integrator() {
+                unsafe { self.o_integrator.add_value(integrator) };
+            }
+            if let Some(sliding) = control_law.sliding_variable() {
+                unsafe { self.o_sliding.add_value(sliding) };
+            }
+            if let Some(inertia) = control_law.inertia_estimate() {
+                unsafe { self.o_inertia.add_value(inertia) };
+            }
         }
     }
 
@@ -82,11 +210,14 @@ impl Atomic for Controller {
         // After sending the command, go back to waiting
         self.w = None;
         self.q = None;
+        self.clock = self.component.get_t_next();
         self.sigma = f64::INFINITY;
     }
 
     fn delta_ext(&mut self, e: f64) {
         self.sigma -= e;
+        self.clock = self.clock.max(self.component.get_t_last() + e);
+        let t = self.clock;
         // Receive new current attitude data
         if !unsafe { self.i_w.is_empty() } {
             self.w = unsafe { self.i_w.get_values().first().copied() };
@@ -94,31 +225,92 @@ impl Atomic for Controller {
         if !unsafe { self.i_q.is_empty() } {
             self.q = unsafe { self.i_q.get_values().first().copied() };
         }
+        // The wheel momentum is kept until the next update, it does not trigger a new command
+        if !unsafe { self.i_h_rw.is_empty() } {
+            self.h_rw = unsafe { self.i_h_rw.get_values().first().copied() };
+        }
+        for fault in unsafe { self.i_fault.get_values() } {
+            if let Fault::StuckWheel(wheel) | Fault::SpeedMismatch(wheel) = *fault {
+                self.isolated_wheels[wheel] = true;
+            }
+        }
+        if !unsafe { self.i_mode.is_empty() } {
+            self.mode = unsafe { self.i_mode.get_values().first().copied() };
+        }
+        // The Guidance reference is also held until it changes
+        if !unsafe { self.i_q_target.is_empty() } {
+            self.q_target = unsafe { self.i_q_target.get_values()[0] };
+        }
+        if !unsafe { self.i_w_ref.is_empty() } {
+            self.w_ref = unsafe { self.i_w_ref.get_values()[0] };
+        }
+        if !unsafe { self.i_w_ref_dot.is_empty() } {
+            self.w_ref_dot = unsafe { self.i_w_ref_dot.get_values()[0] };
+        }
+
+        // Measurements between two control instants only refresh the stored values
+        let due = t >= self.next_control - TIME_EPSILON;
+        if due && self.w.is_some() && self.q.is_some() {
+            while self.period > 0.0 && self.next_control <= t + TIME_EPSILON {
+                self.next_control += self.period;
+            }
 
-        if !self.w.is_none() && !self.q.is_none() {
             /*
-            1. Calculate attitude error quaternion (q_error = q_current * conjugate(q_target))
-            2. Extract error vector (e.g., from the vector part of q_error)
+            1. Calculate attitude error quaternion (q_error = conjugate(q_target) * q_current)
+            2. Extract error vector in the configured representation (shortest rotation)
              */
 
-            self.q_error = Some(Controller::quaternion_error(
+            let (q_error, error) = self.attitude_error.compute(Controller::quaternion_error(
                 self.q.unwrap(),
                 self.q_target,
             ));
+            self.q_error = Some(q_error);
+            self.error = Some(error);
+
+            // 3. Apply the control law:
+            let dt = self.last_control_time.map_or(0.0, |t_last| t - t_last);
+            self.last_control_time = Some(t);
+            if let (Some(q_error), Some(error), Some(w)) = (self.q_error, self.error, self.w) {
+                // Express the reference rates in the body frame; the derivative of the rotated
+                // rate picks up the transport term -(w - w_ref) x w_ref
+                let rotation = UnitQuaternion::new_normalize(q_error.0);
+                let w_ref = rotation.inverse_transform_vector(&self.w_ref.0);
+                let w_ref_dot = rotation.inverse_transform_vector(&self.w_ref_dot.0)
+                    - (w.0 - w_ref).cross(&w_ref);
+                let input = ControlInput {
+                    q_error,
+                    error,
+                    w,
+                    w_ref: Vec3(w_ref),
+                    w_ref_dot: Vec3(w_ref_dot),
+                    h_rw: self.h_rw,
+                    dt,
+                    max_torque: self.max_torque_rw,
+                };
+                let mut torque = self.active_law_mut().compute(&input).0;
 
-            // 3. Apply PD control law:
-            if let (Some(q_error), Some(w)) = (self.q_error.as_ref(), self.w.as_ref()) {
-                // imag() get the vector (x,y,z) (imaginary) part
-                self.torque = Some(Vec3(-self.kp * q_error.0.imag() - self.kd * w.0));
+                // 4. Feed the reference acceleration forward and cancel the gyroscopic coupling:
+                //    I * dw_ref/dt + w x (I * w + h_rw)
+                if let Some(i_sat) = &self.feedforward_inertia {
+                    let h_rw = self.h_rw.unwrap_or(Vec3::default()).0;
+                    torque += i_sat * w_ref_dot + w.0.cross(&(i_sat * w.0 + h_rw));
+                }
+                self.torque = Some(Vec3(torque));
             }
 
-            // Saturate the control torque
+            // Saturate the control torque and drop the isolated wheels
             if let Some(torque) = &self.torque {
-                self.torque = Some(torque.clamp(-self.max_torque_rw, self.max_torque_rw));
+                let mut torque = torque.clamp(-self.max_torque_rw, self.max_torque_rw);
+                for (wheel, isolated) in self.isolated_wheels.iter().enumerate() {
+                    if *isolated {
+                        torque.0[wheel] = 0.0;
+                    }
+                }
+                self.torque = Some(torque);
             }
 
-            // Schedule an immediate output
-            self.sigma = self.time;
+            // Schedule the output after the computational delay
+            self.sigma = self.delay;
         }
     }
 
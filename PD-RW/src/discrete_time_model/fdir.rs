@@ -0,0 +1,342 @@
+use crate::discrete_time_model::{
+    recorder::{OutputPorts, Outputs},
+    rw::{WheelFault, WheelFaultKind},
+    types::{Fault, Quaternion, Vec3},
+};
+use nalgebra::{UnitQuaternion, Vector3};
+use std::fmt;
+use xdevs::modeling::*;
+
+/// Detection thresholds of the `Fdir` component.
+#[derive(Debug, Clone, Copy)]
+pub struct FdirConfig {
+    // Inertia of each reaction wheel [kg m^2]
+    pub i_rw: Vector3<f64>,
+    // Wheel speed limit; wheels at the limit are not checked [rad/s]
+    pub max_speed_rw: f64,
+    // Commanded torque below which a wheel is not checked [Nm]
+    pub min_torque: f64,
+    // Measured over expected speed change below which a wheel is stuck
+    pub stuck_ratio: f64,
+    // Relative error of the speed change above which a wheel mismatches its command
+    pub mismatch_ratio: f64,
+    // Consecutive failed checks before a wheel is isolated
+    pub persistence: usize,
+    // Time without attitude or rate measurements before a dropout is declared [s]
+    pub dropout_timeout: f64,
+    // Largest admissible deviation of the measured quaternion norm from 1
+    pub norm_tolerance: f64,
+    // Body rate above which a change between consecutive attitude samples is implausible [rad/s]
+    pub max_rate: f64,
+}
+
+impl FdirConfig {
+    /// Default thresholds for wheels of inertia `i_rw` and sensors sampled every `sensor_period`
+    pub fn new(i_rw: Vector3<f64>, max_speed_rw: f64, sensor_period: f64) -> Self {
+        FdirConfig {
+            i_rw,
+            max_speed_rw,
+            min_torque: 1.0e-5,
+            stuck_ratio: 0.05,
+            mismatch_ratio: 0.2,
+            persistence: 10,
+            dropout_timeout: 5.0 * sensor_period,
+            norm_tolerance: 0.01,
+            max_rate: 1.0,
+        }
+    }
+}
+
+/// Fault detection, isolation and recovery.
+///
+/// Wheels are checked every time they report their speeds: the speed change since the previous
+/// report is compared with the one the commanded torque should have produced. A wheel failing
+/// the same check `persistence` times in a row is isolated and reported once on `o_fault`; the
+/// Controller stops commanding it and the ModeManager falls back to Safe mode. With only three
+/// orthogonal wheels there is no redundant wheel to reallocate the torque to.
+///
+/// The attitude sensors are declared lost when no measurement arrives for `dropout_timeout`, and
+/// a quaternion that is not unit or that rotates faster than `max_rate` is rejected. Both raise
+/// `o_sensor_valid = false` (and a fault report) until a plausible measurement arrives again.
+pub struct Fdir {
+    component: Component,
+    outputs: OutputPorts,
+    i_torque: InPort<Vec3>,
+    i_rw_speeds: InPort<Vec3>,
+    i_w: InPort<Vec3>,
+    i_q: InPort<Quaternion>,
+    o_fault: OutPort<Fault>,
+    o_sensor_valid: OutPort<bool>,
+    config: FdirConfig,
+    // Command in force since the previous wheel report
+    torque: Option<Vector3<f64>>,
+    last_speeds: Option<(f64, Vector3<f64>)>,
+    stuck_count: [usize; 3],
+    mismatch_count: [usize; 3],
+    isolated: [bool; 3],
+    last_q: Option<(f64, UnitQuaternion<f64>)>,
+    sensor_valid: bool,
+    // Time by which the next measurement must arrive [s]
+    deadline: f64,
+    pending_faults: Vec<Fault>,
+    pending_valid: Option<bool>,
+    sigma: f64,
+}
+
+impl Fdir {
+    pub fn new(name: &str, config: FdirConfig) -> Self {
+        let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
+        let i_t = component.add_in_port::<Vec3>("i_torque");
+        let i_rw = component.add_in_port::<Vec3>("i_rw_speeds");
+        let i_w = component.add_in_port::<Vec3>("i_w");
+        let i_q = component.add_in_port::<Quaternion>("i_q");
+        let o_f = outputs.add::<Fault>(&mut component, "o_fault");
+        let o_sv = outputs.add::<bool>(&mut component, "o_sensor_valid");
+        Fdir {
+            component,
+            outputs,
+            i_torque: i_t,
+            i_rw_speeds: i_rw,
+            i_w,
+            i_q,
+            o_fault: o_f,
+            o_sensor_valid: o_sv,
+            config,
+            torque: None,
+            last_speeds: None,
+            stuck_count: [0; 3],
+            mismatch_count: [0; 3],
+            isolated: [false; 3],
+            last_q: None,
+            sensor_valid: true,
+            // The dropout watchdog starts with the first measurement
+            deadline: f64::INFINITY,
+            pending_faults: Vec::new(),
+            pending_valid: None,
+            sigma: f64::INFINITY,
+        }
+    }
+
+    fn check_wheels(&mut self, t: f64, speeds: Vector3<f64>) {
+        if let (Some((t_prev, previous)), Some(torque)) = (self.last_speeds, self.torque) {
+            let c = self.config;
+            for i in 0..3 {
+                let at_limit = speeds[i].abs().max(previous[i].abs()) >= c.max_speed_rw;
+                if self.isolated[i] || torque[i].abs() < c.min_torque || at_limit {
+                    self.stuck_count[i] = 0;
+                    self.mismatch_count[i] = 0;
+                    continue;
+                }
+                let expected = -torque[i] / c.i_rw[i] * (t - t_prev);
+                let measured = speeds[i] - previous[i];
+                let stuck = measured.abs() < c.stuck_ratio * expected.abs();
+                let mismatch = !stuck && (measured - expected).abs() > c.mismatch_ratio * expected.abs();
+                self.stuck_count[i] = if stuck { self.stuck_count[i] + 1 } else { 0 };
+                self.mismatch_count[i] = if mismatch { self.mismatch_count[i] + 1 } else { 0 };
+
+                let fault = if self.stuck_count[i] >= c.persistence {
+                    Some(Fault::StuckWheel(i))
+                } else if self.mismatch_count[i] >= c.persistence {
+                    Some(Fault::SpeedMismatch(i))
+                } else {
+                    None
+                };
+                if let Some(fault) = fault {
+                    self.isolated[i] = true;
+                    self.pending_faults.push(fault);
+                }
+            }
+        }
+        self.last_speeds = Some((t, speeds));
+    }
+
+    fn check_attitude(&mut self, t: f64, q: Quaternion) {
+        let norm = q.0.norm();
+        let plausible = norm.is_finite()
+            && (norm - 1.0).abs() <= self.config.norm_tolerance
+            && self.last_q.is_none_or(|(t_prev, q_prev)| {
+                let angle = q_prev.angle_to(&UnitQuaternion::new_normalize(q.0));
+                angle <= self.config.max_rate * (t - t_prev)
+            });
+        if plausible {
+            self.last_q = Some((t, UnitQuaternion::new_normalize(q.0)));
+            self.set_sensor_valid(true, Fault::ImplausibleAttitude);
+        } else {
+            self.set_sensor_valid(false, Fault::ImplausibleAttitude);
+        }
+    }
+
+    // Publishes a change of sensor health, reporting `fault` when they become invalid
+    fn set_sensor_valid(&mut self, valid: bool, fault: Fault) {
+        if valid != self.sensor_valid {
+            self.sensor_valid = valid;
+            self.pending_valid = Some(valid);
+            if !valid {
+                self.pending_faults.push(fault);
+            }
+        }
+    }
+
+    fn schedule(&mut self, t: f64) {
+        let has_output = !self.pending_faults.is_empty() || self.pending_valid.is_some();
+        self.sigma = if has_output { 0.0 } else { self.deadline - t };
+    }
+}
+
+impl Outputs for Fdir {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
+    }
+}
+
+impl Atomic for Fdir {
+    fn get_component(&self) -> &Component {
+        &self.component
+    }
+
+    fn get_component_mut(&mut self) -> &mut Component {
+        &mut self.component
+    }
+
+    fn lambda(&self) {
+        for fault in &self.pending_faults {
+            unsafe { self.o_fault.add_value(*fault) };
+        }
+        if let Some(valid) = self.pending_valid {
+            unsafe { self.o_sensor_valid.add_value(valid) };
+        }
+    }
+
+    fn delta_int(&mut self) {
+        let t = self.component.get_t_next();
+        self.pending_faults.clear();
+        self.pending_valid = None;
+        if t >= self.deadline {
+            // No measurement for dropout_timeout: stop the watchdog until data returns
+            self.deadline = f64::INFINITY;
+            self.set_sensor_valid(false, Fault::SensorDropout);
+        }
+        self.schedule(t);
+    }
+
+    fn delta_ext(&mut self, e: f64) {
+        let t = self.component.get_t_last() + e;
+        // The wheels report at the start of each plant step, before the new command, so the
+        // speeds reflect the command held since their previous report
+        if let Some(speeds) = unsafe { self.i_rw_speeds.get_values().first().copied() } {
+            self.check_wheels(t, speeds.0);
+        }
+        if !unsafe { self.i_torque.is_empty() } {
+            self.torque = unsafe { self.i_torque.get_values().first().map(|v| v.0) };
+        }
+
+        let has_w = !unsafe { self.i_w.is_empty() };
+        let q = unsafe { self.i_q.get_values().first().copied() };
+        if has_w || q.is_some() {
+            self.deadline = t + self.config.dropout_timeout;
+            match q {
+                Some(q) => self.check_attitude(t, q),
+                // Rates alone end a dropout, the attitude check runs on the next quaternion
+                None if self.last_q.is_some() => self.set_sensor_valid(true, Fault::SensorDropout),
+                None => {}
+            }
+        }
+        self.schedule(t);
+    }
+
+    fn ta(&self) -> f64 {
+        self.sigma
+    }
+}
+
+/// Fault injected in a scenario, used to configure the faulty components and to score the FDIR.
+#[derive(Debug, Clone, Copy)]
+pub enum Injection {
+    Wheel(WheelFault),
+    // No measurements from start until end [s]
+    SensorDropout { start: f64, end: f64 },
+    // Corrupted attitude sample at the given time [s]
+    AttitudeGlitch(f64),
+}
+
+impl Injection {
+    fn time(&self) -> f64 {
+        match *self {
+            Injection::Wheel(fault) => fault.time,
+            Injection::SensorDropout { start, .. } => start,
+            Injection::AttitudeGlitch(time) => time,
+        }
+    }
+
+    /// Fault the FDIR should report for this injection
+    fn expected_fault(&self) -> Fault {
+        match *self {
+            Injection::Wheel(fault) => match fault.kind {
+                WheelFaultKind::Stuck => Fault::StuckWheel(fault.wheel),
+                WheelFaultKind::TorqueLoss(_) => Fault::SpeedMismatch(fault.wheel),
+            },
+            Injection::SensorDropout { .. } => Fault::SensorDropout,
+            Injection::AttitudeGlitch(_) => Fault::ImplausibleAttitude,
+        }
+    }
+}
+
+/// Detection latency of every injected fault and count of unexplained detections.
+pub struct FdirReport {
+    // (injected fault, injection time, detection latency if detected) [s]
+    pub detections: Vec<(Fault, f64, Option<f64>)>,
+    // Detections that match no injected fault
+    pub false_alarms: Vec<(f64, Fault)>,
+}
+
+impl FdirReport {
+    /// Matches each injection to the first later detection of the expected fault.
+    pub fn new(injections: &[Injection], detected: &[(f64, Fault)]) -> Self {
+        let mut matched = vec![false; detected.len()];
+        let mut detections = Vec::new();
+        for injection in injections {
+            let fault = injection.expected_fault();
+            let time = injection.time();
+            let hit = (0..detected.len())
+                .find(|&i| !matched[i] && detected[i].1 == fault && detected[i].0 >= time);
+            if let Some(i) = hit {
+                matched[i] = true;
+            }
+            detections.push((fault, time, hit.map(|i| detected[i].0 - time)));
+        }
+        let false_alarms = detected
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(detection, _)| *detection)
+            .collect();
+        FdirReport {
+            detections,
+            false_alarms,
+        }
+    }
+}
+
+impl fmt::Display for FdirReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "FDIR report")?;
+        for (fault, time, latency) in &self.detections {
+            match latency {
+                Some(latency) => writeln!(
+                    f,
+                    "  {} injected at {:.2} s: detected after {:.3} s",
+                    fault,
+                    time,
+                    latency
+                )?,
+                None => writeln!(f, "  {} injected at {:.2} s: missed", fault, time)?,
+            }
+        }
+        writeln!(f, "  False alarms: {}", self.false_alarms.len())?;
+        for (time, fault) in &self.false_alarms {
+            writeln!(f, "    {} at {:.2} s", fault, time)?;
+        }
+        Ok(())
+    }
+}
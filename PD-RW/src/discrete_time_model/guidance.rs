@@ -0,0 +1,182 @@
+use crate::discrete_time_model::{
+    recorder::{OutputPorts, Outputs},
+    types::{Mode, Quaternion, Vec3},
+};
+use nalgebra::{Matrix3, Rotation3, UnitQuaternion, Vector3};
+use xdevs::modeling::*;
+
+mod ground_target;
+mod inertial_hold;
+pub mod keep_out;
+mod nadir;
+pub mod orbit;
+mod scripted;
+mod slew;
+mod sun_pointing;
+mod waypoints;
+
+pub use ground_target::GroundTarget;
+pub use inertial_hold::InertialHold;
+pub use nadir::Nadir;
+pub use scripted::Scripted;
+pub use slew::{EigenAxisSlew, SlewLimits};
+pub use sun_pointing::SunPointing;
+pub use waypoints::WaypointSlew;
+
+// Step used to differentiate the target attitude numerically [s]
+const DIFFERENTIATION_STEP: f64 = 0.01;
+
+/// Target attitude with the feed-forward terms the Controller tracks.
+#[derive(Debug, Clone, Copy)]
+pub struct Reference {
+    // Target attitude (target frame to inertial frame)
+    pub q_target: Quaternion,
+    // Angular velocity of the target frame, expressed in the target frame [rad/s]
+    pub w_ref: Vec3,
+    // Angular acceleration of the target frame, expressed in the target frame [rad/s^2]
+    pub w_ref_dot: Vec3,
+}
+
+impl Reference {
+    /// Reference that holds `q_target` with no rotation.
+    pub fn fixed(q_target: Quaternion) -> Self {
+        Reference {
+            q_target,
+            w_ref: Vec3::default(),
+            w_ref_dot: Vec3::default(),
+        }
+    }
+}
+
+/// A pointing profile hosted by the `Guidance` atomic.
+pub trait GuidanceLaw {
+    /// Target attitude at simulation time `t` [s].
+    fn attitude(&self, t: f64) -> Quaternion;
+
+    /// Target attitude and feed-forward rates at `t`. By default the rates are obtained by
+    /// central differences of `attitude`; laws that know them analytically override this.
+    fn reference(&self, t: f64) -> Reference {
+        let d = DIFFERENTIATION_STEP;
+        let rate = |t: f64| {
+            let q0 = UnitQuaternion::new_normalize(self.attitude(t - d).0);
+            let q1 = UnitQuaternion::new_normalize(self.attitude(t + d).0);
+            (q0.inverse() * q1).scaled_axis() / (2.0 * d)
+        };
+        Reference {
+            q_target: self.attitude(t),
+            w_ref: Vec3(rate(t)),
+            w_ref_dot: Vec3((rate(t + d) - rate(t - d)) / (2.0 * d)),
+        }
+    }
+}
+
+/// Attitude whose body +Z axis points along `boresight` and whose body +X axis lies in the plane
+/// of `boresight` and `secondary` (both given in the inertial frame).
+pub fn pointing_attitude(boresight: Vector3<f64>, secondary: Vector3<f64>) -> Quaternion {
+    let z = boresight.normalize();
+    let y = z.cross(&secondary).normalize();
+    let x = y.cross(&z);
+    let rotation = Rotation3::from_matrix_unchecked(Matrix3::from_columns(&[x, y, z]));
+    Quaternion(*UnitQuaternion::from_rotation_matrix(&rotation).quaternion())
+}
+
+pub struct Guidance {
+    component: Component,
+    outputs: OutputPorts,
+    i_mode: InPort<Mode>,
+    o_q_target: OutPort<Quaternion>,
+    o_w_ref: OutPort<Vec3>,
+    o_w_ref_dot: OutPort<Vec3>,
+    guidance_law: Box<dyn GuidanceLaw>,
+    // Laws that replace `guidance_law` in specific ADCS modes
+    mode_laws: Vec<(Mode, Box<dyn GuidanceLaw>)>,
+    mode: Option<Mode>,
+    reference: Reference,
+    sigma: f64,
+    h: f64,
+}
+
+impl Guidance {
+    pub fn new(name: &str, time: f64, guidance_law: Box<dyn GuidanceLaw>, h: f64) -> Self {
+        let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
+        let i_m = component.add_in_port::<Mode>("i_mode");
+        let o_q = outputs.add::<Quaternion>(&mut component, "o_q_target");
+        let o_w = outputs.add::<Vec3>(&mut component, "o_w_ref");
+        let o_wd = outputs.add::<Vec3>(&mut component, "o_w_ref_dot");
+        let reference = guidance_law.reference(time);
+        Guidance {
+            component,
+            outputs,
+            i_mode: i_m,
+            o_q_target: o_q,
+            o_w_ref: o_w,
+            o_w_ref_dot: o_wd,
+            guidance_law,
+            mode_laws: Vec::new(),
+            mode: None,
+            reference,
+            // Publish the first reference immediately
+            sigma: 0.0,
+            h,
+        }
+    }
+
+    /// Uses `guidance_law` while the `ModeManager` reports `mode`. Laws are evaluated at the
+    /// absolute simulation time, they do not restart when their mode is entered.
+    pub fn with_mode_law(mut self, mode: Mode, guidance_law: Box<dyn GuidanceLaw>) -> Self {
+        self.mode_laws.push((mode, guidance_law));
+        self
+    }
+
+    // Law for the current mode, the default one when the mode has none
+    fn active_law(&self) -> &dyn GuidanceLaw {
+        match self.mode_laws.iter().find(|(m, _)| Some(*m) == self.mode) {
+            Some((_, law)) => law.as_ref(),
+            None => self.guidance_law.as_ref(),
+        }
+    }
+}
+
+impl Outputs for Guidance {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
+    }
+}
+
+impl Atomic for Guidance {
+    fn get_component(&self) -> &Component {
+        &self.component
+    }
+
+    fn get_component_mut(&mut self) -> &mut Component {
+        &mut self.component
+    }
+
+    fn lambda(&self) {
+        unsafe { self.o_q_target.add_value(self.reference.q_target) };
+        unsafe { self.o_w_ref.add_value(self.reference.w_ref) };
+        unsafe { self.o_w_ref_dot.add_value(self.reference.w_ref_dot) };
+    }
+
+    fn delta_int(&mut self) {
+        // Prepare the reference for the next publication
+        let t = self.component.get_t_next() + self.h;
+        self.reference = self.active_law().reference(t);
+        self.sigma = self.h;
+    }
+
+    fn delta_ext(&mut self, e: f64) {
+        self.sigma -= e;
+        if !unsafe { self.i_mode.is_empty() } {
+            self.mode = unsafe { self.i_mode.get_values().first().copied() };
+            // Replace the pending reference with the one of the new mode
+            let t = self.component.get_t_last() + e + self.sigma;
+            self.reference = self.active_law().reference(t);
+        }
+    }
+
+    fn ta(&self) -> f64 {
+        self.sigma
+    }
+}
@@ -0,0 +1,42 @@
+use crate::discrete_time_model::{
+    guidance::{
+        GuidanceLaw,
+        orbit::{CircularOrbit, EARTH_RADIUS, EARTH_ROTATION_RATE},
+        pointing_attitude,
+    },
+    types::Quaternion,
+};
+use nalgebra::Vector3;
+
+/// Keeps body +Z on a point of the rotating Earth, with body +X towards the orbital velocity.
+pub struct GroundTarget {
+    orbit: CircularOrbit,
+    // Geodetic latitude of the target (spherical Earth) [rad]
+    latitude: f64,
+    // Longitude of the target measured from the inertial X axis at t = 0 [rad]
+    longitude: f64,
+}
+
+impl GroundTarget {
+    pub fn new(orbit: CircularOrbit, latitude: f64, longitude: f64) -> Self {
+        GroundTarget {
+            orbit,
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Position of the target in the inertial frame at time t [m]
+    fn target_position(&self, t: f64) -> Vector3<f64> {
+        let (sl, cl) = self.latitude.sin_cos();
+        let (sa, ca) = (self.longitude + EARTH_ROTATION_RATE * t).sin_cos();
+        EARTH_RADIUS * Vector3::new(cl * ca, cl * sa, sl)
+    }
+}
+
+impl GuidanceLaw for GroundTarget {
+    fn attitude(&self, t: f64) -> Quaternion {
+        let line_of_sight = self.target_position(t) - self.orbit.position(t);
+        pointing_attitude(line_of_sight, self.orbit.velocity(t))
+    }
+}
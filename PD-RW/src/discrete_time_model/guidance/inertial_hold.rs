@@ -0,0 +1,25 @@
+use crate::discrete_time_model::{
+    guidance::{GuidanceLaw, Reference},
+    types::Quaternion,
+};
+
+/// Holds a fixed attitude with respect to the inertial frame.
+pub struct InertialHold {
+    q_target: Quaternion,
+}
+
+impl InertialHold {
+    pub fn new(q_target: Quaternion) -> Self {
+        InertialHold { q_target }
+    }
+}
+
+impl GuidanceLaw for InertialHold {
+    fn attitude(&self, _t: f64) -> Quaternion {
+        self.q_target
+    }
+
+    fn reference(&self, _t: f64) -> Reference {
+        Reference::fixed(self.q_target)
+    }
+}
@@ -0,0 +1,238 @@
+use crate::discrete_time_model::types::Quaternion;
+use nalgebra::{UnitQuaternion, Vector3};
+use std::f64::consts::TAU;
+
+/// Region a body-fixed boresight must stay out of: a cone of `half_angle` around an inertial
+/// direction (e.g. the Sun for a camera, the Earth for a star tracker). The direction is taken as
+/// fixed over the duration of the manoeuvre. The planner constrains the reference only, so
+/// `half_angle` should include a margin for the tracking error.
+#[derive(Debug, Clone, Copy)]
+pub struct KeepOutCone {
+    // Sensitive axis in the body frame
+    pub boresight: Vector3<f64>,
+    // Centre of the exclusion cone in the inertial frame
+    pub direction: Vector3<f64>,
+    // Half angle of the exclusion cone [rad]
+    pub half_angle: f64,
+}
+
+impl KeepOutCone {
+    fn admits(&self, q: &UnitQuaternion<f64>) -> bool {
+        q.transform_vector(&self.boresight).angle(&self.direction) > self.half_angle
+    }
+}
+
+/// Tuning of the roadmap planner.
+#[derive(Debug, Clone, Copy)]
+pub struct PlannerConfig {
+    // Number of attitudes sampled over the quaternion sphere
+    pub samples: usize,
+    // Longest eigen-axis rotation between two connected attitudes [rad]
+    pub max_edge_angle: f64,
+    // Angular step used to check a rotation against the cones [rad]
+    pub check_step: f64,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        PlannerConfig {
+            samples: 400,
+            max_edge_angle: 0.8,
+            check_step: 0.01,
+        }
+    }
+}
+
+/// Plans a re-orientation from `q_start` to `q_end` as a sequence of eigen-axis rotations that
+/// keep every boresight outside its cone, meant to be flown by `WaypointSlew`.
+///
+/// The direct rotation is used when it is feasible. Otherwise a roadmap is built over
+/// quasi-random attitudes (Halton samples mapped to the unit quaternions), the shortest feasible
+/// path is found with Dijkstra's algorithm and then shortened by skipping every waypoint that a
+/// feasible direct rotation can bypass. Being deterministic, a scenario always yields the same
+/// plan. Returns None when an end point lies inside a cone or no path exists in the roadmap.
+pub fn plan_slew(
+    q_start: Quaternion,
+    q_end: Quaternion,
+    cones: &[KeepOutCone],
+    config: &PlannerConfig,
+) -> Option<Vec<Quaternion>> {
+    let start = UnitQuaternion::new_normalize(q_start.0);
+    let end = UnitQuaternion::new_normalize(q_end.0);
+    let feasible = |q: &UnitQuaternion<f64>| cones.iter().all(|cone| cone.admits(q));
+    if !feasible(&start) || !feasible(&end) {
+        return None;
+    }
+    let segment_feasible = |a: &UnitQuaternion<f64>, b: &UnitQuaternion<f64>| {
+        let rotation = (a.inverse() * b).scaled_axis();
+        let steps = (rotation.norm() / config.check_step).ceil() as usize;
+        (1..steps).all(|i| {
+            let s = i as f64 / steps as f64;
+            feasible(&(a * UnitQuaternion::from_scaled_axis(rotation * s)))
+        })
+    };
+
+    let mut nodes = vec![start, end];
+    nodes.extend(
+        (1..=config.samples)
+            .map(halton_attitude)
+            .filter(|q| feasible(q)),
+    );
+
+    // Dijkstra over the implicit graph, edges are checked lazily when a node is expanded
+    let n = nodes.len();
+    let mut distance = vec![f64::INFINITY; n];
+    let mut previous = vec![usize::MAX; n];
+    let mut visited = vec![false; n];
+    distance[0] = 0.0;
+    while let Some(current) = (0..n)
+        .filter(|&i| !visited[i] && distance[i].is_finite())
+        .min_by(|&a, &b| distance[a].total_cmp(&distance[b]))
+    {
+        if current == 1 {
+            break;
+        }
+        visited[current] = true;
+        for next in 0..n {
+            if visited[next] {
+                continue;
+            }
+            let angle = nodes[current].angle_to(&nodes[next]);
+            if angle <= config.max_edge_angle
+                && distance[current] + angle < distance[next]
+                && segment_feasible(&nodes[current], &nodes[next])
+            {
+                distance[next] = distance[current] + angle;
+                previous[next] = current;
+            }
+        }
+    }
+    if !distance[1].is_finite() {
+        // A direct rotation longer than the edge limit may still be feasible
+        return segment_feasible(&start, &end).then(|| vec![q_start, q_end]);
+    }
+
+    let mut path = vec![1];
+    while *path.last().unwrap() != 0 {
+        path.push(previous[*path.last().unwrap()]);
+    }
+    path.reverse();
+
+    // Shortcut: from each kept waypoint jump to the furthest one reachable directly
+    let mut waypoints = vec![q_start];
+    let mut i = 0;
+    while i + 1 < path.len() {
+        let j = (i + 1..path.len())
+            .rev()
+            .find(|&j| j == i + 1 || segment_feasible(&nodes[path[i]], &nodes[path[j]]))
+            .unwrap();
+        waypoints.push(Quaternion(*nodes[path[j]].quaternion()));
+        i = j;
+    }
+    Some(waypoints)
+}
+
+/// i-th point of the Halton sequence in bases 2, 3 and 5, mapped to a uniformly distributed
+/// unit quaternion (Shoemake's subgroup algorithm)
+fn halton_attitude(i: usize) -> UnitQuaternion<f64> {
+    let halton = |base: usize| {
+        let (mut index, mut f, mut r) = (i, 1.0, 0.0);
+        while index > 0 {
+            f /= base as f64;
+            r += f * (index % base) as f64;
+            index /= base;
+        }
+        r
+    };
+    let (u1, u2, u3) = (halton(2), halton(3), halton(5));
+    let (s2, c2) = (TAU * u2).sin_cos();
+    let (s3, c3) = (TAU * u3).sin_cos();
+    UnitQuaternion::new_normalize(nalgebra::Quaternion::new(
+        u1.sqrt() * c3,
+        (1.0 - u1).sqrt() * s2,
+        (1.0 - u1).sqrt() * c2,
+        u1.sqrt() * s3,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Camera along the body z axis kept 0.5 rad away from a direction between the inertial x
+    // and z axes, as in scenarios/keep_out_slew.json
+    fn cone() -> KeepOutCone {
+        KeepOutCone {
+            boresight: Vector3::z(),
+            direction: Vector3::new(0.7, 0.0, 0.7).normalize(),
+            half_angle: 0.5,
+        }
+    }
+
+    fn attitude(axis: Vector3<f64>, angle: f64) -> Quaternion {
+        Quaternion(*UnitQuaternion::from_scaled_axis(axis * angle).quaternion())
+    }
+
+    // Smallest angle between the boresight and the cone direction along the eigen-axis rotations
+    // through `waypoints`
+    fn clearance(waypoints: &[Quaternion], cone: &KeepOutCone) -> f64 {
+        let mut clearance = f64::INFINITY;
+        for pair in waypoints.windows(2) {
+            let a = UnitQuaternion::new_normalize(pair[0].0);
+            let b = UnitQuaternion::new_normalize(pair[1].0);
+            let rotation = (a.inverse() * b).scaled_axis();
+            for i in 0..=1000 {
+                let q = a * UnitQuaternion::from_scaled_axis(rotation * (i as f64 / 1000.0));
+                let angle = q.transform_vector(&cone.boresight).angle(&cone.direction);
+                clearance = clearance.min(angle);
+            }
+        }
+        clearance
+    }
+
+    #[test]
+    fn direct_rotation_without_cones() {
+        let start = Quaternion::default();
+        let end = attitude(Vector3::y(), 2.5);
+        let plan = plan_slew(start, end, &[], &PlannerConfig::default()).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].0, start.0);
+        assert_eq!(plan[1].0, end.0);
+    }
+
+    #[test]
+    fn path_stays_outside_the_cone() {
+        let cone = cone();
+        let start = Quaternion::default();
+        let end = attitude(Vector3::y(), 2.5);
+        // The direct rotation sweeps the boresight through the cone
+        assert!(clearance(&[start, end], &cone) < cone.half_angle);
+
+        let config = PlannerConfig::default();
+        let plan = plan_slew(start, end, &[cone], &config).unwrap();
+        assert!(plan.len() > 2);
+        assert_eq!(plan[0].0, start.0);
+        assert!(
+            UnitQuaternion::new_normalize(plan[plan.len() - 1].0)
+                .angle_to(&UnitQuaternion::new_normalize(end.0))
+                < 1e-12
+        );
+        // Segments are checked every `check_step`, between checks the boresight cannot move
+        // further than that into the cone
+        assert!(clearance(&plan, &cone) > cone.half_angle - config.check_step);
+    }
+
+    #[test]
+    fn no_plan_ending_inside_the_cone() {
+        let cone = cone();
+        // Rotating the body z axis onto the cone direction
+        let end = attitude(Vector3::y(), std::f64::consts::FRAC_PI_4);
+        let plan = plan_slew(
+            Quaternion::default(),
+            end,
+            &[cone],
+            &PlannerConfig::default(),
+        );
+        assert!(plan.is_none());
+    }
+}
@@ -0,0 +1,21 @@
+use crate::discrete_time_model::{
+    guidance::{GuidanceLaw, orbit::CircularOrbit, pointing_attitude},
+    types::Quaternion,
+};
+
+/// Points body +Z at the centre of the Earth with body +X along the orbital velocity.
+pub struct Nadir {
+    orbit: CircularOrbit,
+}
+
+impl Nadir {
+    pub fn new(orbit: CircularOrbit) -> Self {
+        Nadir { orbit }
+    }
+}
+
+impl GuidanceLaw for Nadir {
+    fn attitude(&self, t: f64) -> Quaternion {
+        pointing_attitude(-self.orbit.position(t), self.orbit.velocity(t))
+    }
+}
@@ -0,0 +1,54 @@
+use nalgebra::Vector3;
+
+// Earth gravitational parameter [m^3/s^2]
+pub const EARTH_MU: f64 = 3.986004418e14;
+// Earth equatorial radius [m]
+pub const EARTH_RADIUS: f64 = 6.378137e6;
+// Earth rotation rate [rad/s]
+pub const EARTH_ROTATION_RATE: f64 = 7.2921159e-5;
+
+/// Circular Keplerian orbit, propagated analytically in the inertial frame.
+#[derive(Debug, Clone, Copy)]
+pub struct CircularOrbit {
+    // Orbit radius [m]
+    radius: f64,
+    // Inclination [rad]
+    inclination: f64,
+    // Right ascension of the ascending node [rad]
+    raan: f64,
+    // Argument of latitude at t = 0 [rad]
+    arg_latitude: f64,
+    // Mean motion [rad/s]
+    mean_motion: f64,
+}
+
+impl CircularOrbit {
+    pub fn new(altitude: f64, inclination: f64, raan: f64, arg_latitude: f64) -> Self {
+        let radius = EARTH_RADIUS + altitude;
+        CircularOrbit {
+            radius,
+            inclination,
+            raan,
+            arg_latitude,
+            mean_motion: (EARTH_MU / radius.powi(3)).sqrt(),
+        }
+    }
+
+    /// Position of the satellite at time t [m]
+    pub fn position(&self, t: f64) -> Vector3<f64> {
+        let (su, cu) = (self.arg_latitude + self.mean_motion * t).sin_cos();
+        let (so, co) = self.raan.sin_cos();
+        let (si, ci) = self.inclination.sin_cos();
+        self.radius * Vector3::new(co * cu - so * su * ci, so * cu + co * su * ci, su * si)
+    }
+
+    /// Velocity of the satellite at time t [m/s]
+    pub fn velocity(&self, t: f64) -> Vector3<f64> {
+        let (su, cu) = (self.arg_latitude + self.mean_motion * t).sin_cos();
+        let (so, co) = self.raan.sin_cos();
+        let (si, ci) = self.inclination.sin_cos();
+        self.radius
+            * self.mean_motion
+            * Vector3::new(-co * su - so * cu * ci, -so * su + co * cu * ci, cu * si)
+    }
+}
@@ -0,0 +1,31 @@
+use crate::discrete_time_model::{
+    guidance::{GuidanceLaw, Reference},
+    types::Quaternion,
+};
+
+/// Sequence of attitudes, each one commanded from its start time until the next one.
+/// Before the first start time the first attitude is held.
+pub struct Scripted {
+    // (start time [s], target attitude), sorted by start time
+    steps: Vec<(f64, Quaternion)>,
+}
+
+impl Scripted {
+    pub fn new(mut steps: Vec<(f64, Quaternion)>) -> Self {
+        assert!(!steps.is_empty(), "a scripted sequence needs at least one attitude");
+        steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Scripted { steps }
+    }
+}
+
+impl GuidanceLaw for Scripted {
+    fn attitude(&self, t: f64) -> Quaternion {
+        let current = self.steps.partition_point(|(start, _)| *start <= t);
+        self.steps[current.saturating_sub(1)].1
+    }
+
+    // Step changes have no meaningful rate: the Controller only sees the new target
+    fn reference(&self, t: f64) -> Reference {
+        Reference::fixed(self.attitude(t))
+    }
+}
@@ -0,0 +1,190 @@
+use crate::discrete_time_model::{
+    guidance::{GuidanceLaw, Reference},
+    types::{Quaternion, Vec3},
+};
+use nalgebra::{Matrix3, UnitQuaternion, Vector3};
+
+/// Rate and acceleration bounds of a slew about a given axis.
+#[derive(Debug, Clone, Copy)]
+pub struct SlewLimits {
+    // Body rate limit [rad/s]
+    pub max_rate: f64,
+    // Wheel torque per axis [Nm]
+    pub max_torque: f64,
+    // Wheel momentum per axis [Nms]
+    pub max_momentum: f64,
+    // Fraction of the wheel torque and momentum the profile may use, the rest is left to the
+    // feedback law (0 < usage <= 1)
+    pub usage: f64,
+    pub i_sat: Matrix3<f64>,
+}
+
+impl SlewLimits {
+    /// Largest rate and acceleration about `axis` that keep every wheel within its limits.
+    /// The slew is assumed to start at rest with the wheels unloaded, and the gyroscopic torque of
+    /// an eigen-axis rotation is neglected (it vanishes when the axis is principal).
+    fn along(&self, axis: &Vector3<f64>) -> (f64, f64) {
+        // Torque and momentum per unit acceleration and rate, on the most loaded wheel
+        let load = (self.i_sat * axis).amax();
+        let rate = self.max_rate.min(self.usage * self.max_momentum / load);
+        let acceleration = self.usage * self.max_torque / load;
+        (rate, acceleration)
+    }
+}
+
+/// Trapezoidal rate profile over an angle: accelerate, coast at the rate limit and brake. When
+/// the angle is too small to reach the rate limit the coast vanishes (bang-bang).
+#[derive(Debug, Clone, Copy)]
+pub struct SlewProfile {
+    angle: f64,
+    acceleration: f64,
+    // Duration of the acceleration (and braking) phase [s]
+    t_acceleration: f64,
+    // Duration of the coast phase [s]
+    t_coast: f64,
+}
+
+impl SlewProfile {
+    pub fn new(angle: f64, max_rate: f64, max_acceleration: f64) -> Self {
+        let (t_acceleration, t_coast) = if angle * max_acceleration >= max_rate * max_rate {
+            let t_acceleration = max_rate / max_acceleration;
+            (t_acceleration, angle / max_rate - t_acceleration)
+        } else {
+            ((angle / max_acceleration).sqrt(), 0.0)
+        };
+        SlewProfile {
+            angle,
+            acceleration: max_acceleration,
+            t_acceleration,
+            t_coast,
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        2.0 * self.t_acceleration + self.t_coast
+    }
+
+    /// Angle, rate and acceleration at time t from the start of the slew
+    pub fn sample(&self, t: f64) -> (f64, f64, f64) {
+        let a = self.acceleration;
+        let t1 = self.t_acceleration;
+        let t2 = t1 + self.t_coast;
+        let peak = a * t1;
+        if t <= 0.0 {
+            (0.0, 0.0, 0.0)
+        } else if t < t1 {
+            (0.5 * a * t * t, a * t, a)
+        } else if t < t2 {
+            (0.5 * a * t1 * t1 + peak * (t - t1), peak, 0.0)
+        } else if t < self.duration() {
+            let remaining = self.duration() - t;
+            (self.angle - 0.5 * a * remaining * remaining, a * remaining, -a)
+        } else {
+            (self.angle, 0.0, 0.0)
+        }
+    }
+}
+
+/// Rotation from `q_start` to `q_end` about the fixed eigen-axis, following a `SlewProfile`
+/// from `t_start`. Before the slew `q_start` is held and after it `q_end`.
+pub struct EigenAxisSlew {
+    q_start: UnitQuaternion<f64>,
+    // Eigen-axis, constant in both the start and the target frames
+    axis: Vector3<f64>,
+    t_start: f64,
+    profile: SlewProfile,
+}
+
+impl EigenAxisSlew {
+    pub fn new(q_start: Quaternion, q_end: Quaternion, t_start: f64, limits: &SlewLimits) -> Self {
+        let q_start = UnitQuaternion::new_normalize(q_start.0);
+        let q_end = UnitQuaternion::new_normalize(q_end.0);
+        // scaled_axis takes the shortest way round
+        let rotation = (q_start.inverse() * q_end).scaled_axis();
+        let angle = rotation.norm();
+        let axis = if angle > 0.0 { rotation / angle } else { Vector3::x() };
+        let (max_rate, max_acceleration) = limits.along(&axis);
+        EigenAxisSlew {
+            q_start,
+            axis,
+            t_start,
+            profile: SlewProfile::new(angle, max_rate, max_acceleration),
+        }
+    }
+
+    /// Time at which the target comes to rest at `q_end` [s]
+    pub fn end_time(&self) -> f64 {
+        self.t_start + self.profile.duration()
+    }
+}
+
+impl GuidanceLaw for EigenAxisSlew {
+    fn attitude(&self, t: f64) -> Quaternion {
+        self.reference(t).q_target
+    }
+
+    fn reference(&self, t: f64) -> Reference {
+        let (angle, rate, acceleration) = self.profile.sample(t - self.t_start);
+        let q = self.q_start * UnitQuaternion::from_scaled_axis(self.axis * angle);
+        Reference {
+            q_target: Quaternion(*q.quaternion()),
+            w_ref: Vec3(self.axis * rate),
+            w_ref_dot: Vec3(self.axis * acceleration),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_sample(profile: &SlewProfile, t: f64, expected: (f64, f64, f64)) {
+        let (angle, rate, acceleration) = profile.sample(t);
+        let error = (angle - expected.0)
+            .abs()
+            .max((rate - expected.1).abs())
+            .max((acceleration - expected.2).abs());
+        assert!(
+            error < 1e-12,
+            "t = {}: {:?} != {:?}",
+            t,
+            (angle, rate, acceleration),
+            expected
+        );
+    }
+
+    // Largest rate over a fine sampling of the profile
+    fn peak_rate(profile: &SlewProfile) -> f64 {
+        (0..=10000)
+            .map(|i| profile.sample(profile.duration() * i as f64 / 10000.0).1)
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn trapezoidal_profile() {
+        // 0.1 rad/s is reached after 10 s at 0.01 rad/s^2, covering 0.5 rad while accelerating
+        // and 0.5 rad while braking, so 1 rad is left to coast for 10 s
+        let profile = SlewProfile::new(2.0, 0.1, 0.01);
+        assert_eq!((profile.t_acceleration, profile.t_coast), (10.0, 10.0));
+        assert_eq!(profile.duration(), 30.0);
+        assert!((peak_rate(&profile) - 0.1).abs() < 1e-12);
+        assert_sample(&profile, 5.0, (0.125, 0.05, 0.01));
+        assert_sample(&profile, 15.0, (1.0, 0.1, 0.0));
+        assert_sample(&profile, 25.0, (1.875, 0.05, -0.01));
+        assert_sample(&profile, 31.0, (2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn triangular_profile() {
+        // 0.25 rad at 0.01 rad/s^2 takes 5 s accelerating and 5 s braking, peaking at 0.05 rad/s
+        // under the 0.1 rad/s limit
+        let profile = SlewProfile::new(0.25, 0.1, 0.01);
+        assert_eq!((profile.t_acceleration, profile.t_coast), (5.0, 0.0));
+        assert_eq!(profile.duration(), 10.0);
+        assert!((peak_rate(&profile) - 0.05).abs() < 1e-12);
+        assert_sample(&profile, 2.5, (0.03125, 0.025, 0.01));
+        assert_sample(&profile, 5.0, (0.125, 0.05, -0.01));
+        assert_sample(&profile, 7.5, (0.21875, 0.025, -0.01));
+        assert_sample(&profile, 10.0, (0.25, 0.0, 0.0));
+    }
+}
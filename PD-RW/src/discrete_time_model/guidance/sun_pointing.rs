@@ -0,0 +1,36 @@
+use crate::discrete_time_model::{
+    guidance::{GuidanceLaw, Reference},
+    types::Quaternion,
+};
+use nalgebra::{UnitQuaternion, Vector3};
+
+/// Turns a body axis (e.g. the solar panel normal) towards the Sun along the shortest rotation.
+/// The Sun direction is taken as fixed in the inertial frame over the simulated time span.
+pub struct SunPointing {
+    q_target: Quaternion,
+}
+
+impl SunPointing {
+    pub fn new(sun_direction: Vector3<f64>, body_axis: Vector3<f64>) -> Self {
+        let rotation = UnitQuaternion::rotation_between(&body_axis, &sun_direction)
+            // Anti-parallel axes: any half turn about an axis perpendicular to body_axis works
+            .unwrap_or_else(|| {
+                let perpendicular = body_axis.cross(&Vector3::x()).try_normalize(1e-9);
+                let axis = perpendicular.unwrap_or_else(|| body_axis.cross(&Vector3::y()).normalize());
+                UnitQuaternion::from_scaled_axis(axis * std::f64::consts::PI)
+            });
+        SunPointing {
+            q_target: Quaternion(*rotation.quaternion()),
+        }
+    }
+}
+
+impl GuidanceLaw for SunPointing {
+    fn attitude(&self, _t: f64) -> Quaternion {
+        self.q_target
+    }
+
+    fn reference(&self, _t: f64) -> Reference {
+        Reference::fixed(self.q_target)
+    }
+}
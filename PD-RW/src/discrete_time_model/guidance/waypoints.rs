@@ -0,0 +1,34 @@
+use crate::discrete_time_model::{
+    guidance::{EigenAxisSlew, GuidanceLaw, Reference, SlewLimits},
+    types::Quaternion,
+};
+
+/// Flies a waypoint sequence (e.g. from `keep_out::plan_slew`) as consecutive rest-to-rest
+/// eigen-axis slews, the first one starting at `t_start`.
+pub struct WaypointSlew {
+    slews: Vec<EigenAxisSlew>,
+}
+
+impl WaypointSlew {
+    pub fn new(waypoints: &[Quaternion], t_start: f64, limits: &SlewLimits) -> Self {
+        assert!(waypoints.len() >= 2, "a waypoint slew needs a start and an end attitude");
+        let mut slews: Vec<EigenAxisSlew> = Vec::new();
+        for pair in waypoints.windows(2) {
+            let t = slews.last().map_or(t_start, |slew| slew.end_time());
+            slews.push(EigenAxisSlew::new(pair[0], pair[1], t, limits));
+        }
+        WaypointSlew { slews }
+    }
+}
+
+impl GuidanceLaw for WaypointSlew {
+    fn attitude(&self, t: f64) -> Quaternion {
+        self.reference(t).q_target
+    }
+
+    fn reference(&self, t: f64) -> Reference {
+        // The slew in progress, or the last one once the sequence is complete
+        let current = self.slews.partition_point(|slew| slew.end_time() <= t);
+        self.slews[current.min(self.slews.len() - 1)].reference(t)
+    }
+}
@@ -0,0 +1,211 @@
+use crate::discrete_time_model::{
+    recorder::{OutputPorts, Outputs},
+    types::{Fault, Mode, Quaternion, Vec3},
+};
+use serde::{Deserialize, Serialize};
+use xdevs::modeling::*;
+
+/// Thresholds driving the mode transitions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModeCriteria {
+    // Body rate above which any mode falls back to Safe [rad/s]
+    pub safe_rate: f64,
+    // Body rate below which Safe hands over to SunAcquisition [rad/s]
+    pub detumbled_rate: f64,
+    // Pointing error below which the Sun counts as acquired [rad]
+    pub sun_acquired_angle: f64,
+    // Pointing error and body rate error below which CoarsePointing enters FinePointing
+    pub fine_entry_angle: f64,
+    pub fine_entry_rate: f64,
+    // Pointing error above which FinePointing drops back to CoarsePointing [rad]
+    pub fine_exit_angle: f64,
+    // Wheel speeds that start and end a momentum dump [rad/s]
+    pub dump_entry_speed: f64,
+    pub dump_exit_speed: f64,
+    // Time a transition condition must hold before the mode changes [s]
+    pub dwell_time: f64,
+}
+
+impl ModeCriteria {
+    /// Default thresholds for wheels saturating at `max_speed_rw` [rad/s]
+    pub fn new(max_speed_rw: f64) -> Self {
+        ModeCriteria {
+            safe_rate: 0.5,
+            detumbled_rate: 0.02,
+            sun_acquired_angle: 0.2,
+            fine_entry_angle: 0.02,
+            fine_entry_rate: 0.005,
+            fine_exit_angle: 0.05,
+            dump_entry_speed: 0.8 * max_speed_rw,
+            dump_exit_speed: 0.3 * max_speed_rw,
+            dwell_time: 1.0,
+        }
+    }
+}
+
+/// ADCS mode state machine.
+///
+/// Safe -> SunAcquisition -> CoarsePointing <-> FinePointing, with MomentumDump entered from the
+/// pointing modes when a wheel runs fast and left for CoarsePointing once they have slowed down.
+/// Any mode falls back to Safe, without waiting, when the body rate is excessive, the sensors
+/// are reported invalid or a wheel has been isolated, in which case Safe is latched for the rest
+/// of the run. The other transitions fire once their condition has held for `dwell_time`. The
+/// pointing error is read from the Controller's `o_q_error`, so it refers to the target of the
+/// current mode. Every new mode is published on `o_mode` for the Controller and the Guidance to
+/// switch their laws. The model has no external torque actuator yet, so MomentumDump only
+/// exercises the mode logic: its laws cannot reduce the total momentum.
+pub struct ModeManager {
+    component: Component,
+    outputs: OutputPorts,
+    i_w: InPort<Vec3>,
+    i_rw_speeds: InPort<Vec3>,
+    i_q_error: InPort<Quaternion>,
+    i_sensor_valid: InPort<bool>,
+    i_fault: InPort<Fault>,
+    o_mode: OutPort<Mode>,
+    criteria: ModeCriteria,
+    mode: Mode,
+    // Mode whose transition condition holds, and since when
+    candidate: Option<(Mode, f64)>,
+    w: Option<Vec3>,
+    rw_speeds: Option<Vec3>,
+    q_error: Option<Quaternion>,
+    sensor_valid: bool,
+    wheel_fault: bool,
+    sigma: f64,
+}
+
+impl ModeManager {
+    pub fn new(name: &str, initial_mode: Mode, criteria: ModeCriteria) -> Self {
+        let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
+        let i_w = component.add_in_port::<Vec3>("i_w");
+        let i_rw = component.add_in_port::<Vec3>("i_rw_speeds");
+        let i_qe = component.add_in_port::<Quaternion>("i_q_error");
+        let i_sv = component.add_in_port::<bool>("i_sensor_valid");
+        let i_f = component.add_in_port::<Fault>("i_fault");
+        let o_m = outputs.add::<Mode>(&mut component, "o_mode");
+        ModeManager {
+            component,
+            outputs,
+            i_w,
+            i_rw_speeds: i_rw,
+            i_q_error: i_qe,
+            i_sensor_valid: i_sv,
+            i_fault: i_f,
+            o_mode: o_m,
+            criteria,
+            mode: initial_mode,
+            candidate: None,
+            w: None,
+            rw_speeds: None,
+            q_error: None,
+            // Sensors are assumed valid until told otherwise
+            sensor_valid: true,
+            wheel_fault: false,
+            // Announce the initial mode
+            sigma: 0.0,
+        }
+    }
+
+    /// Mode the current measurements call for, and whether it must be entered without dwelling
+    fn next_mode(&self) -> (Mode, bool) {
+        let c = &self.criteria;
+        let rate = self.w.map_or(0.0, |w| w.0.norm());
+        let wheel_speed = self.rw_speeds.map_or(0.0, |s| s.0.amax());
+        // Rotation angle of the error quaternion
+        let angle = self
+            .q_error
+            .map_or(f64::INFINITY, |q| 2.0 * q.0.imag().norm().atan2(q.0.w.abs()));
+
+        if !self.sensor_valid || self.wheel_fault || rate > c.safe_rate {
+            return (Mode::Safe, true);
+        }
+        let next = match self.mode {
+            Mode::Safe if rate < c.detumbled_rate => Mode::SunAcquisition,
+            Mode::SunAcquisition if angle < c.sun_acquired_angle && rate < c.detumbled_rate => {
+                Mode::CoarsePointing
+            }
+            Mode::CoarsePointing | Mode::FinePointing if wheel_speed > c.dump_entry_speed => {
+                Mode::MomentumDump
+            }
+            Mode::CoarsePointing if angle < c.fine_entry_angle && rate < c.fine_entry_rate => {
+                Mode::FinePointing
+            }
+            Mode::FinePointing if angle > c.fine_exit_angle => Mode::CoarsePointing,
+            Mode::MomentumDump if wheel_speed < c.dump_exit_speed => Mode::CoarsePointing,
+            mode => mode,
+        };
+        (next, false)
+    }
+}
+
+impl Outputs for ModeManager {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
+    }
+}
+
+impl Atomic for ModeManager {
+    fn get_component(&self) -> &Component {
+        &self.component
+    }
+
+    fn get_component_mut(&mut self) -> &mut Component {
+        &mut self.component
+    }
+
+    fn lambda(&self) {
+        unsafe { self.o_mode.add_value(self.mode) };
+    }
+
+    fn delta_int(&mut self) {
+        self.sigma = f64::INFINITY;
+    }
+
+    fn delta_ext(&mut self, e: f64) {
+        self.sigma -= e;
+        if !unsafe { self.i_w.is_empty() } {
+            self.w = unsafe { self.i_w.get_values().first().copied() };
+        }
+        if !unsafe { self.i_rw_speeds.is_empty() } {
+            self.rw_speeds = unsafe { self.i_rw_speeds.get_values().first().copied() };
+        }
+        if !unsafe { self.i_q_error.is_empty() } {
+            self.q_error = unsafe { self.i_q_error.get_values().first().copied() };
+        }
+        if !unsafe { self.i_sensor_valid.is_empty() } {
+            self.sensor_valid = unsafe { self.i_sensor_valid.get_values()[0] };
+        }
+        for fault in unsafe { self.i_fault.get_values() } {
+            if let Fault::StuckWheel(_) | Fault::SpeedMismatch(_) = fault {
+                self.wheel_fault = true;
+            }
+        }
+
+        let t = self.component.get_t_last() + e;
+        let (next, immediate) = self.next_mode();
+        if next == self.mode {
+            self.candidate = None;
+            return;
+        }
+        let since = match self.candidate {
+            Some((mode, since)) if mode == next => since,
+            _ => t,
+        };
+        if immediate || t - since >= self.criteria.dwell_time {
+            self.mode = next;
+            self.candidate = None;
+            // The error refers to the previous target until the Controller reports again
+            self.q_error = None;
+            self.sigma = 0.0;
+        } else {
+            self.candidate = Some((next, since));
+        }
+    }
+
+    fn ta(&self) -> f64 {
+        self.sigma
+    }
+}
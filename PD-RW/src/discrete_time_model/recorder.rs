@@ -0,0 +1,301 @@
+use crate::discrete_time_model::types::{Fault, Mat3, Mode, Quaternion, Vec3};
+use serde::{Deserialize, Serialize};
+use xdevs::modeling::{port::PortVal, *};
+
+/// Value a `Recorder` can be coupled to, flattened into named components for the results and the
+/// plots.
+pub trait Recordable: PortVal + Copy {
+    const PORT_TYPE: PortType;
+    const COMPONENTS: &'static [&'static str];
+
+    fn components(&self) -> Vec<f64>;
+}
+
+impl Recordable for Vec3 {
+    const PORT_TYPE: PortType = PortType::Vec3;
+    const COMPONENTS: &'static [&'static str] = &["x", "y", "z"];
+
+    fn components(&self) -> Vec<f64> {
+        self.0.iter().copied().collect()
+    }
+}
+
+impl Recordable for Quaternion {
+    const PORT_TYPE: PortType = PortType::Quaternion;
+    const COMPONENTS: &'static [&'static str] = &["w", "x", "y", "z"];
+
+    fn components(&self) -> Vec<f64> {
+        let q = &self.0;
+        vec![q.w, q.i, q.j, q.k]
+    }
+}
+
+impl Recordable for Mat3 {
+    const PORT_TYPE: PortType = PortType::Mat3;
+    const COMPONENTS: &'static [&'static str] =
+        &["xx", "xy", "xz", "yx", "yy", "yz", "zx", "zy", "zz"];
+
+    fn components(&self) -> Vec<f64> {
+        // Row by row
+        self.0.transpose().iter().copied().collect()
+    }
+}
+
+impl Recordable for Mode {
+    const PORT_TYPE: PortType = PortType::Mode;
+    // Position of the mode in `Mode::ALL`
+    const COMPONENTS: &'static [&'static str] = &["mode"];
+
+    fn components(&self) -> Vec<f64> {
+        vec![self.index() as f64]
+    }
+}
+
+impl Recordable for bool {
+    const PORT_TYPE: PortType = PortType::Bool;
+    const COMPONENTS: &'static [&'static str] = &["value"];
+
+    fn components(&self) -> Vec<f64> {
+        vec![*self as u8 as f64]
+    }
+}
+
+impl Recordable for Fault {
+    const PORT_TYPE: PortType = PortType::Fault;
+    // Position of the variant in the declaration of `Fault` and the wheel at fault, -1 for the
+    // sensor faults
+    const COMPONENTS: &'static [&'static str] = &["kind", "wheel"];
+
+    fn components(&self) -> Vec<f64> {
+        match self {
+            Fault::StuckWheel(wheel) => vec![0.0, *wheel as f64],
+            Fault::SpeedMismatch(wheel) => vec![1.0, *wheel as f64],
+            Fault::SensorDropout => vec![2.0, -1.0],
+            Fault::ImplausibleAttitude => vec![3.0, -1.0],
+        }
+    }
+}
+
+/// Type of the values of an output port, one per `Recordable` type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortType {
+    Vec3,
+    Quaternion,
+    Mat3,
+    Mode,
+    Bool,
+    Fault,
+}
+
+/// Output ports of a component and the type of their values, declared as the component creates
+/// them so that a `Recorder` can be coupled to any of them.
+#[derive(Debug, Clone, Default)]
+pub struct OutputPorts(Vec<(&'static str, PortType)>);
+
+impl OutputPorts {
+    /// Adds the output port `name` to `component`
+    pub fn add<T: Recordable>(
+        &mut self,
+        component: &mut Component,
+        name: &'static str,
+    ) -> OutPort<T> {
+        self.0.push((name, T::PORT_TYPE));
+        component.add_out_port::<T>(name)
+    }
+
+    pub fn port_type(&self, name: &str) -> Option<PortType> {
+        self.0
+            .iter()
+            .find(|(port, _)| *port == name)
+            .map(|(_, port_type)| *port_type)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.0.iter().map(|(port, _)| *port).collect()
+    }
+}
+
+/// Atomic model creating its output ports through `OutputPorts`.
+pub trait Outputs {
+    fn output_ports(&self) -> &OutputPorts;
+}
+
+/// Reason a probe cannot be coupled to the model.
+#[derive(Debug)]
+pub enum ProbeError {
+    // The name is already taken by a component of the model or another probe
+    NameTaken,
+    // The component is not in the model, with the components that are
+    UnknownComponent(Vec<String>),
+    // The component has no such output port, with the ports it has
+    UnknownPort(Vec<&'static str>),
+    // The values of the port have no such component, with the ones they have
+    UnknownTriggerComponent(&'static [&'static str]),
+}
+
+/// Condition starting a recording: the first sample whose component, or the norm of all of them
+/// when None, crosses `level` upwards when `rising` and downwards otherwise.
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    // Name of the component, see `Recordable::COMPONENTS`
+    pub component: Option<String>,
+    pub level: f64,
+    pub rising: bool,
+}
+
+/// Samples a `Recorder` keeps.
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    // Keeps one sample out of `decimation`, counted from the first one recorded
+    pub decimation: usize,
+    // Time window of the recording [s]
+    pub start: f64,
+    pub end: f64,
+    pub trigger: Option<Trigger>,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        RecorderConfig {
+            decimation: 1,
+            start: 0.0,
+            end: f64::INFINITY,
+            trigger: None,
+        }
+    }
+}
+
+/// Samples of a `Recorder` with the simulation time they were received at [s], one value per
+/// component.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub name: String,
+    pub components: Vec<String>,
+    pub samples: Vec<(f64, Vec<f64>)>,
+}
+
+/// Recorder of any type read back once the simulation is over, see `DiscreteTimeModel::recorders`.
+pub trait Probe {
+    fn recording(&self) -> Recording;
+}
+
+/// Recordings of `recorders`. The coupled model holding them must still be alive.
+pub fn recordings(recorders: &[*const dyn Probe]) -> Vec<Recording> {
+    recorders
+        .iter()
+        .map(|recorder| unsafe { &**recorder }.recording())
+        .collect()
+}
+
+/// Records every value received on `i_value`, coupled to an output port carrying values of type
+/// `T`, within the window and after the trigger of its `RecorderConfig`.
+pub struct Recorder<T: Recordable> {
+    component: Component,
+    i_value: InPort<T>,
+    sigma: f64,
+    config: RecorderConfig,
+    // Index of the component the trigger watches, None for the norm
+    trigger_component: Option<usize>,
+    triggered: bool,
+    // Samples within the window since the trigger, kept or not
+    received: usize,
+    history: Vec<(f64, T)>,
+}
+
+impl<T: Recordable> Recorder<T> {
+    pub fn new(name: &str, config: RecorderConfig) -> Result<Self, ProbeError> {
+        let trigger_component = match config.trigger.as_ref().and_then(|t| t.component.as_ref()) {
+            Some(name) => Some(
+                T::COMPONENTS
+                    .iter()
+                    .position(|c| c == name)
+                    .ok_or(ProbeError::UnknownTriggerComponent(T::COMPONENTS))?,
+            ),
+            None => None,
+        };
+        let mut component = Component::new(name);
+        let i_value = component.add_in_port::<T>("i_value");
+        Ok(Recorder {
+            component,
+            i_value,
+            // Transition to Waiting state
+            sigma: f64::INFINITY,
+            trigger_component,
+            triggered: config.trigger.is_none(),
+            config,
+            received: 0,
+            history: Vec::new(),
+        })
+    }
+
+    fn fires(&self, trigger: &Trigger, value: &T) -> bool {
+        let components = value.components();
+        let value = match self.trigger_component {
+            Some(i) => components[i],
+            None => components.iter().map(|c| c * c).sum::<f64>().sqrt(),
+        };
+        match trigger.rising {
+            true => value > trigger.level,
+            false => value < trigger.level,
+        }
+    }
+}
+
+impl<T: Recordable> Probe for Recorder<T> {
+    fn recording(&self) -> Recording {
+        Recording {
+            name: self.component.get_name().to_string(),
+            components: T::COMPONENTS.iter().map(|c| c.to_string()).collect(),
+            samples: self
+                .history
+                .iter()
+                .map(|(t, value)| (*t, value.components()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Recordable> Atomic for Recorder<T> {
+    fn get_component(&self) -> &Component {
+        &self.component
+    }
+
+    fn get_component_mut(&mut self) -> &mut Component {
+        &mut self.component
+    }
+
+    fn lambda(&self) {}
+
+    fn delta_int(&mut self) {
+        self.sigma = f64::INFINITY;
+    }
+
+    fn delta_ext(&mut self, e: f64) {
+        self.sigma -= e;
+        let t = self.component.get_t_last() + e;
+        if t < self.config.start || t > self.config.end {
+            return;
+        }
+        for value in unsafe { self.i_value.get_values() } {
+            if !self.triggered {
+                let trigger = self
+                    .config
+                    .trigger
+                    .as_ref()
+                    .expect("untriggered recorders have a trigger");
+                self.triggered = self.fires(trigger, value);
+                if !self.triggered {
+                    continue;
+                }
+            }
+            if self.received.is_multiple_of(self.config.decimation) {
+                self.history.push((t, *value));
+            }
+            self.received += 1;
+        }
+    }
+
+    fn ta(&self) -> f64 {
+        self.sigma
+    }
+}
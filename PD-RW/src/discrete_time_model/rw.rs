@@ -1,21 +1,65 @@
-use crate::discrete_time_model::types::Vec3;
+use crate::discrete_time_model::{
+    recorder::{OutputPorts, Outputs},
+    types::Vec3,
+};
 use nalgebra::Matrix3;
 use xdevs::modeling::*;
 
+/// Failure injected into one reaction wheel.
+#[derive(Debug, Clone, Copy)]
+pub enum WheelFaultKind {
+    /// The wheel seizes: its speed freezes and it delivers no torque
+    Stuck,
+    /// The wheel delivers only this fraction of the commanded torque
+    TorqueLoss(f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WheelFault {
+    // Index of the wheel (0, 1 or 2 for the x, y and z wheels)
+    pub wheel: usize,
+    // Time the fault appears [s]
+    pub time: f64,
+    pub kind: WheelFaultKind,
+}
+
+// Tolerance when comparing event times with update instants [s]
+const TIME_EPSILON: f64 = 1e-9;
+
+/// Reaction wheel assembly.
+///
+/// The wheel speeds are integrated at the plant step `h` and published after every step. A new
+/// torque command is latched at the next actuator update instant (every `actuator_period`, at
+/// once when the period equals the command rate) and the resulting torque on the body is
+/// published on `o_torque`; between updates the latched command is held.
 pub struct RW {
     component: Component,
+    outputs: OutputPorts,
     i_torque: InPort<Vec3>,
     o_h_rw: OutPort<Vec3>,
     o_rw_speeds: OutPort<Vec3>,
+    o_torque: OutPort<Vec3>,
     rw_speeds: Vec3,
+    // Latched command, and the latest command waiting for the next actuator update
     torque: Option<Vec3>,
-    h_rw: Vec3,
+    command: Option<Vec3>,
     sigma: f64,
-    time: f64,
     inertia_rw: Matrix3<f64>,
     max_speed_rw: f64,
     h: f64,
+    actuator_period: f64,
     rw_speeds_dot: Vec3,
+    // Torque the wheels actually apply to the body [Nm]
+    delivered_torque: Vec3,
+    faults: Vec<WheelFault>,
+    // Current time, also valid inside confluent transitions [s]
+    clock: f64,
+    next_step: f64,
+    next_update: f64,
+    // The first publication is the initial state, later ones follow an integration step
+    first_step: bool,
+    // The next output is the delivered torque rather than the wheel state
+    torque_out: bool,
 }
 
 impl RW {
@@ -28,47 +72,98 @@ impl RW {
         h: f64,
     ) -> Self {
         let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
         let i_t = component.add_in_port::<Vec3>("i_torque");
-        let o_h = component.add_out_port::<Vec3>("o_h_rw");
-        let o_rw = component.add_out_port::<Vec3>("o_rw_speeds");
+        let o_h = outputs.add::<Vec3>(&mut component, "o_h_rw");
+        let o_rw = outputs.add::<Vec3>(&mut component, "o_rw_speeds");
+        let o_t = outputs.add::<Vec3>(&mut component, "o_torque");
         RW {
             component: component,
+            outputs,
             i_torque: i_t,
             o_h_rw: o_h,
             o_rw_speeds: o_rw,
+            o_torque: o_t,
             rw_speeds: rw_speeds_initial,
             torque: None,
-            // Initial reaction wheel angular momentum
-            h_rw: Vec3(i_rw * rw_speeds_initial.0),
-            // Transition to Waiting state
-            sigma: f64::INFINITY,
-            time: time,
+            command: None,
+            // Publish the initial state immediately
+            sigma: 0.0,
             inertia_rw: i_rw,
             max_speed_rw: m_speed_rw,
             h: h,
+            actuator_period: h,
             rw_speeds_dot: Vec3::default(),
+            delivered_torque: Vec3::default(),
+            faults: Vec::new(),
+            clock: time,
+            next_step: time,
+            next_update: time,
+            first_step: true,
+            torque_out: false,
         }
     }
 
-    fn compute_derivatives(&mut self) {
+    /// Injects `fault` into the wheels.
+    pub fn with_fault(mut self, fault: WheelFault) -> Self {
+        self.faults.push(fault);
+        self
+    }
+
+    /// Latches new commands only every `actuator_period` seconds (a multiple of `h`).
+    pub fn with_actuator_period(mut self, actuator_period: f64) -> Self {
+        self.actuator_period = actuator_period;
+        self
+    }
+
+    fn compute_derivatives(&mut self, t: f64) {
         if let Some(torque) = &self.torque {
+            // Scale the command of each wheel by its health at time t
+            let mut delivered = torque.0;
+            for fault in self.faults.iter().filter(|fault| fault.time <= t) {
+                delivered[fault.wheel] *= match fault.kind {
+                    WheelFaultKind::Stuck => 0.0,
+                    WheelFaultKind::TorqueLoss(fraction) => fraction,
+                };
+            }
+            self.delivered_torque = Vec3(delivered);
             if let Some(inertia_inv) = self.inertia_rw.try_inverse() {
-                self.rw_speeds_dot = Vec3(inertia_inv * -&torque.0);
+                self.rw_speeds_dot = Vec3(inertia_inv * -delivered);
             }
         }
     }
 
-    fn compute_next_state(&mut self, h: f64) {
-        // Compute the next state:
+    // Wheel speeds at the end of the current plant step
+    fn next_speeds(&self) -> Vec3 {
+        if self.first_step {
+            return self.rw_speeds;
+        }
         // Calculate the next state of the reaction wheels (Euler integration)
-        self.rw_speeds = Vec3(self.rw_speeds.0 + h * self.rw_speeds_dot.0);
-
+        let rw_speeds = Vec3(self.rw_speeds.0 + self.h * self.rw_speeds_dot.0);
         // Limit the speed of the reaction wheels
-        // At the moment, I am not applying this limit
-        self.rw_speeds = self.rw_speeds.clamp(-self.max_speed_rw, self.max_speed_rw);
+        rw_speeds.clamp(-self.max_speed_rw, self.max_speed_rw)
+    }
+
+    // Latches the pending command if an actuator update is due
+    fn update_actuator(&mut self) {
+        if self.command.is_some() && self.clock >= self.next_update - TIME_EPSILON {
+            self.torque = self.command.take();
+            self.compute_derivatives(self.clock);
+            while self.next_update <= self.clock + TIME_EPSILON {
+                self.next_update += self.actuator_period;
+            }
+            self.torque_out = true;
+        }
+    }
 
-        // Update the wheel momentum
-        self.h_rw = Vec3(self.inertia_rw * self.rw_speeds.0);
+    fn schedule(&mut self) {
+        self.sigma = if self.torque_out { 0.0 } else { self.next_step - self.clock };
+    }
+}
+
+impl Outputs for RW {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
     }
 }
 
@@ -82,22 +177,39 @@ impl Atomic for RW {
     }
 
     fn lambda(&self) {
-        unsafe { self.o_h_rw.add_value(self.h_rw) };
-        unsafe { self.o_rw_speeds.add_value(self.rw_speeds) };
+        if self.torque_out {
+            unsafe { self.o_torque.add_value(self.delivered_torque) };
+        } else {
+            // Wheel speeds and angular momentum after the plant step
+            let rw_speeds = self.next_speeds();
+            unsafe { self.o_h_rw.add_value(Vec3(self.inertia_rw * rw_speeds.0)) };
+            unsafe { self.o_rw_speeds.add_value(rw_speeds) };
+        }
     }
 
     fn delta_int(&mut self) {
-        self.compute_next_state(self.h);
-        self.sigma = f64::INFINITY
+        self.clock = self.component.get_t_next();
+        if self.torque_out {
+            self.torque_out = false;
+        } else {
+            // Commit the plant step that has just been published
+            self.rw_speeds = self.next_speeds();
+            self.first_step = false;
+            self.next_step += self.h;
+            self.update_actuator();
+        }
+        self.schedule();
     }
 
     fn delta_ext(&mut self, e: f64) {
         self.sigma -= e;
+        self.clock = self.clock.max(self.component.get_t_last() + e);
 
-        self.torque = unsafe { self.i_torque.get_values().first().copied() };
-        // With the new torque, we can compute the derivatives
-        self.compute_derivatives();
-        self.sigma = self.time;
+        if let Some(torque) = unsafe { self.i_torque.get_values().first().copied() } {
+            self.command = Some(torque);
+            self.update_actuator();
+        }
+        self.schedule();
     }
 
     fn ta(&self) -> f64 {
@@ -1,9 +1,13 @@
-use crate::discrete_time_model::types::{Quaternion, Vec3};
+use crate::discrete_time_model::{
+    recorder::{OutputPorts, Outputs},
+    types::{Quaternion, Vec3},
+};
 use nalgebra::Matrix3;
 use xdevs::modeling::*;
 
 pub struct SatelliteDynamics {
     component: Component,
+    outputs: OutputPorts,
     i_h_rw: InPort<Vec3>,
     i_torque: InPort<Vec3>,
     o_w: OutPort<Vec3>,
@@ -30,12 +34,14 @@ impl SatelliteDynamics {
         i_sat: Matrix3<f64>,
     ) -> Self {
         let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
         let i_h_rw = component.add_in_port::<Vec3>("i_h_rw");
         let i_t = component.add_in_port::<Vec3>("i_torque");
-        let o_w = component.add_out_port::<Vec3>("o_w");
-        let o_q = component.add_out_port::<Quaternion>("o_q");
+        let o_w = outputs.add::<Vec3>(&mut component, "o_w");
+        let o_q = outputs.add::<Quaternion>(&mut component, "o_q");
         SatelliteDynamics {
             component: component,
+            outputs,
             i_h_rw: i_h_rw,
             i_torque: i_t,
             o_w: o_w,
@@ -80,6 +86,12 @@ impl SatelliteDynamics {
     }
 }
 
+impl Outputs for SatelliteDynamics {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
+    }
+}
+
 impl Atomic for SatelliteDynamics {
     fn get_component(&self) -> &Component {
         &self.component
@@ -0,0 +1,172 @@
+use crate::discrete_time_model::{
+    recorder::{OutputPorts, Outputs},
+    types::{Quaternion, Vec3},
+};
+use nalgebra::{UnitQuaternion, Vector3};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::StandardNormal;
+use xdevs::modeling::*;
+
+// Tolerance when comparing event times with sampling instants [s]
+const TIME_EPSILON: f64 = 1e-9;
+
+/// White Gaussian noise on the measurements, drawn from a generator seeded with `seed` so that
+/// runs are repeatable.
+#[derive(Debug, Clone, Copy)]
+pub struct SensorNoise {
+    // Standard deviation of each rate component [rad/s]
+    pub rate_sigma: f64,
+    // Standard deviation of the attitude error about each body axis [rad]
+    pub attitude_sigma: f64,
+    pub seed: u64,
+}
+
+/// Attitude and rate sensors: samples the true state of `SatelliteDynamics` every `period`
+/// seconds (every plant step by default) and relays it to the on-board components, with optional
+/// noise, injected dropouts and attitude glitches.
+pub struct Sensor {
+    component: Component,
+    outputs: OutputPorts,
+    i_w: InPort<Vec3>,
+    i_q: InPort<Quaternion>,
+    o_w: OutPort<Vec3>,
+    o_q: OutPort<Quaternion>,
+    w: Option<Vec3>,
+    q: Option<Quaternion>,
+    sigma: f64,
+    // Intervals without measurements [s]
+    dropouts: Vec<(f64, f64)>,
+    // Times at which the next attitude sample is corrupted [s]
+    glitches: Vec<f64>,
+    // Sampling period, 0 relays every plant step [s]
+    period: f64,
+    next_sample: f64,
+    noise: Option<(SensorNoise, StdRng)>,
+}
+
+impl Sensor {
+    pub fn new(name: &str) -> Self {
+        let mut component = Component::new(name);
+        let mut outputs = OutputPorts::default();
+        let i_w = component.add_in_port::<Vec3>("i_w");
+        let i_q = component.add_in_port::<Quaternion>("i_q");
+        let o_w = outputs.add::<Vec3>(&mut component, "o_w");
+        let o_q = outputs.add::<Quaternion>(&mut component, "o_q");
+        Sensor {
+            component,
+            outputs,
+            i_w,
+            i_q,
+            o_w,
+            o_q,
+            w: None,
+            q: None,
+            // Transition to Waiting state
+            sigma: f64::INFINITY,
+            dropouts: Vec::new(),
+            glitches: Vec::new(),
+            period: 0.0,
+            next_sample: 0.0,
+            noise: None,
+        }
+    }
+
+    /// Samples the state every `period` seconds (a multiple of the plant step).
+    pub fn with_period(mut self, period: f64) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Stops every measurement from `start` until `end` [s].
+    pub fn with_dropout(mut self, start: f64, end: f64) -> Self {
+        self.dropouts.push((start, end));
+        self
+    }
+
+    /// Adds noise to every rate and attitude sample.
+    pub fn with_noise(mut self, noise: SensorNoise) -> Self {
+        self.noise = Some((noise, StdRng::seed_from_u64(noise.seed)));
+        self
+    }
+
+    /// Replaces the first attitude sample after `time` by a non-unit quaternion.
+    pub fn with_attitude_glitch(mut self, time: f64) -> Self {
+        self.glitches.push(time);
+        self
+    }
+}
+
+impl Outputs for Sensor {
+    fn output_ports(&self) -> &OutputPorts {
+        &self.outputs
+    }
+}
+
+impl Atomic for Sensor {
+    fn get_component(&self) -> &Component {
+        &self.component
+    }
+
+    fn get_component_mut(&mut self) -> &mut Component {
+        &mut self.component
+    }
+
+    fn lambda(&self) {
+        if let Some(w) = self.w {
+            unsafe { self.o_w.add_value(w) };
+        }
+        if let Some(q) = self.q {
+            unsafe { self.o_q.add_value(q) };
+        }
+    }
+
+    fn delta_int(&mut self) {
+        self.w = None;
+        self.q = None;
+        self.sigma = f64::INFINITY;
+    }
+
+    fn delta_ext(&mut self, e: f64) {
+        self.sigma -= e;
+        let t = self.component.get_t_last() + e;
+        // Plant steps between two sampling instants are not seen
+        if t < self.next_sample - TIME_EPSILON {
+            return;
+        }
+        while self.period > 0.0 && self.next_sample <= t + TIME_EPSILON {
+            self.next_sample += self.period;
+        }
+        if self.dropouts.iter().any(|&(start, end)| start <= t && t < end) {
+            return;
+        }
+        if !unsafe { self.i_w.is_empty() } {
+            self.w = unsafe { self.i_w.get_values().first().copied() };
+            if let Some((noise, rng)) = &mut self.noise {
+                let n = gaussian(rng, noise.rate_sigma);
+                self.w = self.w.map(|w| Vec3(w.0 + n));
+            }
+        }
+        if !unsafe { self.i_q.is_empty() } {
+            self.q = unsafe { self.i_q.get_values().first().copied() };
+            if let Some((noise, rng)) = &mut self.noise {
+                // Small rotation about the body axes
+                let n = UnitQuaternion::from_scaled_axis(gaussian(rng, noise.attitude_sigma));
+                self.q = self.q.map(|q| Quaternion(q.0 * n.into_inner()));
+            }
+            if let Some(i) = self.glitches.iter().position(|&glitch| glitch <= t) {
+                self.glitches.remove(i);
+                self.q = self.q.map(|q| Quaternion(q.0 * 2.0));
+            }
+        }
+        // Relay the measurements immediately
+        self.sigma = 0.0;
+    }
+
+    fn ta(&self) -> f64 {
+        self.sigma
+    }
+}
+
+fn gaussian(rng: &mut StdRng, sigma: f64) -> Vector3<f64> {
+    Vector3::from_fn(|_, _| sigma * rng.sample::<f64, _>(StandardNormal))
+}
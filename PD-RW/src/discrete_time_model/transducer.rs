@@ -1,4 +1,5 @@
-use crate::discrete_time_model::types::{Quaternion, Vec3};
+use crate::discrete_time_model::types::{Fault, Mat3, Mode, Quaternion, Vec3};
+use nalgebra::UnitQuaternion;
 use xdevs::modeling::*;
 
 pub struct Transducer {
@@ -6,73 +7,115 @@ pub struct Transducer {
     i_w: InPort<Vec3>,
     i_q_error: InPort<Quaternion>,
     i_rw_speeds: InPort<Vec3>,
+    i_integrator: InPort<Vec3>,
+    i_sliding: InPort<Vec3>,
+    i_inertia: InPort<Mat3>,
+    i_mode: InPort<Mode>,
+    i_fault: InPort<Fault>,
+    i_torque: InPort<Vec3>,
+    i_q: InPort<Quaternion>,
+    i_q_target: InPort<Quaternion>,
     sigma: f64,
-    q_error_history: Vec<Quaternion>,
-    w_history: Vec<Vec3>,
-    rw_speeds_history: Vec<Vec3>,
-    q_error_range: (f64, f64),
-    w_history_range: (f64, f64),
-    rw_speeds_history_range: (f64, f64),
-    range_margin: f64
+    // Every sample is stored with the simulation time it was received at [s]
+    q_error_history: Vec<(f64, Quaternion)>,
+    w_history: Vec<(f64, Vec3)>,
+    rw_speeds_history: Vec<(f64, Vec3)>,
+    integrator_history: Vec<(f64, Vec3)>,
+    sliding_history: Vec<(f64, Vec3)>,
+    inertia_history: Vec<(f64, Mat3)>,
+    // Mode transitions
+    mode_history: Vec<(f64, Mode)>,
+    // Faults reported by the FDIR
+    fault_history: Vec<(f64, Fault)>,
+    // Torque delivered by the wheels, applied from its time until the next sample
+    torque_history: Vec<(f64, Vec3)>,
+    // Latest Guidance target, held until it changes
+    q_target: Option<Quaternion>,
+    // True attitude relative to the target, from the plant state instead of the measurements
+    pointing_error_history: Vec<(f64, Quaternion)>,
 }
 
 impl Transducer {
-    pub fn new(name: &str, m: f64) -> Self {
+    pub fn new(name: &str) -> Self {
         let mut component = Component::new(name);
         let i_w = component.add_in_port::<Vec3>("i_w");
         let i_qe = component.add_in_port::<Quaternion>("i_qerror");
         let i_rw = component.add_in_port::<Vec3>("i_rw_speeds");
+        let i_i = component.add_in_port::<Vec3>("i_integrator");
+        let i_s = component.add_in_port::<Vec3>("i_sliding");
+        let i_in = component.add_in_port::<Mat3>("i_inertia");
+        let i_m = component.add_in_port::<Mode>("i_mode");
+        let i_f = component.add_in_port::<Fault>("i_fault");
+        let i_t = component.add_in_port::<Vec3>("i_torque");
+        let i_q = component.add_in_port::<Quaternion>("i_q");
+        let i_qt = component.add_in_port::<Quaternion>("i_q_target");
         Transducer {
             component: component,
             i_w: i_w,
             i_q_error: i_qe,
             i_rw_speeds: i_rw,
+            i_integrator: i_i,
+            i_sliding: i_s,
+            i_inertia: i_in,
+            i_mode: i_m,
+            i_fault: i_f,
+            i_torque: i_t,
+            i_q,
+            i_q_target: i_qt,
             // Transition to Waiting state
             sigma: f64::INFINITY,
             q_error_history: Vec::new(),
             w_history: Vec::new(),
             rw_speeds_history: Vec::new(),
-            q_error_range: (0.0, 0.0),
-            w_history_range: (0.0, 0.0),
-            rw_speeds_history_range: (0.0, 0.0),
-            range_margin: m,
+            integrator_history: Vec::new(),
+            sliding_history: Vec::new(),
+            inertia_history: Vec::new(),
+            mode_history: Vec::new(),
+            fault_history: Vec::new(),
+            torque_history: Vec::new(),
+            q_target: None,
+            pointing_error_history: Vec::new(),
         }
     }
 
-    pub fn get_q_error_history(&self) -> &[Quaternion] {
+    pub fn get_q_error_history(&self) -> &[(f64, Quaternion)] {
         self.q_error_history.as_slice()
     }
 
-    pub fn get_w_history(&self) -> &[Vec3] {
+    pub fn get_w_history(&self) -> &[(f64, Vec3)] {
         self.w_history.as_slice()
     }
 
-    pub fn get_rw_speeds_history(&self) -> &[Vec3] {
+    pub fn get_rw_speeds_history(&self) -> &[(f64, Vec3)] {
         self.rw_speeds_history.as_slice()
     }
 
-    pub fn get_q_error_range_with_margin(&self) -> (f64, f64) {
-        let range = self.q_error_range;
-        let margin = (range.1 - range.0).abs() * self.range_margin;
-        (range.0 - margin, range.1 + margin)
+    pub fn get_integrator_history(&self) -> &[(f64, Vec3)] {
+        self.integrator_history.as_slice()
     }
 
-    pub fn get_w_history_range_with_margin(&self) -> (f64, f64) {
-        let range = self.w_history_range;
-        let margin = (range.1 - range.0).abs() * self.range_margin;
-        (range.0 - margin, range.1 + margin)
+    pub fn get_sliding_history(&self) -> &[(f64, Vec3)] {
+        self.sliding_history.as_slice()
     }
 
-    pub fn get_rw_speeds_range_with_margin(&self) -> (f64, f64) {
-        let range = self.rw_speeds_history_range;
-        let margin = (range.1 - range.0).abs() * self.range_margin;
-        (range.0 - margin, range.1 + margin)
+    pub fn get_inertia_history(&self) -> &[(f64, Mat3)] {
+        self.inertia_history.as_slice()
     }
-    
-    fn update_range(compare: (f64, f64), values: Vec<f64>) -> (f64, f64) {
-        values.iter().fold(compare, |(min_v, max_v), &val| {
-            (f64::min(min_v, val), f64::max(max_v, val))
-        })
+
+    pub fn get_mode_history(&self) -> &[(f64, Mode)] {
+        self.mode_history.as_slice()
+    }
+
+    pub fn get_fault_history(&self) -> &[(f64, Fault)] {
+        self.fault_history.as_slice()
+    }
+
+    pub fn get_torque_history(&self) -> &[(f64, Vec3)] {
+        self.torque_history.as_slice()
+    }
+
+    pub fn get_pointing_error_history(&self) -> &[(f64, Quaternion)] {
+        self.pointing_error_history.as_slice()
     }
 }
 
@@ -93,28 +136,55 @@ impl Atomic for Transducer {
 
     fn delta_ext(&mut self, e: f64) {
         self.sigma -= e;
+        let t = self.component.get_t_last() + e;
 
         if !unsafe { self.i_q_error.is_empty() } {
             if let Some(q_error) = unsafe { self.i_q_error.get_values().first().copied() } {
-                self.q_error_history.push(q_error);
-                let values = vec![q_error.0.i, q_error.0.j, q_error.0.k];
-                self.q_error_range = Transducer::update_range(self.q_error_range, values);
+                self.q_error_history.push((t, q_error));
             }
         }
         if !unsafe { self.i_w.is_empty() } {
             if let Some(w) = unsafe { self.i_w.get_values().first().copied() } {
-                self.w_history.push(w);
-                let values = vec![w.0.x, w.0.y, w.0.z];
-                self.w_history_range = Transducer::update_range(self.w_history_range, values);
+                self.w_history.push((t, w));
             }
         }
         if !unsafe { self.i_rw_speeds.is_empty() } {
             if let Some(rw_speeds) = unsafe { self.i_rw_speeds.get_values().first().copied() } {
-                self.rw_speeds_history.push(rw_speeds);
-                let values = vec![rw_speeds.0.x, rw_speeds.0.y, rw_speeds.0.z];
-                self.rw_speeds_history_range = Transducer::update_range(self.rw_speeds_history_range, values);
+                self.rw_speeds_history.push((t, rw_speeds));
             }
         }
+        if let Some(integrator) = unsafe { self.i_integrator.get_values().first().copied() } {
+            self.integrator_history.push((t, integrator));
+        }
+        if let Some(sliding) = unsafe { self.i_sliding.get_values().first().copied() } {
+            self.sliding_history.push((t, sliding));
+        }
+        if let Some(inertia) = unsafe { self.i_inertia.get_values().first().copied() } {
+            self.inertia_history.push((t, inertia));
+        }
+        if let Some(mode) = unsafe { self.i_mode.get_values().first().copied() } {
+            self.mode_history.push((t, mode));
+        }
+        for fault in unsafe { self.i_fault.get_values() } {
+            self.fault_history.push((t, *fault));
+        }
+        if let Some(torque) = unsafe { self.i_torque.get_values().first().copied() } {
+            self.torque_history.push((t, torque));
+        }
+        // The target is updated first, so an attitude arriving with it is compared to it
+        if let Some(q_target) = unsafe { self.i_q_target.get_values().first().copied() } {
+            self.q_target = Some(q_target);
+        }
+        if let (Some(q), Some(q_target)) = (
+            unsafe { self.i_q.get_values().first().copied() },
+            self.q_target,
+        ) {
+            // conjugate(q_target) * q, normalized and taken as the shortest rotation
+            let q_error = UnitQuaternion::new_normalize(q_target.0.conjugate() * q.0);
+            let sign = if q_error.w < 0.0 { -1.0 } else { 1.0 };
+            let pointing_error = Quaternion(q_error.into_inner() * sign);
+            self.pointing_error_history.push((t, pointing_error));
+        }
     }
 
     fn ta(&self) -> f64 {
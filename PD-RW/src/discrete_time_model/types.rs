@@ -1,7 +1,10 @@
-use nalgebra::{Quaternion as nalgebraQuaternion, Vector3};
-use std::str::FromStr;
+use nalgebra::{Matrix3, Quaternion as nalgebraQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 
-#[derive(Debug, Clone, Copy)]
+// Saved as [x, y, z] in the results files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "[f64; 3]", into = "[f64; 3]")]
 pub struct Vec3(pub Vector3<f64>);
 
 impl Vec3 {
@@ -19,16 +22,34 @@ impl Vec3 {
     }
 }
 
-impl ToString for Vec3 {
-    fn to_string(&self) -> String {
+impl From<[f64; 3]> for Vec3 {
+    fn from(v: [f64; 3]) -> Self {
+        Vec3(Vector3::from(v))
+    }
+}
+
+impl From<Vec3> for [f64; 3] {
+    fn from(v: Vec3) -> Self {
+        v.0.into()
+    }
+}
+
+impl fmt::Display for Vec3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let v = &self.0;
-        format!("({},{},{})", v[0], v[1], v[2])
+        write!(f, "({},{},{})", v[0], v[1], v[2])
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseVec3Error;
 pub struct ParseQuaternionError;
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseMat3Error;
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseModeError;
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseFaultError;
 
 impl FromStr for Vec3 {
     type Err = ParseVec3Error;
@@ -46,7 +67,9 @@ impl FromStr for Vec3 {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+// Saved as [w, x, y, z] in the results files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "[f64; 4]", into = "[f64; 4]")]
 pub struct Quaternion(pub nalgebraQuaternion<f64>);
 
 impl Quaternion{
@@ -55,10 +78,22 @@ impl Quaternion{
     }
 }
 
-impl ToString for Quaternion {
-    fn to_string(&self) -> String {
+impl From<[f64; 4]> for Quaternion {
+    fn from([w, x, y, z]: [f64; 4]) -> Self {
+        Quaternion(nalgebraQuaternion::new(w, x, y, z))
+    }
+}
+
+impl From<Quaternion> for [f64; 4] {
+    fn from(q: Quaternion) -> Self {
+        [q.0.w, q.0.i, q.0.j, q.0.k]
+    }
+}
+
+impl fmt::Display for Quaternion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let v = self.0.coords;
-        format!("(w: {}, x: {}, y: {}, z: {})", v.w, v.x, v.y, v.z)
+        write!(f, "(w: {}, x: {}, y: {}, z: {})", v.w, v.x, v.y, v.z)
     }
 }
 
@@ -78,4 +113,150 @@ impl FromStr for Quaternion {
         let q = nalgebra::Quaternion::new(w, x, y, z);
         Ok(Quaternion(q))
     }
-}
\ No newline at end of file
+}
+
+// Saved row by row in the results files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "[[f64; 3]; 3]", into = "[[f64; 3]; 3]")]
+pub struct Mat3(pub Matrix3<f64>);
+
+impl From<[[f64; 3]; 3]> for Mat3 {
+    fn from(rows: [[f64; 3]; 3]) -> Self {
+        Mat3(Matrix3::from_row_slice(rows.as_flattened()))
+    }
+}
+
+impl From<Mat3> for [[f64; 3]; 3] {
+    fn from(m: Mat3) -> Self {
+        m.0.transpose().into()
+    }
+}
+
+impl fmt::Display for Mat3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = &self.0;
+        write!(
+            f,
+            "({},{},{},{},{},{},{},{},{})",
+            m[(0, 0)], m[(0, 1)], m[(0, 2)],
+            m[(1, 0)], m[(1, 1)], m[(1, 2)],
+            m[(2, 0)], m[(2, 1)], m[(2, 2)]
+        )
+    }
+}
+
+impl FromStr for Mat3 {
+    type Err = ParseMat3Error;
+
+    // Row-major list of the nine entries
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches('(').trim_end_matches(')');
+        let parts: Vec<_> = s.split(',').collect();
+        if parts.len() != 9 {
+            return Err(ParseMat3Error);
+        }
+        let mut values = [0.0; 9];
+        for (value, part) in values.iter_mut().zip(parts) {
+            *value = part.trim().parse::<f64>().map_err(|_| ParseMat3Error)?;
+        }
+        Ok(Mat3(Matrix3::from_row_slice(&values)))
+    }
+}
+
+/// Operating mode of the ADCS, published by the `ModeManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    /// Safe mode: damp the body rates (detumble)
+    Safe,
+    /// Turn the solar panels towards the Sun
+    SunAcquisition,
+    /// Track the mission target with relaxed accuracy
+    CoarsePointing,
+    /// Track the mission target with the accurate controller
+    FinePointing,
+    /// Unload the reaction wheels
+    MomentumDump,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 5] = [
+        Mode::Safe,
+        Mode::SunAcquisition,
+        Mode::CoarsePointing,
+        Mode::FinePointing,
+        Mode::MomentumDump,
+    ];
+
+    /// Position of the mode in `Mode::ALL`
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Safe => "Safe",
+            Mode::SunAcquisition => "SunAcquisition",
+            Mode::CoarsePointing => "CoarsePointing",
+            Mode::FinePointing => "FinePointing",
+            Mode::MomentumDump => "MomentumDump",
+        })
+    }
+}
+
+impl FromStr for Mode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s.trim())
+            .ok_or(ParseModeError)
+    }
+}
+
+/// Fault isolated by the `Fdir` component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fault {
+    /// The wheel does not respond to its torque command
+    StuckWheel(usize),
+    /// The wheel responds, but its speed change does not match the command
+    SpeedMismatch(usize),
+    /// The attitude sensors stopped reporting
+    SensorDropout,
+    /// The measured quaternion is not a unit quaternion or jumps faster than the body can rotate
+    ImplausibleAttitude,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::StuckWheel(wheel) => write!(f, "StuckWheel({})", wheel),
+            Fault::SpeedMismatch(wheel) => write!(f, "SpeedMismatch({})", wheel),
+            Fault::SensorDropout => f.write_str("SensorDropout"),
+            Fault::ImplausibleAttitude => f.write_str("ImplausibleAttitude"),
+        }
+    }
+}
+
+impl FromStr for Fault {
+    type Err = ParseFaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let wheel = |prefix: &str| {
+            s.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|wheel| wheel.parse::<usize>().ok())
+        };
+        match s {
+            "SensorDropout" => Ok(Fault::SensorDropout),
+            "ImplausibleAttitude" => Ok(Fault::ImplausibleAttitude),
+            _ => wheel("StuckWheel(")
+                .map(Fault::StuckWheel)
+                .or_else(|| wheel("SpeedMismatch(").map(Fault::SpeedMismatch))
+                .ok_or(ParseFaultError),
+        }
+    }
+}
@@ -1,15 +1,375 @@
+mod cli;
+mod comparison;
 mod discrete_time_model;
+mod metrics;
+mod monte_carlo;
 mod plotters;
+mod requirements;
+mod results;
+mod scenario;
+mod simulation;
+mod sweep;
+mod tuning;
 
-use discrete_time_model::DiscreteTimeModel;
-use xdevs::simulation::*;
+use clap::Parser;
+use cli::{Cli, Command};
+use comparison::Response;
+use discrete_time_model::fdir::FdirReport;
+use metrics::METRICS_FILE_NAME;
+use monte_carlo::Campaign;
+use requirements::Verification;
+use results::{RESULTS_FILE_NAME, Results};
+use scenario::{Override, Scenario};
+use simulation::simulate;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+use sweep::{Design, Metric, Sweep, SweepParameter};
+use tuning::Tuning;
 
 fn main() {
-    let total_time = 100.0;
-    let model = DiscreteTimeModel::new("DiscreteTimeModel", Some(0.01));
-    let mut simulator = RootCoordinator::new(model.coupled);
-    simulator.simulate(total_time);
+    let cli = Cli::parse();
+    let overrides = cli.options.overrides();
+    let outcome = match &cli.command {
+        Command::Run {
+            scenario,
+            real_time,
+        } => run(scenario, &overrides, *real_time),
+        Command::Sweep {
+            scenario,
+            parameters,
+            latin_hypercube,
+            seed,
+            metrics,
+            threads,
+        } => {
+            let design = match latin_hypercube {
+                Some(samples) => Design::LatinHypercube {
+                    samples: *samples,
+                    seed: *seed,
+                },
+                None => Design::Grid,
+            };
+            sweep(scenario, &overrides, parameters, design, metrics, *threads)
+        }
+        Command::Montecarlo {
+            scenario,
+            runs,
+            seed,
+            threads,
+        } => montecarlo(scenario, &overrides, *runs, *seed, *threads),
+        Command::Tune {
+            scenario,
+            max_iterations,
+            threads,
+        } => tune(scenario, &overrides, *max_iterations, *threads),
+        Command::Plot { results } => plot(results, cli.options.output_dir.as_deref()),
+        Command::Compare { a, b } => compare(a, b, &overrides),
+        Command::Discretization { scenario, period } => {
+            discretization(scenario, &overrides, *period)
+        }
+        Command::Validate { scenarios } => validate(scenarios, &overrides),
+    };
+    if let Err(error) = outcome {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(path: &Path, overrides: &[Override], real_time: Option<f64>) -> Result<(), String> {
+    let scenario = load(path, overrides)?;
+    let results = simulate(&scenario, real_time).map_err(|error| located(path, error))?;
+    let response = Response::new(label(path), &results, &scenario);
+    println!("{}", response);
+    let metrics = response.metrics;
+    println!("{}", metrics);
+    if scenario.fdir {
+        println!(
+            "{}",
+            FdirReport::new(&scenario.injections(), &results.faults)
+        );
+    }
+
+    let directory = output_directory(&scenario)?;
+    let file = directory.join(RESULTS_FILE_NAME);
+    results.save(&file).map_err(|error| located(&file, error))?;
+    eprintln!("Results have been saved to {}", file.display());
+    let file = directory.join(METRICS_FILE_NAME);
+    metrics.save(&file).map_err(|error| located(&file, error))?;
+    eprintln!("Metrics have been saved to {}", file.display());
+    if scenario.output.plot {
+        plotters::draw(&results, directory);
+    }
+
+    if scenario.requirements.is_empty() {
+        return Ok(());
+    }
+    let verification = Verification::new(&results, &scenario);
+    println!("{}", verification);
+    match verification.violated() {
+        0 => Ok(()),
+        violated => Err(format!(
+            "{}: {} of {} requirements are violated",
+            path.display(),
+            violated,
+            verification.verdicts.len()
+        )),
+    }
+}
+
+fn sweep(
+    path: &Path,
+    overrides: &[Override],
+    parameters: &[SweepParameter],
+    design: Design,
+    metrics: &[Metric],
+    threads: Option<usize>,
+) -> Result<(), String> {
+    let points = design.points(parameters)?;
+    let mut scenarios = Vec::with_capacity(points.len());
+    for values in points {
+        let mut point_overrides = overrides.to_vec();
+        for (parameter, value) in parameters.iter().zip(&values) {
+            point_overrides.push(Override::new(&parameter.path, value));
+        }
+        scenarios.push((values, load(path, &point_overrides)?));
+    }
+    let Some((_, scenario)) = scenarios.first() else {
+        return Err("the sweep has no points".to_string());
+    };
+    let scenario = scenario.clone();
+    thread_pool(threads)?;
+
+    eprintln!("Simulating {} points", scenarios.len());
+    let sweep =
+        Sweep::run(parameters.to_vec(), design, scenarios).map_err(|error| located(path, error))?;
+    println!("{}", sweep);
+
+    let directory = output_directory(&scenario)?;
+    let file = directory.join("sweep.csv");
+    sweep
+        .save_csv(&file)
+        .map_err(|error| located(&file, error))?;
+    eprintln!("Results have been saved to {}", file.display());
+    if scenario.output.plot {
+        if parameters.len() == 1 {
+            let responses: Vec<Response> = sweep
+                .points
+                .into_iter()
+                .map(|point| point.response)
+                .collect();
+            let title = format!("Sweep of {}", parameters[0].path);
+            plotters::draw_comparison(&responses, &title, max_time(&responses), directory);
+        } else {
+            for metric in metrics {
+                plotters::draw_heat_map(&sweep, *metric, directory);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn montecarlo(
+    path: &Path,
+    overrides: &[Override],
+    runs: Option<usize>,
+    seed: Option<u64>,
+    threads: Option<usize>,
+) -> Result<(), String> {
+    let mut overrides = overrides.to_vec();
+    if let Some(runs) = runs {
+        overrides.push(Override::new("monte_carlo.runs", &runs.to_string()));
+    }
+    if let Some(seed) = seed {
+        overrides.push(Override::new("monte_carlo.seed", &seed.to_string()));
+    }
+    let nominal = load(path, &overrides)?;
+    let Some(config) = &nominal.monte_carlo else {
+        return Err(located(
+            path,
+            "monte_carlo: missing table, nothing to disperse",
+        ));
+    };
+    thread_pool(threads)?;
+
+    eprintln!("Simulating {} runs", config.runs);
+    let campaign = Campaign::run(&nominal, config).map_err(|error| located(path, error))?;
+    println!("{}", campaign);
+
+    let directory = output_directory(&nominal)?;
+    let file = directory.join("monte_carlo.csv");
+    campaign
+        .save_csv(&file)
+        .map_err(|error| located(&file, error))?;
+    eprintln!("Runs have been saved to {}", file.display());
+    if nominal.output.plot {
+        plotters::draw_envelope(&campaign, directory);
+    }
+    Ok(())
+}
+
+fn tune(
+    path: &Path,
+    overrides: &[Override],
+    max_iterations: Option<usize>,
+    threads: Option<usize>,
+) -> Result<(), String> {
+    let mut overrides = overrides.to_vec();
+    if let Some(max_iterations) = max_iterations {
+        overrides.push(Override::new(
+            "tuning.max_iterations",
+            &max_iterations.to_string(),
+        ));
+    }
+    let nominal = load(path, &overrides)?;
+    let Some(config) = &nominal.tuning else {
+        return Err(located(path, "tuning: missing table, nothing to tune"));
+    };
+    thread_pool(threads)?;
+
+    eprintln!("Tuning {} parameters", config.parameters.len());
+    let tuning = Tuning::run(&nominal, config).map_err(|error| located(path, error))?;
+    println!("{}", tuning);
+
+    let directory = output_directory(&nominal)?;
+    let file = directory.join("tuning.csv");
+    tuning
+        .save_csv(&file)
+        .map_err(|error| located(&file, error))?;
+    eprintln!("Convergence has been saved to {}", file.display());
+    // Same format as the input scenario
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let file = directory.join(format!("{}_tuned.{}", label(path), extension));
+    tuning
+        .scenario
+        .save(&file)
+        .map_err(|error| located(&file, error))?;
+    eprintln!("Tuned scenario has been saved to {}", file.display());
+    if nominal.output.plot {
+        plotters::draw_convergence(&tuning, directory);
+    }
+    Ok(())
+}
+
+fn plot(path: &Path, output_dir: Option<&Path>) -> Result<(), String> {
+    let results = Results::load(path).map_err(|error| located(path, error))?;
+    // Next to the results file unless another directory is given
+    let directory = match output_dir {
+        Some(directory) => directory,
+        None => path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new(".")),
+    };
+    fs::create_dir_all(directory).map_err(|error| located(directory, error))?;
+    plotters::draw(&results, directory);
+    Ok(())
+}
+
+fn compare(a: &Path, b: &Path, overrides: &[Override]) -> Result<(), String> {
+    let mut responses = Vec::new();
+    let mut scenarios = Vec::new();
+    for path in [a, b] {
+        let scenario = load(path, overrides)?;
+        let results = simulate(&scenario, None).map_err(|error| located(path, error))?;
+        let response = Response::new(label(path), &results, &scenario);
+        println!("{}", response);
+        responses.push(response);
+        scenarios.push(scenario);
+    }
+
+    // Into the output directory of the first scenario
+    if scenarios[0].output.plot {
+        let directory = output_directory(&scenarios[0])?;
+        let title = format!("{} vs {}", label(a), label(b));
+        plotters::draw_comparison(&responses, &title, max_time(&responses), directory);
+    }
+    Ok(())
+}
+
+fn discretization(path: &Path, overrides: &[Override], period: f64) -> Result<(), String> {
+    let scenario = load(path, overrides)?;
+    // The continuous design of the scenario's Compensator, discretized for a slower on-board loop
+    let (c_error, c_rate) = scenario
+        .compensator_design()
+        .map_err(|error| located(path, error))?;
+    let responses = comparison::compare_discretizations(&scenario, &c_error, &c_rate, period)
+        .map_err(|error| located(path, error))?;
+    for response in &responses {
+        println!("{}", response);
+    }
+    if scenario.output.plot {
+        let directory = output_directory(&scenario)?;
+        let total_time = scenario.simulation.total_time;
+        plotters::draw_comparison(
+            &responses,
+            "Discretization Comparison",
+            total_time,
+            directory,
+        );
+    }
+    Ok(())
+}
+
+fn validate(paths: &[PathBuf], overrides: &[Override]) -> Result<(), String> {
+    let mut invalid = 0;
+    for path in paths {
+        match load(path, overrides) {
+            Ok(_) => println!("{}: ok", path.display()),
+            Err(error) => {
+                println!("{}", error);
+                invalid += 1;
+            }
+        }
+    }
+    match invalid {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{} of {} scenarios are invalid",
+            invalid,
+            paths.len()
+        )),
+    }
+}
+
+fn load(path: &Path, overrides: &[Override]) -> Result<Scenario, String> {
+    Scenario::load(path, overrides).map_err(|error| located(path, error))
+}
+
+// Limits the parallel commands to `threads` simulations at once
+fn thread_pool(threads: Option<usize>) -> Result<(), String> {
+    match threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|error| error.to_string()),
+        None => Ok(()),
+    }
+}
+
+fn output_directory(scenario: &Scenario) -> Result<&Path, String> {
+    let directory = Path::new(&scenario.output.directory);
+    fs::create_dir_all(directory).map_err(|error| located(directory, error))?;
+    Ok(directory)
+}
+
+fn located(path: &Path, error: impl std::fmt::Display) -> String {
+    format!("{}: {}", path.display(), error)
+}
+
+// Name of a scenario in summaries and legends
+fn label(path: &Path) -> String {
+    path.file_stem().map_or_else(
+        || path.display().to_string(),
+        |stem| stem.to_string_lossy().into(),
+    )
+}
 
-    let transducer = unsafe { &*model.transducer_ref };
-    plotters::draw(transducer, total_time);
+fn max_time(responses: &[Response]) -> f64 {
+    responses
+        .iter()
+        .map(|response| response.metrics.duration)
+        .fold(0.0, f64::max)
 }
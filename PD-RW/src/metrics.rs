@@ -0,0 +1,355 @@
+use crate::{
+    results::{Results, held},
+    scenario::{Scenario, ScenarioError, ensure, positive},
+};
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, io, path::Path};
+
+pub const METRICS_FILE_NAME: &str = "metrics.json";
+
+// Relative margin under a limit within which a wheel counts as saturated
+const SATURATION_MARGIN: f64 = 1e-9;
+
+/// Settings of the performance figures of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    // Attitude error below which the response is settled [rad]
+    #[serde(default = "default_settling_threshold")]
+    pub settling_threshold: f64,
+    // End of the run the steady-state figures are computed over, as a fraction of its duration
+    #[serde(default = "default_steady_state_window")]
+    pub steady_state_window: f64,
+}
+
+fn default_settling_threshold() -> f64 {
+    0.01
+}
+
+fn default_steady_state_window() -> f64 {
+    0.1
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            settling_threshold: default_settling_threshold(),
+            steady_state_window: default_steady_state_window(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    pub(crate) fn validate(&self, field: &str) -> Result<(), ScenarioError> {
+        positive(
+            &format!("{}.settling_threshold", field),
+            self.settling_threshold,
+        )?;
+        let window = self.steady_state_window;
+        ensure(
+            window > 0.0 && window <= 1.0,
+            &format!("{}.steady_state_window", field),
+            "must be above 0 and at most 1",
+        )
+    }
+}
+
+/// Closed-loop performance of a run, computed from its recorded histories. Pointing figures are
+/// in degrees and come from the true attitude error, not the Controller's measured one.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Metrics {
+    // Simulated time [s]
+    pub duration: f64,
+    pub settling_threshold_deg: f64,
+    // Time from which the error stays below the threshold, None if it never settles [s]
+    pub settling_time: Option<f64>,
+    // Largest error past the target along the axis of the initial error, and its ratio to the
+    // initial error [%]
+    pub overshoot_deg: f64,
+    pub overshoot_percent: f64,
+    // Mean and RMS of the error over the steady-state window
+    pub steady_state_error_deg: f64,
+    pub rms_error_deg: f64,
+    pub final_error_deg: f64,
+    // Integral of the norm of the torque delivered by the wheels [N m s]
+    pub effort: f64,
+    // Largest speed of any wheel [rad/s] and largest norm of the wheel angular momentum [N m s]
+    pub peak_wheel_speed: f64,
+    pub peak_wheel_momentum: f64,
+    // Time some wheel spends at its torque limit, and at its speed limit [s]
+    pub torque_saturation_time: f64,
+    pub speed_saturation_time: f64,
+}
+
+impl Metrics {
+    /// Figures of `results`, recorded by a run of `scenario`
+    pub fn new(results: &Results, scenario: &Scenario) -> Self {
+        let config = &scenario.metrics;
+        let wheels = &scenario.wheels;
+        let duration = results.total_time;
+        let error_angle = results.error_angle();
+        let settling_time = settling_time(&error_angle, config.settling_threshold);
+
+        let rotations: Vec<Vector3<f64>> = results
+            .true_error()
+            .iter()
+            .map(|(_, q)| UnitQuaternion::new_normalize(q.0).scaled_axis())
+            .collect();
+        let (overshoot, initial_error) = match rotations.first() {
+            Some(initial) => match initial.try_normalize(0.0) {
+                Some(axis) => (
+                    rotations.iter().map(|r| -r.dot(&axis)).fold(0.0, f64::max),
+                    initial.norm(),
+                ),
+                None => (0.0, 0.0),
+            },
+            None => (0.0, 0.0),
+        };
+
+        // Time-weighted mean and RMS over the end of the run, the last sample when none holds
+        // within it
+        let window_start = duration * (1.0 - config.steady_state_window);
+        let (mut window, mut sum, mut sum_squares) = (0.0, 0.0, 0.0);
+        for (start, end, angle) in held(&error_angle, duration) {
+            let overlap = end - start.max(window_start);
+            if overlap > 0.0 {
+                window += overlap;
+                sum += angle * overlap;
+                sum_squares += angle * angle * overlap;
+            }
+        }
+        let final_error = error_angle.last().map_or(f64::NAN, |(_, angle)| *angle);
+        let (mean, rms) = match window > 0.0 {
+            true => (sum / window, (sum_squares / window).sqrt()),
+            false => (final_error, final_error),
+        };
+
+        let mut effort = 0.0;
+        let mut torque_saturation_time = 0.0;
+        for (start, end, torque) in held(&results.torque, duration) {
+            effort += torque.0.norm() * (end - start);
+            if torque.0.amax() >= wheels.max_torque * (1.0 - SATURATION_MARGIN) {
+                torque_saturation_time += end - start;
+            }
+        }
+
+        let inertia = Vector3::from(wheels.inertia);
+        let speed_limit = wheels.max_speed * (1.0 - SATURATION_MARGIN);
+        let speed_saturation_time = held(&results.rw_speeds, duration)
+            .filter(|(_, _, speeds)| speeds.0.amax() >= speed_limit)
+            .fold(0.0, |time, (start, end, _)| time + end - start);
+
+        Metrics {
+            duration,
+            settling_threshold_deg: config.settling_threshold.to_degrees(),
+            settling_time,
+            overshoot_deg: overshoot.to_degrees(),
+            overshoot_percent: match initial_error > 0.0 {
+                true => 100.0 * overshoot / initial_error,
+                false => 0.0,
+            },
+            steady_state_error_deg: mean.to_degrees(),
+            rms_error_deg: rms.to_degrees(),
+            final_error_deg: final_error.to_degrees(),
+            effort,
+            peak_wheel_speed: results.peak_wheel_speed(),
+            peak_wheel_momentum: results
+                .rw_speeds
+                .iter()
+                .map(|(_, speeds)| speeds.0.component_mul(&inertia).norm())
+                .fold(0.0, f64::max),
+            torque_saturation_time,
+            speed_saturation_time,
+        }
+    }
+
+    /// Settling time, the whole run when the error never settles [s]
+    pub fn settling_time_or_duration(&self) -> f64 {
+        self.settling_time.unwrap_or(self.duration)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+// Time of the first sample of `error_angle` from which the error stays at or below `threshold`,
+// None if the last sample is above it
+fn settling_time(error_angle: &[(f64, f64)], threshold: f64) -> Option<f64> {
+    match error_angle
+        .iter()
+        .rposition(|(_, angle)| *angle > threshold)
+    {
+        Some(i) => error_angle.get(i + 1).map(|(t, _)| *t),
+        None => Some(0.0),
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let settling = match self.settling_time {
+            Some(time) => format!("{:.2} s", time),
+            None => "not settled".to_string(),
+        };
+        let rows = [
+            (
+                format!("Settling time to {:.3} deg", self.settling_threshold_deg),
+                settling,
+            ),
+            (
+                "Overshoot".to_string(),
+                format!(
+                    "{:.4} deg ({:.1} %)",
+                    self.overshoot_deg, self.overshoot_percent
+                ),
+            ),
+            (
+                "Steady-state error".to_string(),
+                format!("{:.6} deg", self.steady_state_error_deg),
+            ),
+            (
+                "RMS steady-state error".to_string(),
+                format!("{:.6} deg", self.rms_error_deg),
+            ),
+            (
+                "Final error".to_string(),
+                format!("{:.6} deg", self.final_error_deg),
+            ),
+            (
+                "Control effort".to_string(),
+                format!("{:.6} N m s", self.effort),
+            ),
+            (
+                "Peak wheel speed".to_string(),
+                format!("{:.3} rad/s", self.peak_wheel_speed),
+            ),
+            (
+                "Peak wheel momentum".to_string(),
+                format!("{:.6} N m s", self.peak_wheel_momentum),
+            ),
+            (
+                "Torque saturation".to_string(),
+                format!("{:.2} s", self.torque_saturation_time),
+            ),
+            (
+                "Speed saturation".to_string(),
+                format!("{:.2} s", self.speed_saturation_time),
+            ),
+        ];
+        for (i, (name, value)) in rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:<32}{}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete_time_model::types::{Quaternion, Vec3};
+
+    fn scenario() -> Scenario {
+        // Wheels limited to 1e-3 N m and 20 rad/s, with an inertia of 5e-5 kg m^2
+        Scenario::from_toml(include_str!("../scenarios/default.toml")).unwrap()
+    }
+
+    // One sample per second of a rotation error about x
+    fn about_x(angles: &[f64]) -> Vec<(f64, Quaternion)> {
+        angles
+            .iter()
+            .enumerate()
+            .map(|(i, angle)| {
+                let q = UnitQuaternion::from_scaled_axis(Vector3::x() * *angle);
+                (i as f64, Quaternion(*q.quaternion()))
+            })
+            .collect()
+    }
+
+    // Run of 10 s with the true pointing error about x at every second, the torque and the wheel
+    // speeds along x given as (start time, value). The Controller measures no error at all.
+    fn results(angles: &[f64], torque: &[(f64, f64)], speeds: &[(f64, f64)]) -> Results {
+        let along_x = |history: &[(f64, f64)]| {
+            history
+                .iter()
+                .map(|(t, x)| (*t, Vec3(Vector3::new(*x, 0.0, 0.0))))
+                .collect()
+        };
+        Results {
+            total_time: 10.0,
+            margin_ratio: 0.0,
+            q_error: about_x(&vec![0.0; angles.len()]),
+            pointing_error: about_x(angles),
+            w: Vec::new(),
+            rw_speeds: along_x(speeds),
+            integrator: Vec::new(),
+            sliding: Vec::new(),
+            inertia: Vec::new(),
+            modes: Vec::new(),
+            faults: Vec::new(),
+            torque: along_x(torque),
+            recordings: Vec::new(),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn pointing_figures() {
+        let angles = [
+            0.5, 0.2, 0.05, -0.02, 0.005, 0.005, 0.005, 0.005, 0.005, 0.005,
+        ];
+        let metrics = Metrics::new(&results(&angles, &[], &[]), &scenario());
+        assert_close(metrics.duration, 10.0);
+        // Last above 0.01 rad at 3 s
+        assert_eq!(metrics.settling_time, Some(4.0));
+        // 0.02 rad past the target along the initial error axis
+        assert_close(metrics.overshoot_deg, 0.02f64.to_degrees());
+        assert_close(metrics.overshoot_percent, 4.0);
+        // The last second holds 0.005 rad
+        assert_close(metrics.steady_state_error_deg, 0.005f64.to_degrees());
+        assert_close(metrics.rms_error_deg, 0.005f64.to_degrees());
+        assert_close(metrics.final_error_deg, 0.005f64.to_degrees());
+    }
+
+    #[test]
+    fn controller_error_without_true_error() {
+        // Results saved before the true error was recorded
+        let mut results = results(&[], &[], &[]);
+        results.q_error = about_x(&[0.5, 0.3, 0.02]);
+        let metrics = Metrics::new(&results, &scenario());
+        assert_eq!(metrics.settling_time, None);
+        assert_close(metrics.final_error_deg, 0.02f64.to_degrees());
+    }
+
+    #[test]
+    fn never_settles() {
+        let metrics = Metrics::new(&results(&[0.5, 0.4, 0.3], &[], &[]), &scenario());
+        assert_eq!(metrics.settling_time, None);
+        assert_close(metrics.settling_time_or_duration(), 10.0);
+        assert_close(metrics.overshoot_deg, 0.0);
+    }
+
+    #[test]
+    fn wheel_figures() {
+        let torque = [(0.0, 0.001), (5.0, 0.0005), (8.0, 0.0)];
+        let speeds = [(0.0, 20.0), (2.0, -10.0)];
+        let metrics = Metrics::new(&results(&[0.0], &torque, &speeds), &scenario());
+        // Each torque holds until the next one
+        assert_close(metrics.effort, 0.001 * 5.0 + 0.0005 * 3.0);
+        assert_close(metrics.torque_saturation_time, 5.0);
+        assert_close(metrics.speed_saturation_time, 2.0);
+        assert_close(metrics.peak_wheel_speed, 20.0);
+        assert_close(metrics.peak_wheel_momentum, 20.0 * 5.0e-5);
+    }
+}
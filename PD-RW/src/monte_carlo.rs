@@ -0,0 +1,471 @@
+use crate::{
+    comparison::Response,
+    results::{Results, value_at},
+    scenario::{Attitude, Scenario, ScenarioError, field_mut},
+    simulation::simulate,
+};
+use nalgebra::{Matrix3, SymmetricEigen, Unit, UnitQuaternion, Vector3};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::StandardNormal;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt, fs, io, path::Path};
+
+// Evenly spaced times the histories of each run are sampled at for the envelopes
+const ENVELOPE_POINTS: usize = 500;
+// Percentiles of the summary table
+const PERCENTILES: [f64; 3] = [50.0, 95.0, 99.0];
+// Row of the summary table and the figure of each run it is computed from
+type RunMetric = (&'static str, fn(&RunSummary) -> f64);
+
+/// Monte Carlo campaign of a scenario, from its `[monte_carlo]` table. Every run draws each
+/// dispersion independently around the nominal scenario: the initial state dispersions are
+/// added to it, the physical parameters are scaled by 1 + the draw (0.05 is 5 %).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonteCarloConfig {
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    // Run i draws from a generator seeded with seed + i, whatever the number of threads
+    #[serde(default)]
+    pub seed: u64,
+    // Rotation away from the initial attitude about a random axis [rad]
+    #[serde(default)]
+    pub attitude: Option<Distribution>,
+    // Added to each component of the initial angular velocity [rad/s]
+    #[serde(default)]
+    pub rate: Option<Distribution>,
+    // Relative change of each principal moment of inertia
+    #[serde(default)]
+    pub inertia: Option<Distribution>,
+    // Rotation of the principal axes of inertia about a random axis [rad]
+    #[serde(default)]
+    pub inertia_axes: Option<Distribution>,
+    // Relative change of the inertia of each wheel, and of the wheel torque and speed limits
+    #[serde(default)]
+    pub wheel_inertia: Option<Distribution>,
+    #[serde(default)]
+    pub wheel_max_torque: Option<Distribution>,
+    #[serde(default)]
+    pub wheel_max_speed: Option<Distribution>,
+    // Standard deviation of the rate [rad/s] and attitude [rad] sensor noise, the absolute
+    // value of the draw. The noise is drawn with a new seed in every run.
+    #[serde(default)]
+    pub rate_noise: Option<Distribution>,
+    #[serde(default)]
+    pub attitude_noise: Option<Distribution>,
+    // Any other number of the scenario, e.g. the control gains
+    #[serde(default)]
+    pub parameters: Vec<ParameterDispersion>,
+}
+
+fn default_runs() -> usize {
+    100
+}
+
+/// Distribution a dispersion is drawn from, selected by `type`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum Distribution {
+    Normal {
+        #[serde(default)]
+        mean: f64,
+        sigma: f64,
+    },
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+}
+
+/// Dispersion of the number at a field path of the scenario, as in the error messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterDispersion {
+    pub path: String,
+    pub distribution: Distribution,
+    // Scales the nominal value by 1 + the draw instead of adding the draw
+    #[serde(default)]
+    pub relative: bool,
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut StdRng) -> f64 {
+        match *self {
+            Distribution::Normal { mean, sigma } => {
+                mean + sigma * rng.sample::<f64, _>(StandardNormal)
+            }
+            Distribution::Uniform { min, max } if min == max => min,
+            Distribution::Uniform { min, max } => rng.random_range(min..max),
+        }
+    }
+
+    fn validate(&self, field: &str) -> Result<(), ScenarioError> {
+        let valid = match *self {
+            Distribution::Normal { mean, sigma } => {
+                mean.is_finite() && sigma.is_finite() && sigma >= 0.0
+            }
+            Distribution::Uniform { min, max } => min.is_finite() && max.is_finite() && min <= max,
+        };
+        match valid {
+            true => Ok(()),
+            false => Err(ScenarioError::new(
+                field,
+                "must have finite bounds, a non-negative sigma and min <= max",
+            )),
+        }
+    }
+}
+
+impl MonteCarloConfig {
+    pub(crate) fn validate(&self, field: &str) -> Result<(), ScenarioError> {
+        if self.runs == 0 {
+            return Err(ScenarioError::new(
+                &format!("{}.runs", field),
+                "must be at least 1",
+            ));
+        }
+        let dispersions = [
+            ("attitude", &self.attitude),
+            ("rate", &self.rate),
+            ("inertia", &self.inertia),
+            ("inertia_axes", &self.inertia_axes),
+            ("wheel_inertia", &self.wheel_inertia),
+            ("wheel_max_torque", &self.wheel_max_torque),
+            ("wheel_max_speed", &self.wheel_max_speed),
+            ("rate_noise", &self.rate_noise),
+            ("attitude_noise", &self.attitude_noise),
+        ];
+        for (name, distribution) in dispersions {
+            if let Some(distribution) = distribution {
+                distribution.validate(&format!("{}.{}", field, name))?;
+            }
+        }
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let field = format!("{}.parameters[{}]", field, i);
+            parameter
+                .distribution
+                .validate(&format!("{}.distribution", field))?;
+        }
+        Ok(())
+    }
+
+    /// Scenario of run `run`, with the values drawn for it
+    pub fn sample(&self, nominal: &Scenario, run: usize) -> Result<Sample, ScenarioError> {
+        let mut sampler = Sampler {
+            rng: StdRng::seed_from_u64(self.seed.wrapping_add(run as u64)),
+            draws: Vec::new(),
+        };
+        let mut scenario = nominal.clone();
+        // The `monte_carlo` table is not needed by the runs
+        scenario.monte_carlo = None;
+
+        if let Some(attitude) = &self.attitude {
+            let rotation = sampler.rotation("attitude", attitude);
+            let q0 = rotation.into_inner() * nominal.initial_state.attitude.quaternion().0;
+            scenario.initial_state.attitude = Attitude::Quaternion([q0.w, q0.i, q0.j, q0.k]);
+        }
+        if let Some(rate) = &self.rate {
+            for (i, w) in scenario.initial_state.w.iter_mut().enumerate() {
+                *w += sampler.draw(format!("rate[{}]", i), rate);
+            }
+        }
+        if self.inertia.is_some() || self.inertia_axes.is_some() {
+            let rows = nominal.spacecraft.inertia;
+            let eigen = SymmetricEigen::new(Matrix3::from_row_slice(rows.as_flattened()));
+            let mut moments = eigen.eigenvalues;
+            if let Some(inertia) = &self.inertia {
+                for (i, moment) in moments.iter_mut().enumerate() {
+                    *moment *= 1.0 + sampler.draw(format!("inertia[{}]", i), inertia);
+                }
+            }
+            let mut axes = eigen.eigenvectors;
+            if let Some(inertia_axes) = &self.inertia_axes {
+                axes = sampler
+                    .rotation("inertia_axes", inertia_axes)
+                    .to_rotation_matrix()
+                    * axes;
+            }
+            let inertia = axes * Matrix3::from_diagonal(&moments) * axes.transpose();
+            // Symmetric up to rounding
+            let inertia = (inertia + inertia.transpose()) / 2.0;
+            for (i, row) in scenario.spacecraft.inertia.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value = inertia[(i, j)];
+                }
+            }
+        }
+        let wheels = &mut scenario.wheels;
+        if let Some(wheel_inertia) = &self.wheel_inertia {
+            for (i, inertia) in wheels.inertia.iter_mut().enumerate() {
+                *inertia *= 1.0 + sampler.draw(format!("wheel_inertia[{}]", i), wheel_inertia);
+            }
+        }
+        if let Some(max_torque) = &self.wheel_max_torque {
+            wheels.max_torque *= 1.0 + sampler.draw("wheel_max_torque".to_string(), max_torque);
+        }
+        if let Some(max_speed) = &self.wheel_max_speed {
+            wheels.max_speed *= 1.0 + sampler.draw("wheel_max_speed".to_string(), max_speed);
+        }
+        let sensors = &mut scenario.sensors;
+        if let Some(rate_noise) = &self.rate_noise {
+            sensors.rate_noise = sampler.draw("rate_noise".to_string(), rate_noise).abs();
+        }
+        if let Some(attitude_noise) = &self.attitude_noise {
+            sensors.attitude_noise = sampler
+                .draw("attitude_noise".to_string(), attitude_noise)
+                .abs();
+        }
+        sensors.seed = sampler.rng.random();
+
+        if !self.parameters.is_empty() {
+            let mut value =
+                serde_json::to_value(&scenario).expect("Unable to convert the scenario to JSON");
+            for (i, parameter) in self.parameters.iter().enumerate() {
+                let target = field_mut(&mut value, &parameter.path)?;
+                let nominal = target.as_f64().ok_or_else(|| {
+                    ScenarioError::new(
+                        &format!("monte_carlo.parameters[{}].path", i),
+                        format!("`{}` is not a number of the scenario", parameter.path),
+                    )
+                })?;
+                let draw = sampler.draw(parameter.path.clone(), &parameter.distribution);
+                let dispersed = match parameter.relative {
+                    true => nominal * (1.0 + draw),
+                    false => nominal + draw,
+                };
+                *target = Value::from(dispersed);
+            }
+            return Ok(Sample {
+                scenario: Scenario::from_value(value)?,
+                draws: sampler.draws,
+            });
+        }
+        scenario.validate()?;
+        Ok(Sample {
+            scenario,
+            draws: sampler.draws,
+        })
+    }
+}
+
+/// Scenario of one run and the values drawn for it, by name.
+pub struct Sample {
+    pub scenario: Scenario,
+    pub draws: Vec<(String, f64)>,
+}
+
+struct Sampler {
+    rng: StdRng,
+    draws: Vec<(String, f64)>,
+}
+
+impl Sampler {
+    fn draw(&mut self, name: String, distribution: &Distribution) -> f64 {
+        let value = distribution.sample(&mut self.rng);
+        self.draws.push((name, value));
+        value
+    }
+
+    // Rotation of the drawn angle about a uniformly distributed axis
+    fn rotation(&mut self, name: &str, distribution: &Distribution) -> UnitQuaternion<f64> {
+        let axis = Vector3::from_fn(|_, _| self.rng.sample::<f64, _>(StandardNormal));
+        let angle = self.draw(name.to_string(), distribution);
+        UnitQuaternion::from_axis_angle(&Unit::new_normalize(axis), angle)
+    }
+}
+
+/// Outcome of one run of the campaign.
+pub struct RunSummary {
+    pub run: usize,
+    pub draws: Vec<(String, f64)>,
+    // Attitude error at the end of the run [rad]
+    pub final_error: f64,
+    // See `Metrics::settling_time` [s]
+    pub settling_time: Option<f64>,
+    // Largest speed reached by any wheel [rad/s]
+    pub peak_wheel_speed: f64,
+    // Attitude error angle [rad] and largest wheel speed [rad/s] at `times` [s], for the
+    // envelopes
+    pub times: Vec<f64>,
+    pub error_angle: Vec<f64>,
+    pub wheel_speed: Vec<f64>,
+}
+
+impl RunSummary {
+    fn new(run: usize, draws: Vec<(String, f64)>, results: &Results, scenario: &Scenario) -> Self {
+        let response = Response::new(format!("run {}", run), results, scenario);
+        let wheel_speed: Vec<(f64, f64)> = results
+            .rw_speeds
+            .iter()
+            .map(|(t, v)| (*t, v.0.amax()))
+            .collect();
+        let times: Vec<f64> = (0..ENVELOPE_POINTS)
+            .map(|i| results.total_time * i as f64 / (ENVELOPE_POINTS - 1) as f64)
+            .collect();
+        let sample = |history: &[(f64, f64)]| {
+            times
+                .iter()
+                .map(|t| value_at(history, *t).unwrap_or(f64::NAN))
+                .collect()
+        };
+        RunSummary {
+            run,
+            draws,
+            final_error: response.final_error(),
+            settling_time: response.settling_time(),
+            peak_wheel_speed: results.peak_wheel_speed(),
+            error_angle: sample(&response.error_angle),
+            wheel_speed: sample(&wheel_speed),
+            times,
+        }
+    }
+}
+
+/// Runs of a Monte Carlo campaign, in run order.
+pub struct Campaign {
+    pub runs: Vec<RunSummary>,
+}
+
+/// Spread of a history over the runs at each of `times` [s]: minimum, median, 95th percentile
+/// and maximum.
+pub struct Envelope {
+    pub times: Vec<f64>,
+    pub min: Vec<f64>,
+    pub median: Vec<f64>,
+    pub p95: Vec<f64>,
+    pub max: Vec<f64>,
+}
+
+impl Campaign {
+    /// Simulates every run of `config` in parallel, on all the cores unless the global rayon
+    /// pool is limited
+    pub fn run(nominal: &Scenario, config: &MonteCarloConfig) -> Result<Self, ScenarioError> {
+        let runs = (0..config.runs)
+            .into_par_iter()
+            .map(|run| {
+                let sample = config.sample(nominal, run).map_err(|error| {
+                    ScenarioError::new(&error.field, format!("{} (run {})", error.reason, run))
+                })?;
+                let results = simulate(&sample.scenario, None)?;
+                Ok(RunSummary::new(
+                    run,
+                    sample.draws,
+                    &results,
+                    &sample.scenario,
+                ))
+            })
+            .collect::<Result<Vec<_>, ScenarioError>>()?;
+        Ok(Campaign { runs })
+    }
+
+    /// Writes one CSV row per run: its results and the values drawn for it
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        let mut csv = String::from("run,final_error,settling_time,peak_wheel_speed");
+        if let Some(first) = self.runs.first() {
+            for (name, _) in &first.draws {
+                csv.push_str(&format!(",{}", name));
+            }
+        }
+        csv.push('\n');
+        for run in &self.runs {
+            csv.push_str(&format!(
+                "{},{},{},{}",
+                run.run,
+                run.final_error,
+                run.settling_time.unwrap_or(f64::INFINITY),
+                run.peak_wheel_speed
+            ));
+            for (_, value) in &run.draws {
+                csv.push_str(&format!(",{}", value));
+            }
+            csv.push('\n');
+        }
+        fs::write(path, csv)
+    }
+
+    pub fn error_envelope(&self) -> Envelope {
+        self.envelope(|run| &run.error_angle)
+    }
+
+    pub fn wheel_speed_envelope(&self) -> Envelope {
+        self.envelope(|run| &run.wheel_speed)
+    }
+
+    fn envelope(&self, history: fn(&RunSummary) -> &Vec<f64>) -> Envelope {
+        // Runs of different lengths are cut to the shortest one
+        let len = self
+            .runs
+            .iter()
+            .map(|run| history(run).len())
+            .min()
+            .unwrap_or(0);
+        let mut envelope = Envelope {
+            times: self
+                .runs
+                .first()
+                .map_or(Vec::new(), |run| run.times[..len].to_vec()),
+            min: Vec::with_capacity(len),
+            median: Vec::with_capacity(len),
+            p95: Vec::with_capacity(len),
+            max: Vec::with_capacity(len),
+        };
+        for i in 0..len {
+            let mut values: Vec<f64> = self.runs.iter().map(|run| history(run)[i]).collect();
+            values.sort_by(f64::total_cmp);
+            envelope.min.push(values[0]);
+            envelope.median.push(percentile(&values, 50.0));
+            envelope.p95.push(percentile(&values, 95.0));
+            envelope.max.push(values[values.len() - 1]);
+        }
+        envelope
+    }
+}
+
+impl fmt::Display for Campaign {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let runs = self.runs.len();
+        let settled = self
+            .runs
+            .iter()
+            .filter(|run| run.settling_time.is_some())
+            .count();
+        writeln!(f, "Monte Carlo: {} runs, {} settled", runs, settled)?;
+        write!(f, "{:<24}", "")?;
+        for p in PERCENTILES {
+            write!(f, "{:>12}", format!("p{}", p))?;
+        }
+        write!(f, "{:>12}", "max")?;
+
+        // Runs that do not settle count as an infinite settling time
+        let metrics: [RunMetric; 3] = [
+            ("settling time [s]", |run| {
+                run.settling_time.unwrap_or(f64::INFINITY)
+            }),
+            ("final error [rad]", |run| run.final_error),
+            ("peak wheel speed [rad/s]", |run| run.peak_wheel_speed),
+        ];
+        for (name, metric) in metrics {
+            let mut values: Vec<f64> = self.runs.iter().map(metric).collect();
+            values.sort_by(f64::total_cmp);
+            write!(f, "\n{:<24}", name)?;
+            for p in PERCENTILES.into_iter().chain([100.0]) {
+                match percentile(&values, p) {
+                    value if value.is_infinite() => write!(f, "{:>12}", "not settled")?,
+                    value => write!(f, "{:>12.4}", value)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Nearest-rank percentile `p` (0 to 100) of sorted `values`
+pub fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
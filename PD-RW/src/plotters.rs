@@ -1,73 +1,601 @@
 use plotters::{
     coord::{Shift, types::RangedCoordf64},
     prelude::*,
+    style::text_anchor::{HPos, Pos, VPos},
 };
 
+use crate::comparison::Response;
 use crate::discrete_time_model::{
-    transducer::Transducer,
-    types::{Quaternion, Vec3},
+    recorder::Recording,
+    types::{Mat3, Mode, Quaternion, Vec3},
 };
+use crate::monte_carlo::{Campaign, Envelope};
+use crate::results::{Results, held};
+use crate::sweep::{Design, Metric, Sweep};
+use crate::tuning::Tuning;
+use std::path::Path;
 
-const OUT_FILE_NAME: &str = "images/Simulation Result.png";
+const OUT_FILE_NAME: &str = "Simulation Result.png";
 
-pub fn draw(transducer: &Transducer, total_time: f64) {
-    let root = BitMapBackend::new(OUT_FILE_NAME, (1600, 1200)).into_drawing_area();
+// Color, legend label and component extracted from each sample of a history
+type Series<T> = (&'static RGBColor, &'static str, fn(&T) -> f64);
+
+/// Plots the recorded histories into `OUT_FILE_NAME` inside the output `directory`
+pub fn draw(results: &Results, directory: &Path) {
+    let out_file = directory.join(OUT_FILE_NAME);
+    let root = BitMapBackend::new(&out_file, (1600, 1200)).into_drawing_area();
     root.fill(&WHITE).unwrap();
 
-    let areas = root.split_evenly((3, 1));
+    // Integrator, sliding variable and inertia panels are only drawn when the control law publishes them
+    let has_integrator = !results.integrator.is_empty();
+    let has_sliding = !results.sliding.is_empty();
+    let has_inertia = !results.inertia.is_empty();
+    // and the mode panel when a ModeManager is present
+    let has_mode = !results.modes.is_empty();
+    // and one panel per probe
+    let rows = 3
+        + has_integrator as usize
+        + has_sliding as usize
+        + has_inertia as usize
+        + has_mode as usize
+        + results.recordings.len();
+    let areas = root.split_evenly((rows, 1));
+    let mut next_area = 3;
+    let total_time = results.total_time;
     let ten_percent = total_time * 0.1;
     let max_x = total_time + ten_percent;
-    let dt = total_time / transducer.get_q_error_history().len() as f64;
-    let q_error_range = transducer.get_q_error_range_with_margin();
-    let w_history_range = transducer.get_w_history_range_with_margin();
-    let rw_speeds_range = transducer.get_rw_speeds_range_with_margin();
-
+    let q_error_range = results.q_error_range_with_margin();
+    let w_history_range = results.w_range_with_margin();
+    let rw_speeds_range = results.rw_speeds_range_with_margin();
 
     draw_q_error(
         &areas[0],
-        transducer.get_q_error_history(),
+        &results.q_error,
         ten_percent,
         max_x,
         q_error_range.0,
         q_error_range.1,
-        dt,
     );
     draw_w_history(
         &areas[1],
-        transducer.get_w_history(),
+        &results.w,
         ten_percent,
         max_x,
         w_history_range.0,
         w_history_range.1,
-        dt,
     );
     draw_rw_speeds_history(
         &areas[2],
-        transducer.get_rw_speeds_history(),
+        &results.rw_speeds,
         ten_percent,
         max_x,
         rw_speeds_range.0,
         rw_speeds_range.1,
-        dt,
     );
 
-    root.present().expect(
-        "Unable to write result to file, please make sure 'images' dir exists under current dir",
-    );
-    println!(
+    if has_integrator {
+        let integrator_range = results.integrator_range_with_margin();
+        draw_integrator_history(
+            &areas[next_area],
+            &results.integrator,
+            ten_percent,
+            max_x,
+            integrator_range.0,
+            integrator_range.1,
+        );
+        next_area += 1;
+    }
+    if has_sliding {
+        let sliding_range = results.sliding_range_with_margin();
+        draw_sliding_history(
+            &areas[next_area],
+            &results.sliding,
+            ten_percent,
+            max_x,
+            sliding_range.0,
+            sliding_range.1,
+        );
+        next_area += 1;
+    }
+    if has_inertia {
+        let inertia_range = results.inertia_range_with_margin();
+        draw_inertia_history(
+            &areas[next_area],
+            &results.inertia,
+            ten_percent,
+            max_x,
+            inertia_range.0,
+            inertia_range.1,
+        );
+        next_area += 1;
+    }
+    if has_mode {
+        draw_mode_history(
+            &areas[next_area],
+            &results.modes,
+            ten_percent,
+            max_x,
+            total_time,
+        );
+        next_area += 1;
+    }
+    for recording in &results.recordings {
+        let range = results.recording_range_with_margin(recording);
+        draw_recording(
+            &areas[next_area],
+            recording,
+            ten_percent,
+            max_x,
+            range.0,
+            range.1,
+        );
+        next_area += 1;
+    }
+
+    root.present()
+        .expect("Unable to write result to file, please make sure the output directory exists");
+    eprintln!(
         "Nanosatellite Attitude Control Simulation has been saved to {}",
-        OUT_FILE_NAME
+        out_file.display()
     );
 }
 
+/// Plots the attitude error angle of each response on a shared time axis, into `<title>.png`
+/// inside the output `directory`
+pub fn draw_comparison(responses: &[Response], title: &str, total_time: f64, directory: &Path) {
+    let colors = [BLACK, BLUE, RED, GREEN, MAGENTA, CYAN];
+    let out_file = directory.join(format!("{}.png", title));
+    let root = BitMapBackend::new(&out_file, (1600, 800)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+
+    let ten_percent = total_time * 0.1;
+    let max_y = responses
+        .iter()
+        .flat_map(|response| response.error_angle.iter().map(|(_, angle)| *angle))
+        .fold(0.0, f64::max);
+    let mut ctx = ChartBuilder::on(&root)
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(30)
+        .caption(title, ("sans-serif", 40))
+        .build_cartesian_2d(-ten_percent..total_time + ten_percent, 0.0..max_y * 1.1)
+        .unwrap();
+
+    configure_mesh(&mut ctx, "Time [s]", "Attitude Error [rad]");
+
+    for (response, color) in responses.iter().zip(colors.iter().cycle()) {
+        ctx.draw_series(LineSeries::new(
+            response.error_angle.iter().copied(),
+            ShapeStyle::from(color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(response.label.as_str())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    }
+
+    draw_series_labels(&mut ctx);
+
+    root.present()
+        .expect("Unable to write result to file, please make sure the output directory exists");
+    eprintln!("{} has been saved to {}", title, out_file.display());
+}
+
+/// Plots the spread of the attitude error and of the largest wheel speed over the runs of a
+/// Monte Carlo campaign
+pub fn draw_envelope(campaign: &Campaign, directory: &Path) {
+    let out_file = directory.join("Monte Carlo Envelope.png");
+    let root = BitMapBackend::new(&out_file, (1600, 1000)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    let areas = root.split_evenly((2, 1));
+
+    let title = format!("Monte Carlo Envelope of {} Runs", campaign.runs.len());
+    draw_envelope_panel(
+        &areas[0],
+        &campaign.error_envelope(),
+        Some(&title),
+        "Attitude Error [rad]",
+    );
+    draw_envelope_panel(
+        &areas[1],
+        &campaign.wheel_speed_envelope(),
+        None,
+        "Max Wheel Speed [rad/s]",
+    );
+
+    root.present()
+        .expect("Unable to write result to file, please make sure the output directory exists");
+    eprintln!("Envelope has been saved to {}", out_file.display());
+}
+
+fn draw_envelope_panel(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    envelope: &Envelope,
+    caption: Option<&str>,
+    y: &str,
+) {
+    let total_time = envelope.times.last().copied().unwrap_or(0.0);
+    let ten_percent = total_time * 0.1;
+    let max_y = envelope.max.iter().copied().fold(0.0, f64::max);
+    let mut builder = ChartBuilder::on(area);
+    builder
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(30);
+    if let Some(caption) = caption {
+        builder.caption(caption, ("sans-serif", 40));
+    }
+    let mut ctx = builder
+        .build_cartesian_2d(-ten_percent..total_time + ten_percent, 0.0..max_y * 1.1)
+        .unwrap();
+
+    configure_mesh(&mut ctx, "Time [s]", y);
+
+    let time = |i: usize| envelope.times[i];
+    // Min to max band, closed along the max going forward and the min coming back
+    let band: Vec<(f64, f64)> = envelope
+        .max
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (time(i), *value))
+        .chain(
+            envelope
+                .min
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(i, value)| (time(i), *value)),
+        )
+        .collect();
+    ctx.draw_series(std::iter::once(Polygon::new(band, BLUE.mix(0.2).filled())))
+        .unwrap()
+        .label("Min to max")
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], BLUE.mix(0.2).filled()));
+
+    let series_data: [(&RGBColor, &str, &Vec<f64>); 2] = [
+        (&RED, "95th percentile", &envelope.p95),
+        (&BLACK, "Median", &envelope.median),
+    ];
+    for (color, label, values) in series_data {
+        ctx.draw_series(LineSeries::new(
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (time(i), *value)),
+            ShapeStyle::from(color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(label)
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    }
+
+    draw_series_labels(&mut ctx);
+}
+
+/// Plots `metric` over the first two parameters of a sweep as a heat map. A grid is also drawn
+/// as a surface, each of its cells holding the smallest value over the other parameters.
+pub fn draw_heat_map(sweep: &Sweep, metric: Metric, directory: &Path) {
+    let out_file = directory.join(format!("Sweep {}.png", metric.name()));
+    let root = BitMapBackend::new(&out_file, (1600, 800)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+
+    let values: Vec<f64> = sweep.points.iter().map(|point| metric.of(point)).collect();
+    let (lo, hi) = values
+        .iter()
+        .filter(|value| value.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), value| {
+            (lo.min(*value), hi.max(*value))
+        });
+    // The color map needs a non-empty range
+    let (lo, hi) = match (lo.is_finite(), lo < hi) {
+        (false, _) => (0.0, 1.0),
+        (true, false) => (lo - 0.5, hi + 0.5),
+        (true, true) => (lo, hi),
+    };
+    let mut title = metric.title().to_string();
+    if values.iter().any(|value| !value.is_finite()) {
+        title.push_str(", grey: never reached");
+    }
+    let x = &sweep.parameters[0];
+    let y = &sweep.parameters[1];
+
+    match sweep.design {
+        Design::Grid => {
+            let x_axis = x.grid().expect("the sweep ran on this grid");
+            let y_axis = y.grid().expect("the sweep ran on this grid");
+            let mut cells = vec![vec![f64::NAN; y_axis.len()]; x_axis.len()];
+            for (point, value) in sweep.points.iter().zip(&values) {
+                let i = x_axis.iter().position(|v| *v == point.values[0]).unwrap();
+                let j = y_axis.iter().position(|v| *v == point.values[1]).unwrap();
+                cells[i][j] = cells[i][j].min(*value);
+            }
+
+            let (left, right) = root.split_horizontally(800);
+            let (map_area, bar_area) = left.split_horizontally(680);
+            let mut ctx = ChartBuilder::on(&map_area)
+                .set_label_area_size(LabelAreaPosition::Left, 100)
+                .set_label_area_size(LabelAreaPosition::Bottom, 60)
+                .margin(30)
+                .caption(&title, ("sans-serif", 30))
+                .build_cartesian_2d(
+                    -0.5..x_axis.len() as f64 - 0.5,
+                    -0.5..y_axis.len() as f64 - 0.5,
+                )
+                .unwrap();
+            let x_label = |v: &f64| axis_label(&x_axis, *v);
+            let y_label = |v: &f64| axis_label(&y_axis, *v);
+            ctx.configure_mesh()
+                .disable_mesh()
+                .x_labels(x_axis.len() + 1)
+                .y_labels(y_axis.len() + 1)
+                .x_label_formatter(&x_label)
+                .y_label_formatter(&y_label)
+                .x_desc(&x.path)
+                .y_desc(&y.path)
+                .label_style(("sans-serif", 20))
+                .draw()
+                .unwrap();
+
+            let cell_points = || {
+                cells.iter().enumerate().flat_map(|(i, column)| {
+                    column
+                        .iter()
+                        .enumerate()
+                        .map(move |(j, value)| (i as f64, j as f64, *value))
+                })
+            };
+            ctx.draw_series(cell_points().map(|(i, j, value)| {
+                Rectangle::new(
+                    [(i - 0.5, j - 0.5), (i + 0.5, j + 0.5)],
+                    heat_style(value, lo, hi),
+                )
+            }))
+            .unwrap();
+            // Values are written in the cells while they are large enough to be read
+            if x_axis.len() <= 12 && y_axis.len() <= 12 {
+                ctx.draw_series(cell_points().map(|(i, j, value)| {
+                    let text = match value {
+                        value if !value.is_finite() => "-".to_string(),
+                        value if value != 0.0 && value.abs() < 0.01 => format!("{:.1e}", value),
+                        value => format!("{:.3}", value),
+                    };
+                    // Dark text on the light end of the color map
+                    let color = match (value - lo) / (hi - lo) > 0.6 || !value.is_finite() {
+                        true => BLACK,
+                        false => WHITE,
+                    };
+                    let style = ("sans-serif", 18)
+                        .into_font()
+                        .color(&color)
+                        .pos(Pos::new(HPos::Center, VPos::Center));
+                    Text::new(text, (i, j), style)
+                }))
+                .unwrap();
+            }
+            draw_color_bar(&bar_area, lo, hi);
+
+            if x_axis.len() > 1 && y_axis.len() > 1 {
+                let caption = format!("{} by {}", x.path, y.path);
+                draw_surface(&right, &cells, (&x_axis, &y_axis), lo, hi, &caption);
+            }
+        }
+        Design::LatinHypercube { .. } => {
+            let (map_area, bar_area) = root.split_horizontally(1480);
+            let coordinates: Vec<(f64, f64)> = sweep
+                .points
+                .iter()
+                .map(|point| {
+                    let parse = |value: &String| value.parse::<f64>().unwrap_or(f64::NAN);
+                    (parse(&point.values[0]), parse(&point.values[1]))
+                })
+                .collect();
+            let range = |coordinate: fn(&(f64, f64)) -> f64| {
+                let (min, max) = coordinates
+                    .iter()
+                    .map(coordinate)
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                        (min.min(value), max.max(value))
+                    });
+                let margin = (max - min).max(f64::EPSILON) * 0.05;
+                min - margin..max + margin
+            };
+            let mut ctx = ChartBuilder::on(&map_area)
+                .set_label_area_size(LabelAreaPosition::Left, 100)
+                .set_label_area_size(LabelAreaPosition::Bottom, 60)
+                .margin(30)
+                .caption(&title, ("sans-serif", 30))
+                .build_cartesian_2d(range(|c| c.0), range(|c| c.1))
+                .unwrap();
+            configure_mesh(&mut ctx, &x.path, &y.path);
+            ctx.draw_series(
+                coordinates
+                    .iter()
+                    .zip(&values)
+                    .map(|(position, value)| Circle::new(*position, 8, heat_style(*value, lo, hi))),
+            )
+            .unwrap();
+            draw_color_bar(&bar_area, lo, hi);
+        }
+    }
+
+    root.present()
+        .expect("Unable to write result to file, please make sure the output directory exists");
+    eprintln!("Heat map has been saved to {}", out_file.display());
+}
+
+// Grid value at an integer axis position, nothing between them
+fn axis_label(axis: &[String], position: f64) -> String {
+    let index = position.round();
+    match (position - index).abs() < 1e-6 && index >= 0.0 {
+        true => axis.get(index as usize).cloned().unwrap_or_default(),
+        false => String::new(),
+    }
+}
+
+fn heat_style(value: f64, lo: f64, hi: f64) -> ShapeStyle {
+    match value.is_finite() {
+        true => ViridisRGB.get_color_normalized(value, lo, hi).filled(),
+        false => BLACK.mix(0.3).filled(),
+    }
+}
+
+fn draw_color_bar(area: &DrawingArea<BitMapBackend, Shift>, lo: f64, hi: f64) {
+    let mut ctx = ChartBuilder::on(area)
+        .set_label_area_size(LabelAreaPosition::Left, 80)
+        .margin_top(80)
+        .margin_bottom(90)
+        .margin_right(20)
+        .build_cartesian_2d(0.0..1.0, lo..hi)
+        .unwrap();
+    ctx.configure_mesh()
+        .disable_mesh()
+        .disable_x_axis()
+        .y_label_formatter(&|v| format!("{:.3}", v))
+        .label_style(("sans-serif", 18))
+        .draw()
+        .unwrap();
+    let steps = 100;
+    let step = (hi - lo) / steps as f64;
+    ctx.draw_series((0..steps).map(|i| {
+        let bottom = lo + i as f64 * step;
+        Rectangle::new(
+            [(0.0, bottom), (1.0, bottom + step)],
+            heat_style(bottom + step / 2.0, lo, hi),
+        )
+    }))
+    .unwrap();
+}
+
+fn draw_surface(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    cells: &[Vec<f64>],
+    (x_axis, y_axis): (&[String], &[String]),
+    lo: f64,
+    hi: f64,
+    caption: &str,
+) {
+    let mut ctx = ChartBuilder::on(area)
+        .margin(30)
+        .caption(caption, ("sans-serif", 30))
+        .build_cartesian_3d(
+            0.0..(x_axis.len() - 1) as f64,
+            lo..hi,
+            0.0..(y_axis.len() - 1) as f64,
+        )
+        .unwrap();
+    ctx.with_projection(|mut projection| {
+        projection.pitch = 0.5;
+        projection.yaw = 0.7;
+        projection.scale = 0.8;
+        projection.into_matrix()
+    });
+    let x_label = |v: &f64| axis_label(x_axis, *v);
+    let z_label = |v: &f64| axis_label(y_axis, *v);
+    ctx.configure_axes()
+        .x_labels(x_axis.len())
+        .z_labels(y_axis.len())
+        .x_formatter(&x_label)
+        .z_formatter(&z_label)
+        .label_style(("sans-serif", 16))
+        .light_grid_style(BLACK.mix(0.1))
+        .max_light_lines(3)
+        .draw()
+        .unwrap();
+
+    // Values that are never reached are drawn at the top of the scale
+    let height = |x: f64, z: f64| match cells[x as usize][z as usize] {
+        value if value.is_finite() => value,
+        _ => hi,
+    };
+    let style = |value: &f64| {
+        ViridisRGB
+            .get_color_normalized(*value, lo, hi)
+            .mix(0.9)
+            .filled()
+    };
+    ctx.draw_series(
+        SurfaceSeries::xoz(
+            (0..x_axis.len()).map(|i| i as f64),
+            (0..y_axis.len()).map(|j| j as f64),
+            height,
+        )
+        .style_func(&style),
+    )
+    .unwrap();
+}
+
+/// Plots the best cost of a tuning at every iteration, and its parameters within their bounds
+pub fn draw_convergence(tuning: &Tuning, directory: &Path) {
+    let colors = [BLUE, RED, GREEN, MAGENTA, CYAN, BLACK];
+    let out_file = directory.join("Tuning Convergence.png");
+    let root = BitMapBackend::new(&out_file, (1600, 1000)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    let areas = root.split_evenly((2, 1));
+
+    let iterations = tuning.iterations.len() as f64;
+    let costs: Vec<f64> = tuning
+        .iterations
+        .iter()
+        .map(|iteration| iteration.best.cost)
+        .collect();
+    let (min_cost, max_cost) = costs
+        .iter()
+        .filter(|cost| cost.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), cost| {
+            (min.min(*cost), max.max(*cost))
+        });
+    let margin = (max_cost - min_cost).max(f64::EPSILON) * 0.1;
+    let mut ctx = ChartBuilder::on(&areas[0])
+        .set_label_area_size(LabelAreaPosition::Left, 80)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(30)
+        .caption("Tuning Convergence", ("sans-serif", 40))
+        .build_cartesian_2d(0.0..iterations, min_cost - margin..max_cost + margin)
+        .unwrap();
+    configure_mesh(&mut ctx, "", "Best Cost");
+    ctx.draw_series(LineSeries::new(
+        costs.iter().enumerate().map(|(i, cost)| (i as f64, *cost)),
+        ShapeStyle::from(&BLACK).stroke_width(2),
+    ))
+    .unwrap();
+
+    let mut ctx = ChartBuilder::on(&areas[1])
+        .set_label_area_size(LabelAreaPosition::Left, 80)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(30)
+        .build_cartesian_2d(0.0..iterations, -0.05..1.05)
+        .unwrap();
+    configure_mesh(&mut ctx, "Iteration", "Parameter, 0 = min, 1 = max");
+    for (i, (parameter, color)) in tuning
+        .parameters
+        .iter()
+        .zip(colors.iter().cycle())
+        .enumerate()
+    {
+        let range = parameter.max - parameter.min;
+        ctx.draw_series(LineSeries::new(
+            tuning.iterations.iter().enumerate().map(|(k, iteration)| {
+                (k as f64, (iteration.best.values[i] - parameter.min) / range)
+            }),
+            ShapeStyle::from(color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(parameter.path.as_str())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    }
+    draw_series_labels(&mut ctx);
+
+    root.present()
+        .expect("Unable to write result to file, please make sure the output directory exists");
+    eprintln!("Convergence has been saved to {}", out_file.display());
+}
+
 fn draw_q_error(
     area: &DrawingArea<BitMapBackend, Shift>,
-    data: &[Quaternion],
+    data: &[(f64, Quaternion)],
     min_x: f64,
     max_x: f64,
     min_y: f64,
     max_y: f64,
-    dt: f64,
 ) {
     let mut ctx = ChartBuilder::on(area)
         .set_label_area_size(LabelAreaPosition::Left, 60)
@@ -82,7 +610,7 @@ fn draw_q_error(
 
     configure_mesh(&mut ctx, "", "Attitude Error");
 
-    let series_data: [(&RGBColor, &str, fn(&Quaternion) -> f64); 3] = [
+    let series_data: [Series<Quaternion>; 3] = [
         (&BLUE, "Error q_x", |q| q.0.i),
         (&RED, "Error q_y", |q| q.0.j),
         (&GREEN, "Error q_z", |q| q.0.k),
@@ -90,9 +618,7 @@ fn draw_q_error(
 
     for (color, label, extractor) in series_data {
         ctx.draw_series(LineSeries::new(
-            data.iter()
-                .enumerate()
-                .map(|(i, q)| (i as f64 * dt, extractor(q))),
+            data.iter().map(|(t, q)| (*t, extractor(q))),
             ShapeStyle::from(color).stroke_width(2),
         ))
         .unwrap()
@@ -105,12 +631,11 @@ fn draw_q_error(
 
 fn draw_w_history(
     area: &DrawingArea<BitMapBackend, Shift>,
-    data: &[Vec3],
+    data: &[(f64, Vec3)],
     min_x: f64,
     max_x: f64,
     min_y: f64,
     max_y: f64,
-    dt: f64,
 ) {
     let mut ctx = ChartBuilder::on(area)
         .margin(30)
@@ -121,7 +646,7 @@ fn draw_w_history(
 
     configure_mesh(&mut ctx, "", "Angular Velocity [rad/s]");
 
-    let series_data: [(&RGBColor, &str, fn(&Vec3) -> f64); 3] = [
+    let series_data: [Series<Vec3>; 3] = [
         (&BLUE, "w_x", |v| v.0.x),
         (&RED, "w_y", |v| v.0.y),
         (&GREEN, "w_z", |v| v.0.z),
@@ -129,9 +654,7 @@ fn draw_w_history(
 
     for (color, label, extractor) in series_data {
         ctx.draw_series(LineSeries::new(
-            data.iter()
-                .enumerate()
-                .map(|(i, v)| (i as f64 * dt, extractor(v))),
+            data.iter().map(|(t, v)| (*t, extractor(v))),
             ShapeStyle::from(color).stroke_width(2),
         ))
         .unwrap()
@@ -144,12 +667,11 @@ fn draw_w_history(
 
 fn draw_rw_speeds_history(
     area: &DrawingArea<BitMapBackend, Shift>,
-    data: &[Vec3],
+    data: &[(f64, Vec3)],
     min_x: f64,
     max_x: f64,
     min_y: f64,
     max_y: f64,
-    dt: f64,
 ) {
     let mut ctx = ChartBuilder::on(area)
         .margin(30)
@@ -160,7 +682,7 @@ fn draw_rw_speeds_history(
 
     configure_mesh(&mut ctx, "Time [s]", "Reaction Wheel Speed [rad/s]");
 
-    let series_data: [(&RGBColor, &str, fn(&Vec3) -> f64); 3] = [
+    let series_data: [Series<Vec3>; 3] = [
         (&BLUE, "RW 1", |v| v.0.x),
         (&RED, "RW 2", |v| v.0.y),
         (&GREEN, "RW 3", |v| v.0.z),
@@ -168,9 +690,43 @@ fn draw_rw_speeds_history(
 
     for (color, label, extractor) in series_data {
         ctx.draw_series(LineSeries::new(
-            data.iter()
-                .enumerate()
-                .map(|(i, v)| (i as f64 * dt, extractor(v))),
+            data.iter().map(|(t, v)| (*t, extractor(v))),
+            ShapeStyle::from(color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(label)
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    }
+
+    draw_series_labels(&mut ctx);
+}
+
+fn draw_integrator_history(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    data: &[(f64, Vec3)],
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+) {
+    let mut ctx = ChartBuilder::on(area)
+        .margin(30)
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(-min_x..max_x, min_y..max_y)
+        .unwrap();
+
+    configure_mesh(&mut ctx, "Time [s]", "Integral Torque [Nm]");
+
+    let series_data: [Series<Vec3>; 3] = [
+        (&BLUE, "I_x", |v| v.0.x),
+        (&RED, "I_y", |v| v.0.y),
+        (&GREEN, "I_z", |v| v.0.z),
+    ];
+
+    for (color, label, extractor) in series_data {
+        ctx.draw_series(LineSeries::new(
+            data.iter().map(|(t, v)| (*t, extractor(v))),
             ShapeStyle::from(color).stroke_width(2),
         ))
         .unwrap()
@@ -181,12 +737,173 @@ fn draw_rw_speeds_history(
     draw_series_labels(&mut ctx);
 }
 
+fn draw_sliding_history(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    data: &[(f64, Vec3)],
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+) {
+    let mut ctx = ChartBuilder::on(area)
+        .margin(30)
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(-min_x..max_x, min_y..max_y)
+        .unwrap();
+
+    configure_mesh(&mut ctx, "Time [s]", "Sliding Variable [rad/s]");
+
+    let series_data: [Series<Vec3>; 3] = [
+        (&BLUE, "s_x", |v| v.0.x),
+        (&RED, "s_y", |v| v.0.y),
+        (&GREEN, "s_z", |v| v.0.z),
+    ];
+
+    for (color, label, extractor) in series_data {
+        ctx.draw_series(LineSeries::new(
+            data.iter().map(|(t, v)| (*t, extractor(v))),
+            ShapeStyle::from(color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(label)
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    }
+
+    draw_series_labels(&mut ctx);
+}
+
+fn draw_inertia_history(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    data: &[(f64, Mat3)],
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+) {
+    let mut ctx = ChartBuilder::on(area)
+        .margin(30)
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(-min_x..max_x, min_y..max_y)
+        .unwrap();
+
+    configure_mesh(&mut ctx, "Time [s]", "Inertia Estimate [kg m^2]");
+
+    let series_data: [Series<Mat3>; 3] = [
+        (&BLUE, "I_xx", |m| m.0[(0, 0)]),
+        (&RED, "I_yy", |m| m.0[(1, 1)]),
+        (&GREEN, "I_zz", |m| m.0[(2, 2)]),
+    ];
+
+    for (color, label, extractor) in series_data {
+        ctx.draw_series(LineSeries::new(
+            data.iter().map(|(t, m)| (*t, extractor(m))),
+            ShapeStyle::from(color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(label)
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    }
+
+    draw_series_labels(&mut ctx);
+}
+
+fn draw_recording(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    recording: &Recording,
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+) {
+    let colors = [BLUE, RED, GREEN, BLACK, MAGENTA, CYAN];
+    let mut ctx = ChartBuilder::on(area)
+        .margin(30)
+        .set_label_area_size(LabelAreaPosition::Left, 100)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(-min_x..max_x, min_y..max_y)
+        .unwrap();
+
+    // Signals of any scale, small ones labelled in scientific notation and rounding noise as 0
+    let scale = min_y.abs().max(max_y.abs());
+    ctx.configure_mesh()
+        .x_desc("Time [s]")
+        .y_desc(recording.name.as_str())
+        .y_label_formatter(&|y| match *y {
+            y if y.abs() < 1e-6 * scale => "0".to_string(),
+            y if scale < 0.01 => format!("{:.1e}", y),
+            y => format!("{}", y),
+        })
+        .label_style(("sans-serif", 20))
+        .draw()
+        .unwrap();
+
+    for (i, (component, color)) in recording
+        .components
+        .iter()
+        .zip(colors.into_iter().cycle())
+        .enumerate()
+    {
+        ctx.draw_series(LineSeries::new(
+            recording.samples.iter().map(|(t, values)| (*t, values[i])),
+            ShapeStyle::from(&color).stroke_width(2),
+        ))
+        .unwrap()
+        .label(format!("{} {}", recording.name, component))
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    draw_series_labels(&mut ctx);
+}
+
+fn draw_mode_history(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    data: &[(f64, Mode)],
+    min_x: f64,
+    max_x: f64,
+    total_time: f64,
+) {
+    let mut ctx = ChartBuilder::on(area)
+        .margin(30)
+        .set_label_area_size(LabelAreaPosition::Left, 160)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(-min_x..max_x, -0.5..Mode::ALL.len() as f64 - 0.5)
+        .unwrap();
+
+    // One tick per mode, labelled with its name
+    ctx.configure_mesh()
+        .x_desc("Time [s]")
+        .y_labels(Mode::ALL.len())
+        .y_label_formatter(&|y| {
+            let index = y.round() as usize;
+            Mode::ALL
+                .get(index)
+                .map_or(String::new(), |mode| mode.to_string())
+        })
+        .label_style(("sans-serif", 20))
+        .draw()
+        .unwrap();
+
+    // Each mode holds until the next transition, the last one until the end of the run
+    let mut points = Vec::new();
+    for (start, end, mode) in held(data, total_time) {
+        points.push((start, mode.index() as f64));
+        points.push((end, mode.index() as f64));
+    }
+    ctx.draw_series(LineSeries::new(
+        points,
+        ShapeStyle::from(&BLUE).stroke_width(2),
+    ))
+    .unwrap();
+}
+
 fn draw_series_labels<'a>(
     ctx: &mut ChartContext<'a, BitMapBackend<'a>, Cartesian2d<RangedCoordf64, RangedCoordf64>>,
 ) {
     ctx.configure_series_labels()
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .position(SeriesLabelPosition::UpperRight)
         .draw()
         .unwrap();
@@ -0,0 +1,312 @@
+use crate::{
+    results::{Results, held},
+    scenario::{Scenario, ScenarioError, ensure, finite, non_negative},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Bound a run must keep, e.g. a pointing error below 0.5 deg after 60 s or the wheels below 90 %
+/// of their maximum speed. `run` checks every requirement of the scenario against the recorded
+/// histories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Requirement {
+    pub name: String,
+    pub signal: Signal,
+    // Largest value the signal may take, in its units
+    pub max: f64,
+    // Part of the run the bound applies to [s], the whole run by default
+    #[serde(default)]
+    pub after: f64,
+    #[serde(default)]
+    pub before: Option<f64>,
+}
+
+/// Recorded quantity a requirement bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Signal {
+    // Rotation angle of the true attitude error, from the plant state [rad]
+    PointingError,
+    // Norm of the angular velocity of the body [rad/s]
+    BodyRate,
+    // Largest speed of any wheel [rad/s]
+    WheelSpeed,
+    // Largest speed of any wheel as a fraction of wheels.max_speed
+    WheelSpeedRatio,
+}
+
+impl Signal {
+    fn name(&self) -> &'static str {
+        match self {
+            Signal::PointingError => "pointing_error",
+            Signal::BodyRate => "body_rate",
+            Signal::WheelSpeed => "wheel_speed",
+            Signal::WheelSpeedRatio => "wheel_speed_ratio",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            Signal::PointingError => " rad",
+            Signal::BodyRate | Signal::WheelSpeed => " rad/s",
+            Signal::WheelSpeedRatio => "",
+        }
+    }
+
+    // Value of the signal at every sample of `results`, with its time [s]
+    fn history(&self, results: &Results, scenario: &Scenario) -> Vec<(f64, f64)> {
+        let max_speed = scenario.wheels.max_speed;
+        match self {
+            Signal::PointingError => results.error_angle(),
+            Signal::BodyRate => results.w.iter().map(|(t, w)| (*t, w.0.norm())).collect(),
+            Signal::WheelSpeed => results
+                .rw_speeds
+                .iter()
+                .map(|(t, v)| (*t, v.0.amax()))
+                .collect(),
+            Signal::WheelSpeedRatio => results
+                .rw_speeds
+                .iter()
+                .map(|(t, v)| (*t, v.0.amax() / max_speed))
+                .collect(),
+        }
+    }
+}
+
+impl Requirement {
+    pub(crate) fn validate(&self, field: &str, total_time: f64) -> Result<(), ScenarioError> {
+        ensure(
+            !self.name.trim().is_empty(),
+            &format!("{}.name", field),
+            "must not be empty",
+        )?;
+        finite(&format!("{}.max", field), self.max)?;
+        non_negative(&format!("{}.after", field), self.after)?;
+        ensure(
+            self.after < total_time,
+            &format!("{}.after", field),
+            "must be before simulation.total_time",
+        )?;
+        match self.before {
+            Some(before) => ensure(
+                before > self.after,
+                &format!("{}.before", field),
+                "must be after `after`",
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the samples of `results` within the window of the requirement
+    pub fn check(&self, results: &Results, scenario: &Scenario) -> Verdict {
+        let history = self.signal.history(results, scenario);
+        let before = self.before.unwrap_or(f64::INFINITY);
+        let mut violation: Option<Violation> = None;
+        for (time, end, value) in held(&history, results.total_time) {
+            let value = *value;
+            if time < self.after || time > before || value <= self.max {
+                continue;
+            }
+            let violation = violation.get_or_insert(Violation {
+                first: time,
+                last: time,
+                duration: 0.0,
+                worst: value,
+                worst_time: time,
+            });
+            violation.last = time;
+            violation.duration += end.min(before) - time;
+            if value > violation.worst {
+                violation.worst = value;
+                violation.worst_time = time;
+            }
+        }
+        Verdict {
+            requirement: self.clone(),
+            violation,
+        }
+    }
+}
+
+/// Samples of a run outside the bound of a requirement.
+#[derive(Debug, Clone, Copy)]
+pub struct Violation {
+    // First and last violating samples and the time spent over the bound [s]
+    pub first: f64,
+    pub last: f64,
+    pub duration: f64,
+    // Largest value of the signal and its time
+    pub worst: f64,
+    pub worst_time: f64,
+}
+
+/// Outcome of a requirement for one run.
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub requirement: Requirement,
+    pub violation: Option<Violation>,
+}
+
+/// Verdicts of every requirement of a scenario.
+pub struct Verification {
+    pub verdicts: Vec<Verdict>,
+}
+
+impl Verification {
+    pub fn new(results: &Results, scenario: &Scenario) -> Self {
+        Verification {
+            verdicts: scenario
+                .requirements
+                .iter()
+                .map(|requirement| requirement.check(results, scenario))
+                .collect(),
+        }
+    }
+
+    pub fn violated(&self) -> usize {
+        self.verdicts
+            .iter()
+            .filter(|verdict| verdict.violation.is_some())
+            .count()
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Requirements: {} of {} violated",
+            self.violated(),
+            self.verdicts.len()
+        )?;
+        for Verdict {
+            requirement,
+            violation,
+        } in &self.verdicts
+        {
+            let signal = requirement.signal;
+            let status = match violation {
+                Some(_) => "FAIL",
+                None => "pass",
+            };
+            write!(
+                f,
+                "\n  {}  {}: {} <= {}{}",
+                status,
+                requirement.name,
+                signal.name(),
+                requirement.max,
+                signal.unit()
+            )?;
+            match (requirement.after, requirement.before) {
+                (after, Some(before)) => write!(f, " from {} s to {} s", after, before)?,
+                (after, None) if after > 0.0 => write!(f, " after {} s", after)?,
+                _ => {}
+            }
+            if let Some(violation) = violation {
+                write!(
+                    f,
+                    "\n        violated from {:.2} s to {:.2} s ({:.2} s in total), worst {:.6}{} at {:.2} s",
+                    violation.first,
+                    violation.last,
+                    violation.duration,
+                    violation.worst,
+                    signal.unit(),
+                    violation.worst_time
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete_time_model::types::{Quaternion, Vec3};
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    // Run of 10 s with one sample of the body rate along x per second
+    fn results() -> Results {
+        let rates = [0.0, 0.0, 3.0, 3.0, 0.0, 0.0, 5.0, 0.0, 0.0, 0.0];
+        Results {
+            total_time: 10.0,
+            margin_ratio: 0.0,
+            q_error: Vec::new(),
+            pointing_error: Vec::new(),
+            w: rates
+                .iter()
+                .enumerate()
+                .map(|(i, w)| (i as f64, Vec3(Vector3::new(*w, 0.0, 0.0))))
+                .collect(),
+            rw_speeds: Vec::new(),
+            integrator: Vec::new(),
+            sliding: Vec::new(),
+            inertia: Vec::new(),
+            modes: Vec::new(),
+            faults: Vec::new(),
+            torque: Vec::new(),
+            recordings: Vec::new(),
+        }
+    }
+
+    fn check(after: f64, before: Option<f64>) -> Option<Violation> {
+        let scenario = Scenario::from_toml(include_str!("../scenarios/default.toml")).unwrap();
+        let requirement = Requirement {
+            name: "rate".to_string(),
+            signal: Signal::BodyRate,
+            max: 1.0,
+            after,
+            before,
+        };
+        requirement.check(&results(), &scenario).violation
+    }
+
+    #[test]
+    fn violation_over_the_whole_run() {
+        let violation = check(0.0, None).unwrap();
+        assert_eq!((violation.first, violation.last), (2.0, 6.0));
+        // The samples at 2, 3 and 6 s each hold for a second
+        assert_eq!(violation.duration, 3.0);
+        assert_eq!((violation.worst, violation.worst_time), (5.0, 6.0));
+    }
+
+    #[test]
+    fn violation_within_a_window() {
+        let violation = check(3.0, Some(6.5)).unwrap();
+        assert_eq!((violation.first, violation.last), (3.0, 6.0));
+        // The sample at 6 s only counts until the end of the window
+        assert_eq!(violation.duration, 1.5);
+        assert_eq!((violation.worst, violation.worst_time), (5.0, 6.0));
+    }
+
+    #[test]
+    fn no_violation_outside_the_window() {
+        assert!(check(7.0, None).is_none());
+        assert!(check(4.0, Some(5.5)).is_none());
+    }
+
+    #[test]
+    fn pointing_error_is_the_true_error() {
+        let scenario = Scenario::from_toml(include_str!("../scenarios/default.toml")).unwrap();
+        let about_x = |angle: f64| {
+            let q = UnitQuaternion::from_scaled_axis(Vector3::x() * angle);
+            Quaternion(*q.quaternion())
+        };
+        // The measurements hide an error of 0.2 rad from 4 s on
+        let mut results = results();
+        results.q_error = vec![(0.0, about_x(0.0))];
+        results.pointing_error = vec![(0.0, about_x(0.0)), (4.0, about_x(0.2))];
+        let requirement = Requirement {
+            name: "pointing".to_string(),
+            signal: Signal::PointingError,
+            max: 0.1,
+            after: 0.0,
+            before: None,
+        };
+        let violation = requirement.check(&results, &scenario).violation.unwrap();
+        assert_eq!((violation.first, violation.last), (4.0, 4.0));
+        assert!((violation.worst - 0.2).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::discrete_time_model::{
+    recorder::Recording,
+    transducer::Transducer,
+    types::{Fault, Mat3, Mode, Quaternion, Vec3},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+pub const RESULTS_FILE_NAME: &str = "results.json";
+
+/// Histories recorded by the Transducer during one run, each sample with the simulation time it
+/// was recorded at [s]. `run` saves them as JSON next to the plots, so a run can be plotted again
+/// or post-processed without simulating it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Results {
+    // Simulated time [s]
+    pub total_time: f64,
+    // Margin around the recorded ranges in the plots
+    pub margin_ratio: f64,
+    // Error quaternion the Controller computed from the measurements
+    pub q_error: Vec<(f64, Quaternion)>,
+    // True attitude of the body relative to the Guidance target, free of sensor noise and
+    // dropouts
+    #[serde(default)]
+    pub pointing_error: Vec<(f64, Quaternion)>,
+    pub w: Vec<(f64, Vec3)>,
+    pub rw_speeds: Vec<(f64, Vec3)>,
+    // Histories only published by some control laws
+    #[serde(default)]
+    pub integrator: Vec<(f64, Vec3)>,
+    #[serde(default)]
+    pub sliding: Vec<(f64, Vec3)>,
+    #[serde(default)]
+    pub inertia: Vec<(f64, Mat3)>,
+    // Mode transitions and detected faults
+    #[serde(default)]
+    pub modes: Vec<(f64, Mode)>,
+    #[serde(default)]
+    pub faults: Vec<(f64, Fault)>,
+    // Torque delivered by the wheels from its time on, until the next one [Nm]
+    #[serde(default)]
+    pub torque: Vec<(f64, Vec3)>,
+    // Signals of the probes of the scenario
+    #[serde(default)]
+    pub recordings: Vec<Recording>,
+}
+
+impl Results {
+    pub(crate) fn new(
+        transducer: &Transducer,
+        recordings: Vec<Recording>,
+        total_time: f64,
+        margin_ratio: f64,
+    ) -> Self {
+        Results {
+            total_time,
+            margin_ratio,
+            q_error: transducer.get_q_error_history().to_vec(),
+            pointing_error: transducer.get_pointing_error_history().to_vec(),
+            w: transducer.get_w_history().to_vec(),
+            rw_speeds: transducer.get_rw_speeds_history().to_vec(),
+            integrator: transducer.get_integrator_history().to_vec(),
+            sliding: transducer.get_sliding_history().to_vec(),
+            inertia: transducer.get_inertia_history().to_vec(),
+            modes: transducer.get_mode_history().to_vec(),
+            faults: transducer.get_fault_history().to_vec(),
+            torque: transducer.get_torque_history().to_vec(),
+            recordings,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// True pointing error, or the Controller's error quaternion for results saved before it was
+    /// recorded
+    pub fn true_error(&self) -> &[(f64, Quaternion)] {
+        match self.pointing_error.is_empty() {
+            true => &self.q_error,
+            false => &self.pointing_error,
+        }
+    }
+
+    /// Rotation angle of the true pointing error at every sample [rad]
+    pub fn error_angle(&self) -> Vec<(f64, f64)> {
+        self.true_error()
+            .iter()
+            .map(|(t, q)| (*t, 2.0 * q.0.vector().norm().min(1.0).asin()))
+            .collect()
+    }
+
+    /// Largest speed reached by any wheel [rad/s]
+    pub fn peak_wheel_speed(&self) -> f64 {
+        self.rw_speeds
+            .iter()
+            .map(|(_, v)| v.0.amax())
+            .fold(0.0, f64::max)
+    }
+
+    pub fn q_error_range_with_margin(&self) -> (f64, f64) {
+        let values = self.q_error.iter().flat_map(|(_, q)| [q.0.i, q.0.j, q.0.k]);
+        self.range_with_margin((0.0, 0.0), values)
+    }
+
+    pub fn w_range_with_margin(&self) -> (f64, f64) {
+        self.range_with_margin((0.0, 0.0), components(&self.w))
+    }
+
+    pub fn rw_speeds_range_with_margin(&self) -> (f64, f64) {
+        self.range_with_margin((0.0, 0.0), components(&self.rw_speeds))
+    }
+
+    pub fn integrator_range_with_margin(&self) -> (f64, f64) {
+        self.range_with_margin((0.0, 0.0), components(&self.integrator))
+    }
+
+    pub fn sliding_range_with_margin(&self) -> (f64, f64) {
+        self.range_with_margin((0.0, 0.0), components(&self.sliding))
+    }
+
+    pub fn inertia_range_with_margin(&self) -> (f64, f64) {
+        // Only the principal moments are plotted. Inertia is far from zero, so its range starts
+        // empty
+        let values = self
+            .inertia
+            .iter()
+            .flat_map(|(_, m)| [m.0[(0, 0)], m.0[(1, 1)], m.0[(2, 2)]]);
+        self.range_with_margin((f64::INFINITY, f64::NEG_INFINITY), values)
+    }
+
+    pub fn recording_range_with_margin(&self, recording: &Recording) -> (f64, f64) {
+        let values = recording
+            .samples
+            .iter()
+            .flat_map(|(_, values)| values.iter().copied());
+        self.range_with_margin((0.0, 0.0), values)
+    }
+
+    fn range_with_margin(
+        &self,
+        start: (f64, f64),
+        values: impl Iterator<Item = f64>,
+    ) -> (f64, f64) {
+        let range = values.fold(start, |(min_v, max_v), val| {
+            (f64::min(min_v, val), f64::max(max_v, val))
+        });
+        let margin = (range.1 - range.0).abs() * self.margin_ratio;
+        (range.0 - margin, range.1 + margin)
+    }
+}
+
+fn components(history: &[(f64, Vec3)]) -> impl Iterator<Item = f64> + '_ {
+    history.iter().flat_map(|(_, v)| [v.0.x, v.0.y, v.0.z])
+}
+
+/// Samples of `history` with the interval each one holds over [s]: from its time until the next
+/// sample, the last one until `end`
+pub fn held<T>(history: &[(f64, T)], end: f64) -> impl Iterator<Item = (f64, f64, &T)> + '_ {
+    history.iter().enumerate().map(move |(i, (t, value))| {
+        let next = history.get(i + 1).map_or(end, |(t_next, _)| *t_next);
+        (*t, next.max(*t), value)
+    })
+}
+
+/// Value of `history` at time `t`: the last sample at or before it, the first one before any
+/// sample
+pub fn value_at<T: Copy>(history: &[(f64, T)], t: f64) -> Option<T> {
+    let i = history.partition_point(|(time, _)| *time <= t);
+    history.get(i.saturating_sub(1)).map(|(_, value)| *value)
+}
@@ -0,0 +1,1578 @@
+use crate::discrete_time_model::{
+    DiscreteTimeModel, ModeSetup, ModelOptions, Plant, ProbeSetup, Rates, SensorNoise, WheelFault,
+    WheelFaultKind,
+    attitude_error::{AttitudeError, ErrorRepresentation},
+    control_law::{
+        Adaptive, AntiWindup, BoundaryLayer, Compensator, ControlLaw, Detumble, Mpc, MpcConfig, PD,
+        Pid, SlidingMode,
+        discretization::{Discretization, TransferFunction},
+        lqr::{LinearizedModel, LqrGains, LqrWeights},
+    },
+    fdir::Injection,
+    guidance::{
+        EigenAxisSlew, GroundTarget, GuidanceLaw, InertialHold, Nadir, Scripted, SlewLimits,
+        SunPointing, WaypointSlew,
+        keep_out::{KeepOutCone, PlannerConfig, plan_slew},
+        orbit::CircularOrbit,
+    },
+    mode_manager::ModeCriteria,
+    recorder::{ProbeError, RecorderConfig, Trigger},
+    types::{Mat3, Mode, Quaternion, Vec3},
+};
+use crate::metrics::MetricsConfig;
+use crate::monte_carlo::MonteCarloConfig;
+use crate::requirements::Requirement;
+use crate::tuning::TuningConfig;
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{f64::consts::PI, fmt, fs, path::Path, str::FromStr};
+
+/// Simulation scenario: the spacecraft, its wheels, sensors and on-board software, the initial
+/// state, the duration and the outputs of a run. Read from a TOML or JSON file, see
+/// `scenarios/default.toml` for the reference 1U CubeSat scenario.
+///
+/// All quantities are in SI units and angles in radians. Vectors are `[x, y, z]`, matrices are
+/// written row by row and attitudes are either `{ axis = [x, y, z], angle = a }` or a quaternion
+/// `[w, x, y, z]` (body frame to inertial frame).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub simulation: Simulation,
+    pub spacecraft: Spacecraft,
+    pub wheels: Wheels,
+    #[serde(default)]
+    pub sensors: Sensors,
+    pub controller: ControllerConfig,
+    pub guidance: GuidanceConfig,
+    pub initial_state: InitialState,
+    // ADCS modes (None keeps the controller and guidance laws for the whole run)
+    #[serde(default)]
+    pub modes: Option<Modes>,
+    #[serde(default)]
+    pub faults: Vec<FaultConfig>,
+    // Adds the FDIR component
+    #[serde(default)]
+    pub fdir: bool,
+    // Settings of the performance figures of a run
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    // Bounds checked after each run
+    #[serde(default)]
+    pub requirements: Vec<Requirement>,
+    // Extra signals recorded during the run
+    #[serde(default)]
+    pub probes: Vec<ProbeConfig>,
+    // Dispersions of the `montecarlo` command
+    #[serde(default)]
+    pub monte_carlo: Option<MonteCarloConfig>,
+    // Tuned parameters and cost of the `tune` command
+    #[serde(default)]
+    pub tuning: Option<TuningConfig>,
+    #[serde(default)]
+    pub output: Output,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Simulation {
+    // Plant integration step [s]
+    pub h: f64,
+    // Simulated time [s]
+    pub total_time: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spacecraft {
+    // Inertia tensor of the satellite in the body frame [kg m^2]
+    pub inertia: [[f64; 3]; 3],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wheels {
+    // Inertia of the x, y and z wheels about their spin axes [kg m^2]
+    pub inertia: [f64; 3],
+    // Maximum torque of each wheel [Nm]
+    pub max_torque: f64,
+    // Maximum angular speed of each wheel [rad/s]
+    pub max_speed: f64,
+    #[serde(default)]
+    pub initial_speeds: [f64; 3],
+    // Period at which a new torque command is latched (None at every plant step) [s]
+    #[serde(default)]
+    pub actuator_period: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sensors {
+    // Sampling period of the attitude and rate sensors (None at every plant step) [s]
+    #[serde(default)]
+    pub period: Option<f64>,
+    // Standard deviation of the rate noise on each axis [rad/s]
+    #[serde(default)]
+    pub rate_noise: f64,
+    // Standard deviation of the attitude noise about each axis [rad]
+    #[serde(default)]
+    pub attitude_noise: f64,
+    // Seed of the noise generator
+    #[serde(default)]
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerConfig {
+    pub law: LawConfig,
+    // Period of the control law (None at every plant step) [s]
+    #[serde(default)]
+    pub period: Option<f64>,
+    // Delay between a measurement and the command computed from it [s]
+    #[serde(default)]
+    pub delay: f64,
+    #[serde(default = "default_error")]
+    pub error: ErrorRepresentation,
+    // Hysteresis on the sign of the error quaternion (0 always takes the shortest rotation)
+    #[serde(default)]
+    pub hysteresis: f64,
+    // Adds the gyroscopic and reference acceleration feedforward to the command
+    #[serde(default)]
+    pub feedforward: bool,
+}
+
+fn default_error() -> ErrorRepresentation {
+    ErrorRepresentation::ShortestVectorPart
+}
+
+/// Control law hosted by the Controller, selected by `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum LawConfig {
+    PD {
+        kp: f64,
+        kd: f64,
+    },
+    #[serde(rename = "PID")]
+    Pid {
+        kp: f64,
+        ki: f64,
+        kd: f64,
+        anti_windup: AntiWindup,
+    },
+    /// PD gains from the LQR design for the spacecraft, the initial wheel speeds and
+    /// `controller.error`, solved in discrete time for the controller period when `discrete` is set
+    #[serde(rename = "LQR")]
+    Lqr {
+        attitude: [f64; 3],
+        rate: [f64; 3],
+        torque: [f64; 3],
+        #[serde(default)]
+        discrete: bool,
+    },
+    SlidingMode {
+        lambda: f64,
+        k: [f64; 3],
+        boundary_layer: BoundaryLayer,
+    },
+    #[serde(rename = "MPC")]
+    Mpc {
+        horizon: usize,
+        dt: f64,
+        attitude: [f64; 3],
+        rate: [f64; 3],
+        torque: [f64; 3],
+        max_iterations: usize,
+    },
+    /// Adaptive law starting from `initial_inertia` (the spacecraft inertia when omitted)
+    Adaptive {
+        lambda: f64,
+        kd: [f64; 3],
+        gamma: f64,
+        #[serde(default)]
+        initial_inertia: Option<[[f64; 3]; 3]>,
+    },
+    Detumble {
+        kd: f64,
+    },
+    /// Continuous compensator discretized for the controller period
+    Compensator {
+        error: TransferFunctionConfig,
+        rate: TransferFunctionConfig,
+        discretization: Discretization,
+    },
+}
+
+/// Coefficients of num(s)/den(s) in descending powers of s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransferFunctionConfig {
+    pub num: Vec<f64>,
+    pub den: Vec<f64>,
+}
+
+/// Guidance law providing the target attitude, selected by `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum GuidanceConfig {
+    InertialHold {
+        #[serde(default)]
+        attitude: Attitude,
+    },
+    Nadir {
+        orbit: OrbitConfig,
+    },
+    SunPointing {
+        sun_direction: [f64; 3],
+        body_axis: [f64; 3],
+    },
+    GroundTarget {
+        orbit: OrbitConfig,
+        latitude: f64,
+        longitude: f64,
+    },
+    Scripted {
+        steps: Vec<ScriptedStep>,
+    },
+    /// Rest-to-rest slew starting at `start_time` from `from` (the initial attitude when omitted)
+    /// to `to`, routed around the keep-out cones if there are any. The wheel limits come from
+    /// the `wheels` section.
+    Slew {
+        #[serde(default)]
+        from: Option<Attitude>,
+        to: Attitude,
+        start_time: f64,
+        // Body rate limit [rad/s]
+        max_rate: f64,
+        // Fraction of the wheel torque and momentum the profile may use
+        usage: f64,
+        #[serde(default)]
+        keep_out: Vec<KeepOutConfig>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrbitConfig {
+    // Altitude over the equatorial radius [m]
+    pub altitude: f64,
+    pub inclination: f64,
+    #[serde(default)]
+    pub raan: f64,
+    // Argument of latitude at t = 0
+    #[serde(default)]
+    pub arg_latitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedStep {
+    pub time: f64,
+    pub attitude: Attitude,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeepOutConfig {
+    pub boresight: [f64; 3],
+    pub direction: [f64; 3],
+    pub half_angle: f64,
+}
+
+/// Attitude given as a rotation or as the quaternion coefficients [w, x, y, z].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(
+    untagged,
+    deny_unknown_fields,
+    expecting = "an attitude { axis = [x, y, z], angle = a } or a quaternion [w, x, y, z]"
+)]
+pub enum Attitude {
+    AxisAngle { axis: [f64; 3], angle: f64 },
+    Quaternion([f64; 4]),
+}
+
+impl Default for Attitude {
+    fn default() -> Self {
+        Attitude::Quaternion([1.0, 0.0, 0.0, 0.0])
+    }
+}
+
+impl Attitude {
+    pub fn quaternion(&self) -> Quaternion {
+        match *self {
+            Attitude::AxisAngle { axis, angle } => {
+                let axis = Vector3::from(axis).normalize();
+                let w = (angle / 2.0).cos();
+                let v = axis * (angle / 2.0).sin();
+                Quaternion(nalgebra::Quaternion::new(w, v.x, v.y, v.z))
+            }
+            Attitude::Quaternion([w, x, y, z]) => {
+                Quaternion(nalgebra::Quaternion::new(w, x, y, z).normalize())
+            }
+        }
+    }
+
+    fn validate(&self, field: &str) -> Result<(), ScenarioError> {
+        match *self {
+            Attitude::AxisAngle { axis, angle } => {
+                nonzero(&format!("{}.axis", field), axis)?;
+                finite(&format!("{}.angle", field), angle)
+            }
+            Attitude::Quaternion(q) => ensure(
+                q.iter().all(|c| c.is_finite()) && q.iter().any(|c| *c != 0.0),
+                field,
+                "must be a non-zero quaternion",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InitialState {
+    // Angular velocity of the satellite [rad/s]
+    pub w: [f64; 3],
+    pub attitude: Attitude,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Modes {
+    pub initial: Mode,
+    // Transition thresholds (None uses `ModeCriteria::new` for the wheels)
+    #[serde(default)]
+    pub criteria: Option<ModeCriteria>,
+    #[serde(default)]
+    pub laws: Vec<ModeLaws>,
+}
+
+/// Laws a mode switches to; the `controller` and `guidance` laws are used otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModeLaws {
+    pub mode: Mode,
+    #[serde(default)]
+    pub control: Option<LawConfig>,
+    #[serde(default)]
+    pub guidance: Option<GuidanceConfig>,
+}
+
+/// Fault injected during the run, selected by `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum FaultConfig {
+    StuckWheel {
+        wheel: usize,
+        time: f64,
+    },
+    // The wheel only delivers `fraction` of the commanded torque
+    TorqueLoss {
+        wheel: usize,
+        time: f64,
+        fraction: f64,
+    },
+    SensorDropout {
+        start: f64,
+        end: f64,
+    },
+    AttitudeGlitch {
+        time: f64,
+    },
+}
+
+/// Recorder coupled to an output port of the model (see `OutputPorts`), saved with the results
+/// and plotted in a panel of its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeConfig {
+    // Name of the recording, unique among the probes and the components of the model
+    pub name: String,
+    // Component and output port recorded, e.g. `ReationWheels` and `o_h_rw`
+    pub component: String,
+    pub port: String,
+    // Keeps one sample out of `decimation`
+    #[serde(default = "default_decimation")]
+    pub decimation: usize,
+    // Time window of the recording [s], the whole run by default
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default)]
+    pub end: Option<f64>,
+    #[serde(default)]
+    pub trigger: Option<TriggerConfig>,
+}
+
+fn default_decimation() -> usize {
+    1
+}
+
+/// Starts a recording the first time a component of the value, or the norm of all of them, goes
+/// above or below a level. Exactly one of `above` and `below` is given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerConfig {
+    // Name of the component, e.g. `x`, the norm when omitted
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Output {
+    // Directory the plots are written to
+    #[serde(default = "default_directory")]
+    pub directory: String,
+    // Margin around the recorded ranges in the plots
+    #[serde(default = "default_margin_ratio")]
+    pub margin_ratio: f64,
+    #[serde(default = "default_plot")]
+    pub plot: bool,
+}
+
+fn default_directory() -> String {
+    "images".to_string()
+}
+
+fn default_margin_ratio() -> f64 {
+    0.1
+}
+
+fn default_plot() -> bool {
+    true
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            directory: default_directory(),
+            margin_ratio: default_margin_ratio(),
+            plot: default_plot(),
+        }
+    }
+}
+
+/// Scenario that cannot be read or built, with the path of the offending field (e.g.
+/// `controller.law.kp`).
+#[derive(Debug)]
+pub struct ScenarioError {
+    pub field: String,
+    pub reason: String,
+}
+
+impl ScenarioError {
+    pub(crate) fn new(field: &str, reason: impl Into<String>) -> Self {
+        ScenarioError {
+            field: field.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "{}: {}", self.field, self.reason)
+        }
+    }
+}
+
+/// Replacement of one scenario value, written `path=value` with the field paths of the error
+/// messages, e.g. `controller.law.kp=0.02` or `wheels.inertia[0]=6e-5`. The value is read as
+/// JSON, then in the text form of the model types (e.g. `spacecraft.inertia=(a,0,0,0,b,0,0,0,c)`
+/// for a `Mat3`, row by row, or `modes.initial=Safe` for a `Mode`) and taken as a string
+/// otherwise (e.g. `controller.law.type=PID`).
+#[derive(Debug, Clone)]
+pub struct Override {
+    pub path: String,
+    pub value: Value,
+}
+
+impl Override {
+    pub fn new(path: &str, value: &str) -> Self {
+        Override {
+            path: path.to_string(),
+            value: parse_value(value),
+        }
+    }
+
+    fn apply(&self, root: &mut Value) -> Result<(), ScenarioError> {
+        *field_mut(root, &self.path)? = self.value.clone();
+        Ok(())
+    }
+}
+
+// Value of an override, see `Override`
+fn parse_value(text: &str) -> Value {
+    fn typed<T: FromStr + Serialize>(text: &str) -> Option<Value> {
+        serde_json::to_value(T::from_str(text).ok()?).ok()
+    }
+    serde_json::from_str(text)
+        .ok()
+        .or_else(|| typed::<Vec3>(text))
+        .or_else(|| typed::<Quaternion>(text))
+        .or_else(|| typed::<Mat3>(text))
+        .or_else(|| typed::<Mode>(text))
+        .unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+/// Value at a field path of a scenario, see `Override`. Tables missing along the path are
+/// created, array elements must exist.
+pub(crate) fn field_mut<'a>(
+    root: &'a mut Value,
+    path: &str,
+) -> Result<&'a mut Value, ScenarioError> {
+    let error = |reason: &str| ScenarioError::new(path, reason);
+    let mut target = root;
+    for key in path.split('.') {
+        let (name, indices) = match key.split_once('[') {
+            Some((name, indices)) => (name, Some(indices)),
+            None => (key, None),
+        };
+        if name.is_empty() {
+            return Err(error("is not a valid field path"));
+        }
+        target = match target {
+            Value::Object(table) => table
+                .entry(name)
+                .or_insert_with(|| Value::Object(Map::new())),
+            _ => return Err(error(&format!("`{}` is not inside a table", name))),
+        };
+        for index in indices.into_iter().flat_map(|indices| indices.split('[')) {
+            let index = index
+                .strip_suffix(']')
+                .and_then(|index| index.parse::<usize>().ok())
+                .ok_or_else(|| error("is not a valid field path"))?;
+            target = target
+                .as_array_mut()
+                .and_then(|array| array.get_mut(index))
+                .ok_or_else(|| error(&format!("`{}` has no element {}", name, index)))?;
+        }
+    }
+    Ok(target)
+}
+
+// Scenario of a JSON tree, not validated
+fn from_tree(document: &Value) -> Result<Scenario, ScenarioError> {
+    serde_path_to_error::deserialize(document)
+        .map_err(|e| ScenarioError::new(&e.path().to_string(), e.inner().to_string()))
+}
+
+// serde reports an error inside an internally tagged table (`type = "..."`) at the table, e.g.
+// `controller.law` for a wrong `kp`. The field is found by removing each one in turn: fields are
+// read in order and missing ones only reported after all of them, so removing the faulty field
+// is the only removal that changes the error.
+fn locate(document: &Value, error: ScenarioError) -> ScenarioError {
+    let reported = match from_tree(document) {
+        Err(reported) if reported.field == error.field => reported,
+        _ => return error,
+    };
+    let same_error = |probe: &Value| match from_tree(probe) {
+        Err(e) => e.field == reported.field && e.reason == reported.reason,
+        Ok(_) => false,
+    };
+    let mut field = reported.field.clone();
+    loop {
+        let mut tree = document.clone();
+        let keys: Vec<String> = match field_mut(&mut tree, &field) {
+            // Without its `type` any table fails, whatever the faulty field
+            Ok(Value::Object(table)) => {
+                table.keys().filter(|key| *key != "type").cloned().collect()
+            }
+            _ => break,
+        };
+        let faulty = keys.into_iter().find(|key| {
+            let mut probe = document.clone();
+            if let Ok(Value::Object(table)) = field_mut(&mut probe, &field) {
+                table.remove(key);
+            }
+            !same_error(&probe)
+        });
+        match faulty {
+            Some(key) => field = format!("{}.{}", field, key),
+            None => break,
+        }
+    }
+    ScenarioError::new(&field, error.reason)
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PATH=VALUE, found `{}`", s))?;
+        Ok(Override::new(path.trim(), value.trim()))
+    }
+}
+
+impl Scenario {
+    /// Reads a scenario, as TOML or JSON depending on the file extension, replaces the
+    /// `overrides` values and validates the result
+    pub fn load(path: &Path, overrides: &[Override]) -> Result<Self, ScenarioError> {
+        let text = fs::read_to_string(path).map_err(|e| ScenarioError::new("", e.to_string()))?;
+        let mut value: Value = match path.extension().and_then(|extension| extension.to_str()) {
+            // Without overrides the file is read directly, for the line numbers in the errors
+            Some("toml") if overrides.is_empty() => return Scenario::from_toml(&text),
+            Some("json") if overrides.is_empty() => return Scenario::from_json(&text),
+            Some("toml") => {
+                toml::from_str(&text).map_err(|e| ScenarioError::new("", e.to_string()))?
+            }
+            Some("json") => {
+                serde_json::from_str(&text).map_err(|e| ScenarioError::new("", e.to_string()))?
+            }
+            _ => {
+                return Err(ScenarioError::new(
+                    "",
+                    "unknown scenario format, expected a .toml or .json file",
+                ));
+            }
+        };
+        for replacement in overrides {
+            replacement.apply(&mut value)?;
+        }
+        Scenario::from_value(value)
+    }
+
+    /// Reads and validates a scenario from its JSON tree
+    pub fn from_value(value: Value) -> Result<Self, ScenarioError> {
+        let scenario = from_tree(&value).map_err(|e| locate(&value, e))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ScenarioError> {
+        let deserializer =
+            toml::Deserializer::parse(text).map_err(|e| ScenarioError::new("", e.to_string()))?;
+        let scenario: Scenario = serde_path_to_error::deserialize(deserializer)
+            .map_err(|e| ScenarioError::new(&e.path().to_string(), e.inner().message()))
+            .map_err(|e| match toml::from_str(text) {
+                Ok(document) => locate(&document, e),
+                Err(_) => e,
+            })?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ScenarioError> {
+        let mut deserializer = serde_json::Deserializer::from_str(text);
+        let scenario: Scenario = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|e| ScenarioError::new(&e.path().to_string(), e.inner().to_string()))
+            .map_err(|e| match serde_json::from_str(text) {
+                Ok(document) => locate(&document, e),
+                Err(_) => e,
+            })?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Writes the scenario, as TOML or JSON depending on the file extension
+    pub fn save(&self, path: &Path) -> Result<(), ScenarioError> {
+        let text = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => {
+                toml::to_string(self).map_err(|e| ScenarioError::new("", e.to_string()))?
+            }
+            Some("json") => serde_json::to_string_pretty(self)
+                .map_err(|e| ScenarioError::new("", e.to_string()))?,
+            _ => {
+                return Err(ScenarioError::new(
+                    "",
+                    "unknown scenario format, expected a .toml or .json file",
+                ));
+            }
+        };
+        fs::write(path, text).map_err(|e| ScenarioError::new("", e.to_string()))
+    }
+
+    /// Checks every value is in range and that the laws can be built for this spacecraft
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        let h = self.simulation.h;
+        positive("simulation.h", h)?;
+        positive("simulation.total_time", self.simulation.total_time)?;
+
+        inertia_tensor("spacecraft.inertia", self.spacecraft.inertia)?;
+
+        let wheels = &self.wheels;
+        for (i, inertia) in wheels.inertia.iter().enumerate() {
+            positive(&format!("wheels.inertia[{}]", i), *inertia)?;
+        }
+        positive("wheels.max_torque", wheels.max_torque)?;
+        positive("wheels.max_speed", wheels.max_speed)?;
+        for (i, speed) in wheels.initial_speeds.iter().enumerate() {
+            ensure(
+                speed.abs() <= wheels.max_speed,
+                &format!("wheels.initial_speeds[{}]", i),
+                "must not exceed wheels.max_speed",
+            )?;
+        }
+        non_negative("sensors.rate_noise", self.sensors.rate_noise)?;
+        non_negative("sensors.attitude_noise", self.sensors.attitude_noise)?;
+
+        let controller = &self.controller;
+        self.checked_rates()?;
+        ensure(
+            (0.0..1.0).contains(&controller.hysteresis),
+            "controller.hysteresis",
+            "must be in [0, 1)",
+        )?;
+        self.control_law(&controller.law, "controller.law")?;
+        self.guidance_law(&self.guidance, "guidance")?;
+
+        for (i, w) in self.initial_state.w.iter().enumerate() {
+            finite(&format!("initial_state.w[{}]", i), *w)?;
+        }
+        self.initial_state
+            .attitude
+            .validate("initial_state.attitude")?;
+
+        if let Some(modes) = &self.modes {
+            if let Some(criteria) = &modes.criteria {
+                mode_criteria("modes.criteria", criteria)?;
+            }
+            for (i, laws) in modes.laws.iter().enumerate() {
+                if let Some(law) = &laws.control {
+                    self.control_law(law, &format!("modes.laws[{}].control", i))?;
+                }
+                if let Some(guidance) = &laws.guidance {
+                    self.guidance_law(guidance, &format!("modes.laws[{}].guidance", i))?;
+                }
+            }
+        }
+
+        for (i, fault) in self.faults.iter().enumerate() {
+            let field = format!("faults[{}]", i);
+            match *fault {
+                FaultConfig::StuckWheel { wheel, time } => {
+                    wheel_index(&format!("{}.wheel", field), wheel)?;
+                    non_negative(&format!("{}.time", field), time)?;
+                }
+                FaultConfig::TorqueLoss {
+                    wheel,
+                    time,
+                    fraction,
+                } => {
+                    wheel_index(&format!("{}.wheel", field), wheel)?;
+                    non_negative(&format!("{}.time", field), time)?;
+                    ensure(
+                        (0.0..=1.0).contains(&fraction),
+                        &format!("{}.fraction", field),
+                        "must be in [0, 1]",
+                    )?;
+                }
+                FaultConfig::SensorDropout { start, end } => {
+                    non_negative(&format!("{}.start", field), start)?;
+                    ensure(
+                        end > start,
+                        &format!("{}.end", field),
+                        "must be after start",
+                    )?;
+                }
+                FaultConfig::AttitudeGlitch { time } => {
+                    non_negative(&format!("{}.time", field), time)?;
+                }
+            }
+        }
+
+        for (i, probe) in self.probes.iter().enumerate() {
+            let field = format!("probes[{}]", i);
+            self.probe(probe, &field)?;
+            ensure(
+                self.probes[..i]
+                    .iter()
+                    .all(|other| other.name != probe.name),
+                &format!("{}.name", field),
+                "is already the name of another probe",
+            )?;
+        }
+        if !self.probes.is_empty() {
+            // Components and ports are only known once the model is built
+            self.build("DiscreteTimeModel")?;
+        }
+
+        self.metrics.validate("metrics")?;
+        for (i, requirement) in self.requirements.iter().enumerate() {
+            requirement.validate(&format!("requirements[{}]", i), self.simulation.total_time)?;
+        }
+        if let Some(monte_carlo) = &self.monte_carlo {
+            monte_carlo.validate("monte_carlo")?;
+        }
+        if let Some(tuning) = &self.tuning {
+            tuning.validate("tuning")?;
+        }
+
+        ensure(
+            !self.output.directory.is_empty(),
+            "output.directory",
+            "must not be empty",
+        )?;
+        non_negative("output.margin_ratio", self.output.margin_ratio)
+    }
+
+    /// Builds the coupled model of the scenario
+    pub fn build(&self, name: &str) -> Result<DiscreteTimeModel, ScenarioError> {
+        let control_law = self.control_law(&self.controller.law, "controller.law")?;
+        let guidance_law = self.guidance_law(&self.guidance, "guidance")?;
+        let options = self.model_options()?;
+        let mut model =
+            DiscreteTimeModel::new(name, self.simulation.h, control_law, guidance_law, options);
+        for (i, probe) in self.probes.iter().enumerate() {
+            let field = format!("probes[{}]", i);
+            let at = |name: &str| format!("{}.{}", field, name);
+            model
+                .add_probe(&self.probe(probe, &field)?)
+                .map_err(|error| match error {
+                    ProbeError::NameTaken => ScenarioError::new(
+                        &at("name"),
+                        "is already the name of a component of the model",
+                    ),
+                    ProbeError::UnknownComponent(components) => ScenarioError::new(
+                        &at("component"),
+                        format!(
+                            "is not a component of the model with output ports, must be one of {}",
+                            components.join(", ")
+                        ),
+                    ),
+                    ProbeError::UnknownPort(ports) => ScenarioError::new(
+                        &at("port"),
+                        format!(
+                            "is not an output port of {}, must be one of {}",
+                            probe.component,
+                            ports.join(", ")
+                        ),
+                    ),
+                    ProbeError::UnknownTriggerComponent(components) => ScenarioError::new(
+                        &at("trigger.component"),
+                        format!("must be one of {}", components.join(", ")),
+                    ),
+                })?;
+        }
+        Ok(model)
+    }
+
+    /// Plant, loop rates and optional components of the model
+    pub fn model_options(&self) -> Result<ModelOptions, ScenarioError> {
+        let modes = match &self.modes {
+            Some(modes) => {
+                let mut setup = ModeSetup {
+                    initial_mode: modes.initial,
+                    criteria: modes.criteria,
+                    control_laws: Vec::new(),
+                    guidance_laws: Vec::new(),
+                };
+                for (i, laws) in modes.laws.iter().enumerate() {
+                    if let Some(law) = &laws.control {
+                        let field = format!("modes.laws[{}].control", i);
+                        setup
+                            .control_laws
+                            .push((laws.mode, self.control_law(law, &field)?));
+                    }
+                    if let Some(guidance) = &laws.guidance {
+                        let field = format!("modes.laws[{}].guidance", i);
+                        setup
+                            .guidance_laws
+                            .push((laws.mode, self.guidance_law(guidance, &field)?));
+                    }
+                }
+                Some(setup)
+            }
+            None => None,
+        };
+        Ok(ModelOptions {
+            plant: self.plant(),
+            attitude_error: AttitudeError::new(self.controller.error, self.controller.hysteresis),
+            feedforward: self.controller.feedforward,
+            rates: Some(self.checked_rates()?),
+            sensor_noise: self.sensor_noise(),
+            modes,
+            faults: self.injections(),
+            fdir: self.fdir,
+        })
+    }
+
+    // Recorder of `probe`, at `field` in the scenario
+    fn probe(&self, probe: &ProbeConfig, field: &str) -> Result<ProbeSetup, ScenarioError> {
+        let at = |name: &str| format!("{}.{}", field, name);
+        ensure(
+            !probe.name.trim().is_empty(),
+            &at("name"),
+            "must not be empty",
+        )?;
+        ensure(
+            probe.decimation > 0,
+            &at("decimation"),
+            "must be at least 1",
+        )?;
+        non_negative(&at("start"), probe.start)?;
+        if let Some(end) = probe.end {
+            ensure(end > probe.start, &at("end"), "must be after start")?;
+        }
+        let trigger = match &probe.trigger {
+            Some(trigger) => {
+                let (level, rising) = match (trigger.above, trigger.below) {
+                    (Some(level), None) => (level, true),
+                    (None, Some(level)) => (level, false),
+                    _ => {
+                        return Err(ScenarioError::new(
+                            &at("trigger"),
+                            "needs exactly one of above and below",
+                        ));
+                    }
+                };
+                let bound = match rising {
+                    true => "trigger.above",
+                    false => "trigger.below",
+                };
+                finite(&at(bound), level)?;
+                Some(Trigger {
+                    component: trigger.component.clone(),
+                    level,
+                    rising,
+                })
+            }
+            None => None,
+        };
+        Ok(ProbeSetup {
+            name: probe.name.clone(),
+            component: probe.component.clone(),
+            port: probe.port.clone(),
+            config: RecorderConfig {
+                decimation: probe.decimation,
+                start: probe.start,
+                end: probe.end.unwrap_or(f64::INFINITY),
+                trigger,
+            },
+        })
+    }
+
+    pub fn plant(&self) -> Plant {
+        let wheels = &self.wheels;
+        Plant {
+            i_sat: matrix(self.spacecraft.inertia),
+            i_rw: Matrix3::from_diagonal(&Vector3::from(wheels.inertia)),
+            max_torque_rw: wheels.max_torque,
+            max_speed_rw: wheels.max_speed,
+            rw_speeds_initial: Vec3(Vector3::from(wheels.initial_speeds)),
+            w0: Vec3(Vector3::from(self.initial_state.w)),
+            q0: self.initial_state.attitude.quaternion(),
+        }
+    }
+
+    pub fn rates(&self) -> Rates {
+        let h = self.simulation.h;
+        Rates {
+            sensor_period: self.sensors.period.unwrap_or(h),
+            control_period: self.controller.period.unwrap_or(h),
+            actuator_period: self.wheels.actuator_period.unwrap_or(h),
+            control_delay: self.controller.delay,
+        }
+    }
+
+    // Periods that are multiples of the plant step, a controller sampling whole sensor periods
+    // and a delay shorter than the control period
+    fn checked_rates(&self) -> Result<Rates, ScenarioError> {
+        let h = self.simulation.h;
+        period("wheels.actuator_period", self.wheels.actuator_period, h)?;
+        period("sensors.period", self.sensors.period, h)?;
+        period("controller.period", self.controller.period, h)?;
+        let rates = self.rates();
+        ensure(
+            multiple(rates.control_period, rates.sensor_period),
+            "controller.period",
+            "must be a multiple of sensors.period (simulation.h by default)",
+        )?;
+        non_negative("controller.delay", rates.control_delay)?;
+        ensure(
+            rates.control_delay < rates.control_period,
+            "controller.delay",
+            "must be shorter than controller.period (simulation.h by default)",
+        )?;
+        Ok(rates)
+    }
+
+    pub fn sensor_noise(&self) -> Option<SensorNoise> {
+        let sensors = &self.sensors;
+        if sensors.rate_noise == 0.0 && sensors.attitude_noise == 0.0 {
+            return None;
+        }
+        Some(SensorNoise {
+            rate_sigma: sensors.rate_noise,
+            attitude_sigma: sensors.attitude_noise,
+            seed: sensors.seed,
+        })
+    }
+
+    /// Faults to inject, also used to score the FDIR
+    pub fn injections(&self) -> Vec<Injection> {
+        self.faults
+            .iter()
+            .map(|fault| match *fault {
+                FaultConfig::StuckWheel { wheel, time } => Injection::Wheel(WheelFault {
+                    wheel,
+                    time,
+                    kind: WheelFaultKind::Stuck,
+                }),
+                FaultConfig::TorqueLoss {
+                    wheel,
+                    time,
+                    fraction,
+                } => Injection::Wheel(WheelFault {
+                    wheel,
+                    time,
+                    kind: WheelFaultKind::TorqueLoss(fraction),
+                }),
+                FaultConfig::SensorDropout { start, end } => {
+                    Injection::SensorDropout { start, end }
+                }
+                FaultConfig::AttitudeGlitch { time } => Injection::AttitudeGlitch(time),
+            })
+            .collect()
+    }
+
+    /// Checks the parameters of `law` and builds it for the spacecraft and controller period
+    pub fn control_law(
+        &self,
+        law: &LawConfig,
+        field: &str,
+    ) -> Result<Box<dyn ControlLaw>, ScenarioError> {
+        let at = |name: &str| format!("{}.{}", field, name);
+        let plant = self.plant();
+        let period = self.rates().control_period;
+        let law: Box<dyn ControlLaw> = match law {
+            LawConfig::PD { kp, kd } => {
+                positive(&at("kp"), *kp)?;
+                positive(&at("kd"), *kd)?;
+                Box::new(PD::new(*kp, *kd))
+            }
+            LawConfig::Pid {
+                kp,
+                ki,
+                kd,
+                anti_windup,
+            } => {
+                positive(&at("kp"), *kp)?;
+                non_negative(&at("ki"), *ki)?;
+                positive(&at("kd"), *kd)?;
+                if let AntiWindup::BackCalculation(kb) = anti_windup {
+                    positive(&at("anti_windup.BackCalculation"), *kb)?;
+                }
+                Box::new(Pid::new(*kp, *ki, *kd, *anti_windup))
+            }
+            LawConfig::Lqr {
+                attitude,
+                rate,
+                torque,
+                discrete,
+            } => {
+                let weights = lqr_weights(field, attitude, rate, torque)?;
+                let gains = LinearizedModel::new(
+                    plant.i_sat,
+                    plant.i_rw,
+                    plant.rw_speeds_initial,
+                    self.controller.error,
+                )
+                .and_then(|model| {
+                    if *discrete {
+                        LqrGains::discrete(&model, &weights, period)
+                    } else {
+                        LqrGains::continuous(&model, &weights)
+                    }
+                })
+                .ok_or_else(|| ScenarioError::new(field, "the Riccati equation has no solution"))?;
+                Box::new(gains.to_pd())
+            }
+            LawConfig::SlidingMode {
+                lambda,
+                k,
+                boundary_layer,
+            } => {
+                positive(&at("lambda"), *lambda)?;
+                positive_vector(&at("k"), k)?;
+                if let BoundaryLayer::Saturation(width) | BoundaryLayer::Tanh(width) =
+                    boundary_layer
+                {
+                    positive(&at("boundary_layer"), *width)?;
+                }
+                Box::new(SlidingMode::new(
+                    *lambda,
+                    Vector3::from(*k),
+                    plant.i_sat,
+                    *boundary_layer,
+                ))
+            }
+            LawConfig::Mpc {
+                horizon,
+                dt,
+                attitude,
+                rate,
+                torque,
+                max_iterations,
+            } => {
+                ensure(*horizon > 0, &at("horizon"), "must be positive")?;
+                positive(&at("dt"), *dt)?;
+                ensure(
+                    *max_iterations > 0,
+                    &at("max_iterations"),
+                    "must be positive",
+                )?;
+                let config = MpcConfig {
+                    horizon: *horizon,
+                    dt: *dt,
+                    weights: lqr_weights(field, attitude, rate, torque)?,
+                    max_torque_rw: plant.max_torque_rw,
+                    max_speed_rw: plant.max_speed_rw,
+                    max_iterations: *max_iterations,
+                    error: self.controller.error,
+                };
+                let mpc = Mpc::new(config, plant.i_sat, plant.i_rw, plant.rw_speeds_initial)
+                    .ok_or_else(|| {
+                        ScenarioError::new(field, "the Riccati equation has no solution")
+                    })?;
+                Box::new(mpc)
+            }
+            LawConfig::Adaptive {
+                lambda,
+                kd,
+                gamma,
+                initial_inertia,
+            } => {
+                positive(&at("lambda"), *lambda)?;
+                positive_vector(&at("kd"), kd)?;
+                positive(&at("gamma"), *gamma)?;
+                let i_sat_initial = match initial_inertia {
+                    Some(inertia) => {
+                        inertia_tensor(&at("initial_inertia"), *inertia)?;
+                        matrix(*inertia)
+                    }
+                    None => plant.i_sat,
+                };
+                Box::new(Adaptive::new(
+                    *lambda,
+                    Vector3::from(*kd),
+                    *gamma,
+                    i_sat_initial,
+                ))
+            }
+            LawConfig::Detumble { kd } => {
+                positive(&at("kd"), *kd)?;
+                Box::new(Detumble::new(*kd))
+            }
+            LawConfig::Compensator {
+                error,
+                rate,
+                discretization,
+            } => {
+                let c_error = transfer_function(&at("error"), error)?;
+                let c_rate = transfer_function(&at("rate"), rate)?;
+                Box::new(Compensator::from_continuous(
+                    &c_error,
+                    &c_rate,
+                    *discretization,
+                    period,
+                ))
+            }
+        };
+        Ok(law)
+    }
+
+    /// Continuous design c_error(s), c_rate(s) of the Compensator law of the controller
+    pub fn compensator_design(
+        &self,
+    ) -> Result<(TransferFunction, TransferFunction), ScenarioError> {
+        match &self.controller.law {
+            LawConfig::Compensator { error, rate, .. } => Ok((
+                transfer_function("controller.law.error", error)?,
+                transfer_function("controller.law.rate", rate)?,
+            )),
+            _ => Err(ScenarioError::new(
+                "controller.law",
+                "must be a Compensator to compare its discretizations",
+            )),
+        }
+    }
+
+    /// Checks the parameters of `guidance` and builds it for the spacecraft and wheels
+    pub fn guidance_law(
+        &self,
+        guidance: &GuidanceConfig,
+        field: &str,
+    ) -> Result<Box<dyn GuidanceLaw>, ScenarioError> {
+        let at = |name: &str| format!("{}.{}", field, name);
+        let law: Box<dyn GuidanceLaw> = match guidance {
+            GuidanceConfig::InertialHold { attitude } => {
+                attitude.validate(&at("attitude"))?;
+                Box::new(InertialHold::new(attitude.quaternion()))
+            }
+            GuidanceConfig::Nadir { orbit } => {
+                Box::new(Nadir::new(circular_orbit(&at("orbit"), orbit)?))
+            }
+            GuidanceConfig::SunPointing {
+                sun_direction,
+                body_axis,
+            } => {
+                nonzero(&at("sun_direction"), *sun_direction)?;
+                nonzero(&at("body_axis"), *body_axis)?;
+                Box::new(SunPointing::new(
+                    Vector3::from(*sun_direction).normalize(),
+                    Vector3::from(*body_axis).normalize(),
+                ))
+            }
+            GuidanceConfig::GroundTarget {
+                orbit,
+                latitude,
+                longitude,
+            } => {
+                ensure(
+                    latitude.abs() <= PI / 2.0,
+                    &at("latitude"),
+                    "must be in [-pi/2, pi/2]",
+                )?;
+                finite(&at("longitude"), *longitude)?;
+                Box::new(GroundTarget::new(
+                    circular_orbit(&at("orbit"), orbit)?,
+                    *latitude,
+                    *longitude,
+                ))
+            }
+            GuidanceConfig::Scripted { steps } => {
+                ensure(!steps.is_empty(), &at("steps"), "must not be empty")?;
+                let mut script = Vec::with_capacity(steps.len());
+                for (i, step) in steps.iter().enumerate() {
+                    let step_field = format!("{}.steps[{}]", field, i);
+                    finite(&format!("{}.time", step_field), step.time)?;
+                    step.attitude
+                        .validate(&format!("{}.attitude", step_field))?;
+                    script.push((step.time, step.attitude.quaternion()));
+                }
+                Box::new(Scripted::new(script))
+            }
+            GuidanceConfig::Slew {
+                from,
+                to,
+                start_time,
+                max_rate,
+                usage,
+                keep_out,
+            } => {
+                let from = match from {
+                    Some(from) => {
+                        from.validate(&at("from"))?;
+                        *from
+                    }
+                    None => self.initial_state.attitude,
+                };
+                to.validate(&at("to"))?;
+                non_negative(&at("start_time"), *start_time)?;
+                positive(&at("max_rate"), *max_rate)?;
+                ensure(
+                    *usage > 0.0 && *usage <= 1.0,
+                    &at("usage"),
+                    "must be in (0, 1]",
+                )?;
+                let plant = self.plant();
+                let limits = SlewLimits {
+                    max_rate: *max_rate,
+                    max_torque: plant.max_torque_rw,
+                    // The smallest wheel sets the momentum every axis can store
+                    max_momentum: plant.i_rw.diagonal().min() * plant.max_speed_rw,
+                    usage: *usage,
+                    i_sat: plant.i_sat,
+                };
+                if keep_out.is_empty() {
+                    Box::new(EigenAxisSlew::new(
+                        from.quaternion(),
+                        to.quaternion(),
+                        *start_time,
+                        &limits,
+                    ))
+                } else {
+                    let mut cones = Vec::with_capacity(keep_out.len());
+                    for (i, cone) in keep_out.iter().enumerate() {
+                        let cone_field = format!("{}.keep_out[{}]", field, i);
+                        nonzero(&format!("{}.boresight", cone_field), cone.boresight)?;
+                        nonzero(&format!("{}.direction", cone_field), cone.direction)?;
+                        ensure(
+                            cone.half_angle > 0.0 && cone.half_angle < PI,
+                            &format!("{}.half_angle", cone_field),
+                            "must be in (0, pi)",
+                        )?;
+                        cones.push(KeepOutCone {
+                            boresight: Vector3::from(cone.boresight).normalize(),
+                            direction: Vector3::from(cone.direction).normalize(),
+                            half_angle: cone.half_angle,
+                        });
+                    }
+                    let waypoints = plan_slew(
+                        from.quaternion(),
+                        to.quaternion(),
+                        &cones,
+                        &PlannerConfig::default(),
+                    )
+                    .ok_or_else(|| {
+                        ScenarioError::new(&at("keep_out"), "no slew avoids the keep-out cones")
+                    })?;
+                    Box::new(WaypointSlew::new(&waypoints, *start_time, &limits))
+                }
+            }
+        };
+        Ok(law)
+    }
+}
+
+fn matrix(rows: [[f64; 3]; 3]) -> Matrix3<f64> {
+    Matrix3::from_row_slice(rows.as_flattened())
+}
+
+pub(crate) fn ensure(condition: bool, field: &str, reason: &str) -> Result<(), ScenarioError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(ScenarioError::new(field, reason))
+    }
+}
+
+pub(crate) fn finite(field: &str, value: f64) -> Result<(), ScenarioError> {
+    ensure(value.is_finite(), field, "must be a finite number")
+}
+
+pub(crate) fn positive(field: &str, value: f64) -> Result<(), ScenarioError> {
+    ensure(value.is_finite() && value > 0.0, field, "must be positive")
+}
+
+pub(crate) fn non_negative(field: &str, value: f64) -> Result<(), ScenarioError> {
+    ensure(
+        value.is_finite() && value >= 0.0,
+        field,
+        "must not be negative",
+    )
+}
+
+fn positive_vector(field: &str, values: &[f64; 3]) -> Result<(), ScenarioError> {
+    for (i, value) in values.iter().enumerate() {
+        positive(&format!("{}[{}]", field, i), *value)?;
+    }
+    Ok(())
+}
+
+fn nonzero(field: &str, vector: [f64; 3]) -> Result<(), ScenarioError> {
+    ensure(
+        vector.iter().all(|c| c.is_finite()) && vector.iter().any(|c| *c != 0.0),
+        field,
+        "must be a non-zero vector",
+    )
+}
+
+// On-board periods run on whole plant steps
+fn period(field: &str, value: Option<f64>, h: f64) -> Result<(), ScenarioError> {
+    match value {
+        Some(value) => {
+            ensure(
+                value.is_finite() && value >= h,
+                field,
+                "must not be shorter than simulation.h",
+            )?;
+            ensure(
+                multiple(value, h),
+                field,
+                "must be a multiple of simulation.h",
+            )
+        }
+        None => Ok(()),
+    }
+}
+
+// Whether `value` is a whole number of `base`, up to rounding
+fn multiple(value: f64, base: f64) -> bool {
+    let ratio = value / base;
+    (ratio - ratio.round()).abs() <= 1e-9 * ratio.max(1.0)
+}
+
+fn wheel_index(field: &str, wheel: usize) -> Result<(), ScenarioError> {
+    ensure(wheel < 3, field, "must be 0, 1 or 2")
+}
+
+fn inertia_tensor(field: &str, rows: [[f64; 3]; 3]) -> Result<(), ScenarioError> {
+    let m = matrix(rows);
+    ensure(
+        m.iter().all(|c| c.is_finite()),
+        field,
+        "must contain finite numbers",
+    )?;
+    ensure(
+        (m - m.transpose()).amax() <= 1e-12 * m.amax(),
+        field,
+        "must be symmetric",
+    )?;
+    ensure(m.cholesky().is_some(), field, "must be positive definite")
+}
+
+fn lqr_weights(
+    field: &str,
+    attitude: &[f64; 3],
+    rate: &[f64; 3],
+    torque: &[f64; 3],
+) -> Result<LqrWeights, ScenarioError> {
+    positive_vector(&format!("{}.attitude", field), attitude)?;
+    positive_vector(&format!("{}.rate", field), rate)?;
+    positive_vector(&format!("{}.torque", field), torque)?;
+    Ok(LqrWeights {
+        attitude: Vector3::from(*attitude),
+        rate: Vector3::from(*rate),
+        torque: Vector3::from(*torque),
+    })
+}
+
+fn transfer_function(
+    field: &str,
+    config: &TransferFunctionConfig,
+) -> Result<TransferFunction, ScenarioError> {
+    // Degree of a polynomial once its leading zeros are dropped (None for the zero polynomial)
+    let degree = |coefficients: &[f64]| {
+        coefficients
+            .iter()
+            .position(|c| *c != 0.0)
+            .map(|first| coefficients.len() - 1 - first)
+    };
+    for (name, coefficients) in [("num", &config.num), ("den", &config.den)] {
+        ensure(
+            coefficients.iter().all(|c| c.is_finite()),
+            &format!("{}.{}", field, name),
+            "must contain finite numbers",
+        )?;
+    }
+    let den_degree = degree(&config.den)
+        .ok_or_else(|| ScenarioError::new(&format!("{}.den", field), "must not be zero"))?;
+    ensure(
+        degree(&config.num).unwrap_or(0) <= den_degree,
+        &format!("{}.num", field),
+        "must not be of higher degree than den",
+    )?;
+    Ok(TransferFunction::new(
+        config.num.clone(),
+        config.den.clone(),
+    ))
+}
+
+fn circular_orbit(field: &str, orbit: &OrbitConfig) -> Result<CircularOrbit, ScenarioError> {
+    positive(&format!("{}.altitude", field), orbit.altitude)?;
+    finite(&format!("{}.inclination", field), orbit.inclination)?;
+    finite(&format!("{}.raan", field), orbit.raan)?;
+    finite(&format!("{}.arg_latitude", field), orbit.arg_latitude)?;
+    Ok(CircularOrbit::new(
+        orbit.altitude,
+        orbit.inclination,
+        orbit.raan,
+        orbit.arg_latitude,
+    ))
+}
+
+fn mode_criteria(field: &str, criteria: &ModeCriteria) -> Result<(), ScenarioError> {
+    let at = |name: &str| format!("{}.{}", field, name);
+    for (name, value) in [
+        ("safe_rate", criteria.safe_rate),
+        ("detumbled_rate", criteria.detumbled_rate),
+        ("sun_acquired_angle", criteria.sun_acquired_angle),
+        ("fine_entry_angle", criteria.fine_entry_angle),
+        ("fine_entry_rate", criteria.fine_entry_rate),
+        ("fine_exit_angle", criteria.fine_exit_angle),
+        ("dump_entry_speed", criteria.dump_entry_speed),
+        ("dump_exit_speed", criteria.dump_exit_speed),
+    ] {
+        positive(&at(name), value)?;
+    }
+    non_negative(&at("dwell_time"), criteria.dwell_time)?;
+    ensure(
+        criteria.detumbled_rate < criteria.safe_rate,
+        &at("detumbled_rate"),
+        "must be below safe_rate",
+    )?;
+    ensure(
+        criteria.fine_entry_angle < criteria.fine_exit_angle,
+        &at("fine_entry_angle"),
+        "must be below fine_exit_angle",
+    )?;
+    ensure(
+        criteria.dump_exit_speed < criteria.dump_entry_speed,
+        &at("dump_exit_speed"),
+        "must be below dump_entry_speed",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT: &str = include_str!("../scenarios/default.toml");
+
+    // Field the error of loading the default TOML scenario with `from` replaced by `to` names
+    fn toml_error(from: &str, to: &str) -> String {
+        assert!(DEFAULT.contains(from));
+        Scenario::from_toml(&DEFAULT.replacen(from, to, 1))
+            .unwrap_err()
+            .field
+    }
+
+    // Field the error of loading the default scenario, as JSON with `requirement` added, names
+    fn json_error(requirement: Value) -> String {
+        let mut scenario: Value = toml::from_str(DEFAULT).unwrap();
+        scenario["requirements"] = Value::Array(vec![requirement]);
+        Scenario::from_json(&scenario.to_string())
+            .unwrap_err()
+            .field
+    }
+
+    #[test]
+    fn toml_errors_name_the_field() {
+        assert_eq!(toml_error("kp = 0.01", "kp = -0.01"), "controller.law.kp");
+        assert_eq!(
+            toml_error("kp = 0.01", "kp = \"high\""),
+            "controller.law.kp"
+        );
+        assert_eq!(toml_error("kp = 0.01", "kpp = 0.01"), "controller.law.kpp");
+        assert_eq!(
+            toml_error("max_speed = 20.0", "max_speed = [20.0]"),
+            "wheels.max_speed"
+        );
+        assert_eq!(
+            toml_error(
+                "initial_speeds = [0.0, 0.0, 0.0]",
+                "initial_speeds = [0.0, 30.0, 0.0]"
+            ),
+            "wheels.initial_speeds[1]"
+        );
+    }
+
+    #[test]
+    fn json_errors_name_the_field() {
+        let requirement = |before: Value| {
+            serde_json::json!({
+                "name": "pointing",
+                "signal": "pointing_error",
+                "max": 0.01,
+                "after": 60.0,
+                "before": before,
+            })
+        };
+        assert_eq!(
+            json_error(requirement(Value::from(30.0))),
+            "requirements[0].before"
+        );
+        assert_eq!(
+            json_error(requirement(Value::from("end"))),
+            "requirements[0].before"
+        );
+    }
+
+    #[test]
+    fn valid_scenario_loads() {
+        assert!(Scenario::from_toml(DEFAULT).is_ok());
+        let scenario: Value = toml::from_str(DEFAULT).unwrap();
+        assert!(Scenario::from_json(&scenario.to_string()).is_ok());
+    }
+}
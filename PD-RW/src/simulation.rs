@@ -0,0 +1,47 @@
+use crate::{
+    discrete_time_model::recorder::recordings,
+    results::Results,
+    scenario::{Scenario, ScenarioError},
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use xdevs::{modeling::Coupled, simulation::*};
+
+/// Runs the scenario to its end, paced by the wall clock at `real_time` simulated seconds per
+/// second when given
+pub fn simulate(scenario: &Scenario, real_time: Option<f64>) -> Result<Results, ScenarioError> {
+    let model = scenario.build("DiscreteTimeModel")?;
+    let total_time = scenario.simulation.total_time;
+    let mut simulator = RootCoordinator::new(model.coupled);
+    match real_time {
+        Some(speed) => simulate_real_time(&mut simulator, total_time, speed),
+        None => simulator.simulate(total_time),
+    }
+
+    let transducer = unsafe { &*model.transducer_ref };
+    Ok(Results::new(
+        transducer,
+        recordings(&model.recorders),
+        total_time,
+        scenario.output.margin_ratio,
+    ))
+}
+
+// The loop of `RootCoordinator::simulate`, waiting for the wall clock before each event. The
+// real-time coordinator of xdevs is not used as it drops the model, and the Transducer with its
+// recordings, when the simulation ends.
+fn simulate_real_time(simulator: &mut RootCoordinator<Coupled>, t_stop: f64, speed: f64) {
+    let start = Instant::now();
+    let mut t_next = simulator.start(0.);
+    while t_next < t_stop {
+        let due = Duration::from_secs_f64(t_next / speed);
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+        simulator.collection(t_next);
+        t_next = simulator.transition(t_next);
+    }
+    simulator.stop(t_next);
+}
@@ -0,0 +1,264 @@
+use crate::{
+    comparison::Response,
+    scenario::{Scenario, ScenarioError},
+    simulation::simulate,
+};
+use clap::ValueEnum;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use rayon::prelude::*;
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+/// Swept parameter: a field path of the scenario, as in `--set`, and its values.
+#[derive(Debug, Clone)]
+pub struct SweepParameter {
+    pub path: String,
+    pub values: SweepValues,
+}
+
+#[derive(Debug, Clone)]
+pub enum SweepValues {
+    // Any scenario values, from PATH=V1,V2,...
+    List(Vec<String>),
+    // From PATH=MIN:MAX:COUNT, COUNT evenly spaced values of a grid. A Latin hypercube draws any
+    // value in between and needs no COUNT.
+    Range {
+        min: f64,
+        max: f64,
+        count: Option<usize>,
+    },
+}
+
+impl FromStr for SweepParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PATH=VALUES, got `{}`", s))?;
+        let bounds: Vec<&str> = values.split(':').collect();
+        let values = match bounds[..] {
+            [min, max] | [min, max, _] => {
+                let number = |text: &str| {
+                    text.trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|value| value.is_finite())
+                        .ok_or_else(|| format!("`{}` is not a finite number", text))
+                };
+                let (min, max) = (number(min)?, number(max)?);
+                if min > max {
+                    return Err(format!("the range {} must have MIN <= MAX", values));
+                }
+                let count = match bounds.get(2) {
+                    Some(count) => match count.trim().parse::<usize>() {
+                        Ok(count) if count > 0 => Some(count),
+                        _ => return Err(format!("`{}` is not a positive count", count)),
+                    },
+                    None => None,
+                };
+                SweepValues::Range { min, max, count }
+            }
+            _ => SweepValues::List(values.split(',').map(|v| v.trim().to_string()).collect()),
+        };
+        Ok(SweepParameter {
+            path: path.trim().to_string(),
+            values,
+        })
+    }
+}
+
+impl SweepParameter {
+    /// Values of the parameter along its grid axis
+    pub fn grid(&self) -> Result<Vec<String>, String> {
+        match &self.values {
+            SweepValues::List(values) => Ok(values.clone()),
+            SweepValues::Range {
+                min,
+                max,
+                count: Some(count),
+            } => Ok((0..*count)
+                .map(|i| match count {
+                    1 => *min,
+                    _ => min + (max - min) * i as f64 / (count - 1) as f64,
+                })
+                .map(|value| value.to_string())
+                .collect()),
+            SweepValues::Range { count: None, .. } => Err(format!(
+                "{}: a grid needs the number of values, PATH=MIN:MAX:COUNT",
+                self.path
+            )),
+        }
+    }
+
+    // Value at `u` (0 to 1) of the range
+    fn at(&self, u: f64) -> Result<String, String> {
+        match self.values {
+            SweepValues::Range { min, max, .. } => Ok((min + (max - min) * u).to_string()),
+            SweepValues::List(_) => Err(format!(
+                "{}: a Latin hypercube needs a range, PATH=MIN:MAX",
+                self.path
+            )),
+        }
+    }
+}
+
+/// Points a sweep simulates.
+#[derive(Debug, Clone, Copy)]
+pub enum Design {
+    // Every combination of the values, the first parameter changing slowest
+    Grid,
+    // `samples` points, each parameter range split into as many strata, each stratum drawn once
+    LatinHypercube { samples: usize, seed: u64 },
+}
+
+impl Design {
+    /// Parameter values of every point, in the order of `parameters`
+    pub fn points(&self, parameters: &[SweepParameter]) -> Result<Vec<Vec<String>>, String> {
+        match *self {
+            Design::Grid => {
+                let mut points = vec![Vec::new()];
+                for parameter in parameters {
+                    let values = parameter.grid()?;
+                    points = points
+                        .into_iter()
+                        .flat_map(|point| {
+                            values.iter().map(move |value| {
+                                let mut point = point.clone();
+                                point.push(value.clone());
+                                point
+                            })
+                        })
+                        .collect();
+                }
+                Ok(points)
+            }
+            Design::LatinHypercube { samples, seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut points = vec![Vec::with_capacity(parameters.len()); samples];
+                for parameter in parameters {
+                    let mut strata: Vec<usize> = (0..samples).collect();
+                    strata.shuffle(&mut rng);
+                    for (point, stratum) in points.iter_mut().zip(strata) {
+                        let u = (stratum as f64 + rng.random::<f64>()) / samples as f64;
+                        point.push(parameter.at(u)?);
+                    }
+                }
+                Ok(points)
+            }
+        }
+    }
+}
+
+/// Metric of a sweep point, plotted in the heat maps.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Metric {
+    FinalError,
+    SettlingTime,
+    PeakWheelSpeed,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 3] = [
+        Metric::FinalError,
+        Metric::SettlingTime,
+        Metric::PeakWheelSpeed,
+    ];
+
+    /// Value of the metric at `point`, infinite for a settling time that is never reached
+    pub fn of(&self, point: &SweepPoint) -> f64 {
+        match self {
+            Metric::FinalError => point.response.final_error(),
+            Metric::SettlingTime => point.response.settling_time().unwrap_or(f64::INFINITY),
+            Metric::PeakWheelSpeed => point.peak_wheel_speed,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::FinalError => "final_error",
+            Metric::SettlingTime => "settling_time",
+            Metric::PeakWheelSpeed => "peak_wheel_speed",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Metric::FinalError => "Final Error [rad]",
+            Metric::SettlingTime => "Settling Time [s]",
+            Metric::PeakWheelSpeed => "Peak Wheel Speed [rad/s]",
+        }
+    }
+}
+
+/// Simulated point of a sweep.
+pub struct SweepPoint {
+    // Values of the parameters, in the order of the sweep
+    pub values: Vec<String>,
+    pub response: Response,
+    pub peak_wheel_speed: f64,
+}
+
+/// Responses of a scenario over the points of a design.
+pub struct Sweep {
+    pub parameters: Vec<SweepParameter>,
+    pub design: Design,
+    pub points: Vec<SweepPoint>,
+}
+
+impl Sweep {
+    /// Simulates the scenario of every point in parallel. `scenarios` holds the values of each
+    /// point with the scenario they give.
+    pub fn run(
+        parameters: Vec<SweepParameter>,
+        design: Design,
+        scenarios: Vec<(Vec<String>, Scenario)>,
+    ) -> Result<Self, ScenarioError> {
+        let points = scenarios
+            .into_par_iter()
+            .map(|(values, scenario)| {
+                let results = simulate(&scenario, None)?;
+                let label = parameters
+                    .iter()
+                    .zip(&values)
+                    .map(|(parameter, value)| format!("{} = {}", parameter.path, value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Ok(SweepPoint {
+                    values,
+                    response: Response::new(label, &results, &scenario),
+                    peak_wheel_speed: results.peak_wheel_speed(),
+                })
+            })
+            .collect::<Result<Vec<_>, ScenarioError>>()?;
+        Ok(Sweep {
+            parameters,
+            design,
+            points,
+        })
+    }
+
+    /// Writes the result table as CSV
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.table(","))
+    }
+
+    fn table(&self, separator: &str) -> String {
+        let mut columns: Vec<&str> = self.parameters.iter().map(|p| p.path.as_str()).collect();
+        columns.extend(Metric::ALL.map(|metric| metric.name()));
+        let mut table = columns.join(separator);
+        for point in &self.points {
+            let mut row = point.values.clone();
+            row.extend(Metric::ALL.map(|metric| metric.of(point).to_string()));
+            table.push('\n');
+            table.push_str(&row.join(separator));
+        }
+        table
+    }
+}
+
+/// Result table, tab-separated
+impl fmt::Display for Sweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.table("\t"))
+    }
+}
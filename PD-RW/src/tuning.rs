@@ -0,0 +1,498 @@
+use crate::{
+    metrics::Metrics,
+    scenario::{Scenario, ScenarioError, ensure, field_mut, finite, non_negative, positive},
+    simulation::simulate,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt, fs, io, path::Path};
+
+// Size of the initial simplex along each parameter, as a fraction of its range
+const INITIAL_STEP: f64 = 0.1;
+// Simplex size, in fractions of the ranges, below which the search has converged
+const MIN_SIMPLEX_SIZE: f64 = 1e-6;
+// Row of the report and the metric it shows
+type Figure = (&'static str, fn(&Metrics) -> f64);
+
+/// Tuning of scenario parameters, from its `[tuning]` table. A Nelder–Mead search moves the
+/// parameters within their bounds to the lowest cost, simulating the whole scenario for every
+/// cost evaluation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TuningConfig {
+    pub parameters: Vec<TunedParameter>,
+    pub cost: CostWeights,
+    #[serde(default)]
+    pub constraints: Constraints,
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+    // The search stops once the costs of the simplex are within this fraction of the best one
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+}
+
+fn default_max_iterations() -> usize {
+    100
+}
+
+fn default_tolerance() -> f64 {
+    1e-3
+}
+
+/// Number of the scenario at a field path, searched between `min` and `max` from its value in
+/// the scenario.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TunedParameter {
+    pub path: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Weight of each figure of `Metrics` in the cost, per second for the settling time, per radian
+/// for the overshoot and per N m s for the effort and the wheel momentum.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CostWeights {
+    #[serde(default)]
+    pub settling_time: f64,
+    #[serde(default)]
+    pub overshoot: f64,
+    #[serde(default)]
+    pub effort: f64,
+    #[serde(default)]
+    pub wheel_momentum: f64,
+}
+
+/// Upper limits of the figures of `Metrics`, in the units of `CostWeights`, rad/s for the wheel
+/// speed and rad for the final error. Each violated limit adds `penalty` times its relative
+/// excess to the cost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Constraints {
+    #[serde(default)]
+    pub max_settling_time: Option<f64>,
+    #[serde(default)]
+    pub max_overshoot: Option<f64>,
+    #[serde(default)]
+    pub max_effort: Option<f64>,
+    #[serde(default)]
+    pub max_wheel_momentum: Option<f64>,
+    #[serde(default)]
+    pub max_wheel_speed: Option<f64>,
+    #[serde(default)]
+    pub max_final_error: Option<f64>,
+    #[serde(default = "default_penalty")]
+    pub penalty: f64,
+}
+
+fn default_penalty() -> f64 {
+    1000.0
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints {
+            max_settling_time: None,
+            max_overshoot: None,
+            max_effort: None,
+            max_wheel_momentum: None,
+            max_wheel_speed: None,
+            max_final_error: None,
+            penalty: default_penalty(),
+        }
+    }
+}
+
+impl TuningConfig {
+    pub(crate) fn validate(&self, field: &str) -> Result<(), ScenarioError> {
+        ensure(
+            !self.parameters.is_empty(),
+            &format!("{}.parameters", field),
+            "must hold at least one parameter",
+        )?;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let field = format!("{}.parameters[{}]", field, i);
+            finite(&format!("{}.min", field), parameter.min)?;
+            finite(&format!("{}.max", field), parameter.max)?;
+            ensure(
+                parameter.min < parameter.max,
+                &format!("{}.max", field),
+                "must be above min",
+            )?;
+        }
+
+        let cost = &self.cost;
+        let weights = [
+            ("settling_time", cost.settling_time),
+            ("overshoot", cost.overshoot),
+            ("effort", cost.effort),
+            ("wheel_momentum", cost.wheel_momentum),
+        ];
+        for (name, weight) in weights {
+            non_negative(&format!("{}.cost.{}", field, name), weight)?;
+        }
+        ensure(
+            weights.iter().any(|(_, weight)| *weight > 0.0),
+            &format!("{}.cost", field),
+            "must weigh at least one figure",
+        )?;
+
+        let constraints = &self.constraints;
+        let limits = [
+            ("max_settling_time", constraints.max_settling_time),
+            ("max_overshoot", constraints.max_overshoot),
+            ("max_effort", constraints.max_effort),
+            ("max_wheel_momentum", constraints.max_wheel_momentum),
+            ("max_wheel_speed", constraints.max_wheel_speed),
+            ("max_final_error", constraints.max_final_error),
+        ];
+        for (name, limit) in limits {
+            if let Some(limit) = limit {
+                positive(&format!("{}.constraints.{}", field, name), limit)?;
+            }
+        }
+        non_negative(
+            &format!("{}.constraints.penalty", field),
+            constraints.penalty,
+        )?;
+        positive(&format!("{}.tolerance", field), self.tolerance)
+    }
+
+    /// Weighted figures plus the penalties of the violated constraints
+    pub fn cost(&self, metrics: &Metrics) -> f64 {
+        let weights = &self.cost;
+        let constraints = &self.constraints;
+        // An error that never settles counts as settling at the end of the run
+        let settling_time = metrics.settling_time_or_duration();
+        let overshoot = metrics.overshoot_deg.to_radians();
+        let cost = weights.settling_time * settling_time
+            + weights.overshoot * overshoot
+            + weights.effort * metrics.effort
+            + weights.wheel_momentum * metrics.peak_wheel_momentum;
+        let excess: f64 = [
+            (constraints.max_settling_time, settling_time),
+            (constraints.max_overshoot, overshoot),
+            (constraints.max_effort, metrics.effort),
+            (constraints.max_wheel_momentum, metrics.peak_wheel_momentum),
+            (constraints.max_wheel_speed, metrics.peak_wheel_speed),
+            (
+                constraints.max_final_error,
+                metrics.final_error_deg.to_radians(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(limit, value)| limit.map(|limit| (value / limit - 1.0).max(0.0)))
+        .sum();
+        cost + constraints.penalty * excess
+    }
+
+    // Scenario with the parameters at `values`
+    fn apply(&self, nominal: &Scenario, values: &[f64]) -> Result<Scenario, ScenarioError> {
+        let mut value =
+            serde_json::to_value(nominal).expect("Unable to convert the scenario to JSON");
+        for (parameter, x) in self.parameters.iter().zip(values) {
+            *field_mut(&mut value, &parameter.path)? = Value::from(*x);
+        }
+        Scenario::from_value(value)
+    }
+
+    // Values of the parameters in `scenario`, moved into their bounds
+    fn initial_values(&self, scenario: &Scenario) -> Result<Vec<f64>, ScenarioError> {
+        let mut value =
+            serde_json::to_value(scenario).expect("Unable to convert the scenario to JSON");
+        let mut values = Vec::with_capacity(self.parameters.len());
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let nominal = field_mut(&mut value, &parameter.path)?
+                .as_f64()
+                .ok_or_else(|| {
+                    ScenarioError::new(
+                        &format!("tuning.parameters[{}].path", i),
+                        format!("`{}` is not a number of the scenario", parameter.path),
+                    )
+                })?;
+            values.push(nominal.clamp(parameter.min, parameter.max));
+        }
+        Ok(values)
+    }
+}
+
+/// Simulated point of the search.
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub values: Vec<f64>,
+    pub cost: f64,
+    pub metrics: Metrics,
+}
+
+/// Step of the Nelder–Mead search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Initial,
+    Reflection,
+    Expansion,
+    Contraction,
+    Shrink,
+}
+
+/// State of the search after an iteration.
+#[derive(Debug, Clone)]
+pub struct Iteration {
+    pub step: Step,
+    // Simulations run so far
+    pub evaluations: usize,
+    pub best: Evaluation,
+}
+
+/// Outcome of a tuning: the convergence history and the tuned scenario.
+pub struct Tuning {
+    pub parameters: Vec<TunedParameter>,
+    pub iterations: Vec<Iteration>,
+    pub initial: Evaluation,
+    pub converged: bool,
+    pub scenario: Scenario,
+}
+
+// Cost evaluations of the search, in the parameter ranges scaled to [0, 1]
+struct Problem<'a> {
+    nominal: &'a Scenario,
+    config: &'a TuningConfig,
+    evaluations: usize,
+}
+
+impl Problem<'_> {
+    fn values(&self, point: &[f64]) -> Vec<f64> {
+        self.config
+            .parameters
+            .iter()
+            .zip(point)
+            .map(|(parameter, u)| parameter.min + (parameter.max - parameter.min) * u)
+            .collect()
+    }
+
+    // Simulates the points in parallel. Each point is first moved into the bounds.
+    fn evaluate(&mut self, points: Vec<Vec<f64>>) -> Result<Vec<Vertex>, ScenarioError> {
+        self.evaluations += points.len();
+        points
+            .into_par_iter()
+            .map(|point| {
+                let point: Vec<f64> = point.iter().map(|u| u.clamp(0.0, 1.0)).collect();
+                let values = self.values(&point);
+                let scenario = self.config.apply(self.nominal, &values)?;
+                let results = simulate(&scenario, None)?;
+                let metrics = Metrics::new(&results, &scenario);
+                Ok(Vertex {
+                    point,
+                    evaluation: Evaluation {
+                        cost: self.config.cost(&metrics),
+                        values,
+                        metrics,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn evaluate_one(&mut self, point: Vec<f64>) -> Result<Vertex, ScenarioError> {
+        Ok(self.evaluate(vec![point])?.remove(0))
+    }
+}
+
+struct Vertex {
+    point: Vec<f64>,
+    evaluation: Evaluation,
+}
+
+impl Vertex {
+    fn cost(&self) -> f64 {
+        self.evaluation.cost
+    }
+}
+
+// a + t (b - a)
+fn along(a: &[f64], b: &[f64], t: f64) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| a + t * (b - a)).collect()
+}
+
+impl Tuning {
+    /// Searches the parameters of `config` for the lowest cost, from their values in `nominal`
+    pub fn run(nominal: &Scenario, config: &TuningConfig) -> Result<Self, ScenarioError> {
+        let mut problem = Problem {
+            nominal,
+            config,
+            evaluations: 0,
+        };
+        let start: Vec<f64> = config
+            .initial_values(nominal)?
+            .iter()
+            .zip(&config.parameters)
+            .map(|(x, parameter)| (x - parameter.min) / (parameter.max - parameter.min))
+            .collect();
+        // The start and one step along each parameter, backwards at the upper bound
+        let mut points = vec![start.clone()];
+        for i in 0..start.len() {
+            let mut point = start.clone();
+            point[i] += match point[i] + INITIAL_STEP <= 1.0 {
+                true => INITIAL_STEP,
+                false => -INITIAL_STEP,
+            };
+            points.push(point);
+        }
+        let mut simplex = problem.evaluate(points)?;
+        let initial = simplex[0].evaluation.clone();
+        simplex.sort_by(|a, b| a.cost().total_cmp(&b.cost()));
+        let mut iterations = vec![Iteration {
+            step: Step::Initial,
+            evaluations: problem.evaluations,
+            best: simplex[0].evaluation.clone(),
+        }];
+
+        let n = start.len();
+        let mut converged = false;
+        while iterations.len() <= config.max_iterations {
+            let best = simplex[0].cost();
+            let worst = simplex[n].cost();
+            let size = simplex
+                .iter()
+                .flat_map(|vertex| {
+                    vertex
+                        .point
+                        .iter()
+                        .zip(&simplex[0].point)
+                        .map(|(u, u_best)| (u - u_best).abs())
+                })
+                .fold(0.0, f64::max);
+            if worst - best <= config.tolerance * best.abs() || size < MIN_SIMPLEX_SIZE {
+                converged = true;
+                break;
+            }
+
+            // Centroid of every vertex but the worst
+            let centroid: Vec<f64> = (0..n)
+                .map(|i| simplex[..n].iter().map(|v| v.point[i]).sum::<f64>() / n as f64)
+                .collect();
+            let reflected = problem.evaluate_one(along(&simplex[n].point, &centroid, 2.0))?;
+            let step = if reflected.cost() < best {
+                let expanded = problem.evaluate_one(along(&simplex[n].point, &centroid, 3.0))?;
+                match expanded.cost() < reflected.cost() {
+                    true => {
+                        simplex[n] = expanded;
+                        Step::Expansion
+                    }
+                    false => {
+                        simplex[n] = reflected;
+                        Step::Reflection
+                    }
+                }
+            } else if reflected.cost() < simplex[n - 1].cost() {
+                simplex[n] = reflected;
+                Step::Reflection
+            } else {
+                // Halfway to the reflected point when it improves on the worst, else halfway
+                // to the worst
+                let (target, bound) = match reflected.cost() < worst {
+                    true => (&reflected.point, reflected.cost()),
+                    false => (&simplex[n].point, worst),
+                };
+                let contracted = problem.evaluate_one(along(&centroid, target, 0.5))?;
+                if contracted.cost() < bound {
+                    simplex[n] = contracted;
+                    Step::Contraction
+                } else {
+                    let points = simplex[1..]
+                        .iter()
+                        .map(|vertex| along(&simplex[0].point, &vertex.point, 0.5))
+                        .collect();
+                    let shrunk = problem.evaluate(points)?;
+                    simplex.truncate(1);
+                    simplex.extend(shrunk);
+                    Step::Shrink
+                }
+            };
+            simplex.sort_by(|a, b| a.cost().total_cmp(&b.cost()));
+            iterations.push(Iteration {
+                step,
+                evaluations: problem.evaluations,
+                best: simplex[0].evaluation.clone(),
+            });
+        }
+
+        let scenario = config.apply(nominal, &simplex[0].evaluation.values)?;
+        Ok(Tuning {
+            parameters: config.parameters.clone(),
+            iterations,
+            initial,
+            converged,
+            scenario,
+        })
+    }
+
+    pub fn best(&self) -> &Evaluation {
+        &self.iterations[self.iterations.len() - 1].best
+    }
+
+    /// Writes the convergence history as CSV, one row per iteration
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        let mut csv = String::from("iteration,step,simulations,cost");
+        for parameter in &self.parameters {
+            csv.push_str(&format!(",{}", parameter.path));
+        }
+        for (i, iteration) in self.iterations.iter().enumerate() {
+            csv.push_str(&format!(
+                "\n{},{:?},{},{}",
+                i, iteration.step, iteration.evaluations, iteration.best.cost
+            ));
+            for value in &iteration.best.values {
+                csv.push_str(&format!(",{}", value));
+            }
+        }
+        csv.push('\n');
+        fs::write(path, csv)
+    }
+}
+
+/// Convergence report: the outcome of the search, then the initial and tuned parameters and
+/// figures.
+impl fmt::Display for Tuning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let simulations = self.iterations.last().map_or(0, |i| i.evaluations);
+        let outcome = match self.converged {
+            true => "converged",
+            false => "stopped at the iteration limit",
+        };
+        writeln!(
+            f,
+            "Tuning {} after {} iterations and {} simulations",
+            outcome,
+            self.iterations.len() - 1,
+            simulations
+        )?;
+        write!(f, "{:<28}{:>14}{:>14}", "", "initial", "tuned")?;
+
+        let (initial, tuned) = (&self.initial, self.best());
+        let mut rows = vec![("cost".to_string(), initial.cost, tuned.cost)];
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            rows.push((parameter.path.clone(), initial.values[i], tuned.values[i]));
+        }
+        let figures: [Figure; 6] = [
+            ("settling time [s]", |m| m.settling_time_or_duration()),
+            ("overshoot [deg]", |m| m.overshoot_deg),
+            ("effort [N m s]", |m| m.effort),
+            ("wheel momentum [N m s]", |m| m.peak_wheel_momentum),
+            ("wheel speed [rad/s]", |m| m.peak_wheel_speed),
+            ("final error [deg]", |m| m.final_error_deg),
+        ];
+        for (name, figure) in figures {
+            rows.push((
+                name.to_string(),
+                figure(&initial.metrics),
+                figure(&tuned.metrics),
+            ));
+        }
+        for (name, initial, tuned) in rows {
+            write!(f, "\n{:<28}{:>14.6e}{:>14.6e}", name, initial, tuned)?;
+        }
+        Ok(())
+    }
+}